@@ -0,0 +1,44 @@
+#ifndef DETERMINE_PRESET_H
+#define DETERMINE_PRESET_H
+
+#include <stdint.h>
+
+/**
+ * The result of `determine_preset_c`. Free it with `determine_preset_free`.
+ */
+typedef struct DeterminePresetResult {
+  /**
+   * The status the command line tool would exit with: 0 for a match, 2 if several presets
+   * match, 3 if none does, 4 if the input has none of the presets' parameters, 7 if the video
+   * is from a hardware encoder, and 1 for any other error.
+   */
+  int32_t status;
+  /**
+   * The matching preset, or NULL if there is no single match.
+   */
+  char *preset;
+  /**
+   * Why there is no single match, or NULL if there is one.
+   */
+  char *error;
+} DeterminePresetResult;
+
+/**
+ * Determines the preset of `mediainfo` output, x265 settings or an encoder command line.
+ *
+ * # Safety
+ *
+ * `settings` must be NULL or point to a NUL-terminated string.
+ */
+struct DeterminePresetResult determine_preset_c(const char *settings);
+
+/**
+ * Frees the strings of a result of `determine_preset_c`.
+ *
+ * # Safety
+ *
+ * `result` must have been returned by `determine_preset_c`, and not freed before.
+ */
+void determine_preset_free(struct DeterminePresetResult result);
+
+#endif  /* DETERMINE_PRESET_H */
@@ -0,0 +1,86 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use crate::presets::Preset;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting the allocations for `bench` while `count_allocations` is on.
+/// The determine-preset binary only installs it as the global allocator with the
+/// `count-allocations` feature; otherwise, and in other programs, `allocations` stays at zero.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.load(Ordering::Relaxed) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.load(Ordering::Relaxed) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        }
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if COUNTING.load(Ordering::Relaxed) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            ALLOCATED_BYTES.fetch_add(new_size.saturating_sub(layout.size()) as u64, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Starts or stops counting allocations, around the work that `bench` measures.
+pub fn count_allocations(counting: bool) {
+    COUNTING.store(counting, Ordering::Relaxed);
+}
+
+/// The number of allocations counted so far, and the bytes allocated, counting the growth of
+/// reallocations. Zero unless `CountingAllocator` is the global allocator.
+pub fn allocations() -> (u64, u64) {
+    (ALLOCATIONS.load(Ordering::Relaxed), ALLOCATED_BYTES.load(Ordering::Relaxed))
+}
+
+/// `count` inputs in the form of `mediainfo` output, with the settings of the presets in turn.
+/// Every third input has one parameter changed, so that the corpus also has inputs that don't
+/// match.
+pub fn synthetic_corpus(presets: &[Preset], count: usize) -> Vec<String> {
+    (0..count)
+        .map(|index| {
+            let (_, preset_settings) = &presets[index % presets.len()];
+            let mut settings = preset_settings.iter().collect::<Vec<_>>();
+            settings.sort();
+            let changed = (index % 3 == 2).then(|| index / 3 % settings.len().max(1));
+            let settings = settings.iter().enumerate()
+                .map(|(param, (param_name, value))| {
+                    let suffix = if changed == Some(param) { "1" } else { "" };
+                    format!("{}={}{}", param_name, value, suffix)
+                })
+                .collect::<Vec<_>>();
+            format!("General\nComplete name : synthetic-{}.mkv\n\nVideo\nWriting library : x265 3.5\nEncoding settings : {}\n", index, settings.join(" / "))
+        })
+        .collect()
+}
+
+#[test]
+fn test_bench() {
+    let presets = crate::presets::parse("fast ctu=32 bframes=4\nslow ctu=64 bframes=8\n").unwrap();
+    let corpus = synthetic_corpus(&presets, 3);
+    assert_eq!(corpus.len(), 3);
+    assert!(corpus[0].ends_with("Encoding settings : bframes=4 / ctu=32\n"));
+    assert!(corpus[2].ends_with("Encoding settings : bframes=41 / ctu=32\n"));
+}
@@ -0,0 +1,80 @@
+use std::{
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+use crate::{exit_code, i18n::Lang, matcher, presets};
+
+/// The result of `determine_preset_c`. Free it with `determine_preset_free`.
+#[repr(C)]
+pub struct DeterminePresetResult {
+    /// The status the command line tool would exit with: 0 for a match, 2 if several presets
+    /// match, 3 if none does, 4 if the input has none of the presets' parameters, 7 if the video
+    /// is from a hardware encoder, and 1 for any other error.
+    pub status: i32,
+    /// The matching preset, or NULL if there is no single match.
+    pub preset: *mut c_char,
+    /// Why there is no single match, or NULL if there is one.
+    pub error: *mut c_char,
+}
+
+/// Determines the preset of `mediainfo` output, x265 settings or an encoder command line.
+///
+/// # Safety
+///
+/// `settings` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn determine_preset_c(settings: *const c_char) -> DeterminePresetResult {
+    if settings.is_null() {
+        return result(exit_code::ERROR, Err(Lang::En.tr("The settings are NULL").to_string()));
+    }
+    let Ok(input) = CStr::from_ptr(settings).to_str() else {
+        return result(exit_code::ERROR, Err(Lang::En.tr("The settings aren't valid UTF-8").to_string()));
+    };
+    let presets = presets::x265();
+    match matcher::normalized_settings(input, &presets) {
+        Ok(settings) => {
+            let determination = matcher::determination(&settings, &presets).for_input(input);
+            result(determination.exit_code(), matcher::preset_or_reason(determination, &settings, &presets, Lang::En))
+        }
+        Err(error_message) => result(exit_code::ERROR, Err(error_message)),
+    }
+}
+
+fn result(status: i32, preset: Result<String, String>) -> DeterminePresetResult {
+    // the messages come from Rust strings, which can't contain NUL unless the input did
+    let c_string = |text: String| CString::new(text.replace('\0', "")).expect("NUL characters were removed").into_raw();
+    match preset {
+        Ok(preset_name) => DeterminePresetResult { status, preset: c_string(preset_name), error: ptr::null_mut() },
+        Err(error_message) => DeterminePresetResult { status, preset: ptr::null_mut(), error: c_string(error_message) },
+    }
+}
+
+/// Frees the strings of a result of `determine_preset_c`.
+///
+/// # Safety
+///
+/// `result` must have been returned by `determine_preset_c`, and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn determine_preset_free(result: DeterminePresetResult) {
+    for text in [result.preset, result.error] {
+        if !text.is_null() {
+            drop(CString::from_raw(text));
+        }
+    }
+}
+
+#[test]
+fn test_determine_preset_c() {
+    let settings = CString::new("ctu=64 bframes=4 rdLevel=4 lookahead-slices=4").unwrap();
+    let result = unsafe { determine_preset_c(settings.as_ptr()) };
+    assert_eq!(result.status, exit_code::SUCCESS);
+    assert_eq!(unsafe { CStr::from_ptr(result.preset) }.to_str(), Ok("slow"));
+    assert!(result.error.is_null());
+    unsafe { determine_preset_free(result) };
+
+    let result = unsafe { determine_preset_c(ptr::null()) };
+    assert_eq!(result.status, exit_code::ERROR);
+    assert!(result.preset.is_null());
+    unsafe { determine_preset_free(result) };
+}
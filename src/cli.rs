@@ -0,0 +1,2609 @@
+use std::{collections::HashMap, path::Path, process::exit, sync::{atomic::{AtomicBool, Ordering}, Arc}};
+
+use clap::{ArgAction, Parser, Subcommand};
+use indexmap::IndexMap;
+use tracing::{debug, error, info, info_span, warn};
+
+use crate::{
+    bench, encode_log, encoder_name, encoding, exit_code, filter, handbrake, i18n::Lang, input, intern, logging::LogLevel, matcher, media_properties, params, policy, presets, rename, resolve, schema, selftest,
+    table::{Cell, Highlight, Table, TableStyle},
+    wizard,
+    Determination, Difference,
+};
+#[cfg(unix)]
+use crate::{daemon, metrics};
+#[cfg(feature = "parquet")]
+use {crate::parquet, std::{io::Write, sync::Mutex}};
+#[cfg(feature = "self-update")]
+use crate::update;
+#[cfg(test)]
+use crate::{parse_settings, parse_string};
+/// The matching strategies of `--matcher` (see `matcher::Matcher`).
+#[derive(Default, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum MatcherKind {
+    /// Every parameter that both the input and the preset have must be the same.
+    #[default]
+    Exact,
+    /// Like `exact`, and the input must have every parameter that the preset defines.
+    Strict,
+    /// The presets with the fewest differing parameters, if at most `--tolerance` differ.
+    Tolerant,
+    /// The presets whose matching parameters weigh the most, if they are at least 90% of the
+    /// weight. Parameters that tell more presets apart weigh more.
+    Weighted,
+}
+
+impl MatcherKind {
+    fn matcher(self, tolerance: usize) -> Arc<dyn matcher::Matcher> {
+        match self {
+            MatcherKind::Exact => Arc::new(matcher::Exact),
+            MatcherKind::Strict => Arc::new(matcher::Strict),
+            MatcherKind::Tolerant => Arc::new(matcher::Tolerant { max_differences: tolerance }),
+            MatcherKind::Weighted => Arc::new(matcher::Weighted { min_score: 0.9 }),
+        }
+    }
+}
+
+/// The orders of the parameters in tables (see `--param-order`).
+#[derive(Default, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ParamOrder {
+    /// The order of the input's settings, followed by the parameters it doesn't have in the
+    /// `table` order.
+    #[default]
+    Input,
+    /// The order of the parameters in the preset table, followed by the others by name.
+    Table,
+    /// By name.
+    Name,
+}
+
+#[derive(Default, Clone, Debug, clap::ValueEnum)]
+pub(crate) enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Parser, Default)]
+/// Read x265 encoding flags (for example from the output of `mediainfo`),
+/// and print which preset the video was encoded with.
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Without a subcommand, the arguments of `determine`.
+    #[command(flatten)]
+    pub determine: DetermineArgs,
+
+    /// In the case of no match, colors are used to show close matches
+    /// in verbose mode. `auto` respects the NO_COLOR, CLICOLOR and CLICOLOR_FORCE
+    /// environment variables.
+    #[arg(short, long, global = true, num_args(0..=1), default_value = "auto", default_missing_value = "auto", env = "DETERMINE_PRESET_COLOR")]
+    pub(crate) color: ColorMode,
+
+    /// Lay out the output for this many columns instead of the terminal width. When omitted and
+    /// the output is not a terminal, the width is not limited.
+    #[arg(long, global = true, env = "DETERMINE_PRESET_WIDTH")]
+    pub(crate) width: Option<usize>,
+
+    /// Make the parameter names in the verbose table links to the x265 documentation, in
+    /// terminals that support OSC 8 hyperlinks.
+    #[arg(long, global = true, num_args(0..=1), default_value = "auto", default_missing_value = "always", env = "DETERMINE_PRESET_HYPERLINKS")]
+    pub(crate) hyperlinks: ColorMode,
+
+    /// Match against the presets in this file instead of the built-in x265 presets. Each line
+    /// is a preset, from the fastest to the slowest, like `slow ctu=64 bframes=4 ...`.
+    #[arg(long, global = true, env = "DETERMINE_PRESET_PRESETS_FILE")]
+    pub(crate) presets_file: Option<String>,
+
+    /// The style of the tables printed in verbose mode and by `explain`.
+    #[arg(long, global = true, value_enum, default_value = "ascii", env = "DETERMINE_PRESET_TABLE_STYLE")]
+    pub(crate) table_style: TableStyle,
+
+    /// The order of the parameters in the verbose table and the settings printed with `-vvv`:
+    /// that of the input's settings, the preset table's, or by name. It's the same between
+    /// runs, so the output can be compared.
+    #[arg(long, global = true, value_enum, default_value = "input", env = "DETERMINE_PRESET_PARAM_ORDER")]
+    pub(crate) param_order: ParamOrder,
+
+    /// Print nothing on STDERR: no errors, warnings or notes. Failures are only reported by the
+    /// exit status.
+    #[arg(short, long, global = true, env = "DETERMINE_PRESET_QUIET")]
+    quiet: bool,
+
+    /// How much to print on STDERR. `debug` and `trace` add what determine-preset is doing,
+    /// like running `mediainfo`, for diagnosing batch runs.
+    #[arg(long, global = true, value_enum, default_value = "info", env = "DETERMINE_PRESET_LOG_LEVEL")]
+    log_level: LogLevel,
+
+    /// Print presets as their position in the preset order instead of their name: 0 for
+    /// ultrafast to 9 for placebo, like x265's numeric `--preset` values.
+    #[arg(long, global = true, env = "DETERMINE_PRESET_NUMERIC")]
+    numeric: bool,
+
+    /// Never run external programs, like `mediainfo` to read videos, for locked-down
+    /// environments. Inputs that need them fail with exit status 8 (`exec_forbidden` in the
+    /// JSON output) instead; settings dumps and command lines are read as usual.
+    #[arg(long, global = true, env = "DETERMINE_PRESET_NO_EXEC")]
+    pub no_exec: bool,
+
+    /// The `mediainfo` binary that videos are read with, instead of the one in the `PATH`.
+    #[arg(long, global = true, value_name = "PATH", env = "DETERMINE_PRESET_MEDIAINFO")]
+    pub mediainfo: Option<String>,
+
+    /// An extra argument to pass `mediainfo`, before the path of the video. Repeat it for
+    /// several, like `--mediainfo-arg=--ParseSpeed=0`.
+    #[arg(long, global = true, value_name = "ARG", allow_hyphen_values = true)]
+    pub mediainfo_arg: Vec<String>,
+
+    /// Leave these parameters out of the input's settings, e.g. `--ignore crf,aq-mode`.
+    #[arg(long, global = true, value_delimiter = ',', value_name = "PARAMS", env = "DETERMINE_PRESET_IGNORE")]
+    pub(crate) ignore: Vec<String>,
+
+    /// How to decide which presets the settings match.
+    #[arg(long, global = true, value_enum, default_value = "exact", env = "DETERMINE_PRESET_MATCHER")]
+    pub(crate) matcher: MatcherKind,
+
+    /// With `--matcher tolerant`, how many parameters can differ from a preset.
+    #[arg(long, global = true, default_value_t = 2, value_name = "PARAMS")]
+    pub(crate) tolerance: usize,
+
+    /// The language of the messages and table headers. Defaults to the locale's language, from
+    /// `LC_ALL`, `LC_MESSAGES` or `LANG`.
+    #[arg(long, global = true, value_enum)]
+    pub lang: Option<Lang>,
+
+    /// Print the JSON Schema of the `--format json` output and exit.
+    #[arg(long)]
+    pub schema: bool,
+}
+
+impl Cli {
+    /// Whether `determine` prints its result as JSON, and so errors should be too.
+    pub fn json_output(&self) -> bool {
+        self.determine.format == OutputFormat::Json
+    }
+
+    /// The level of the messages to print on STDERR.
+    pub fn log_level(&self) -> LogLevel {
+        if self.quiet { LogLevel::Off } else { self.log_level }
+    }
+}
+
+/// The arguments for determining the preset of one input.
+#[derive(clap::Args, Default)]
+pub struct DetermineArgs {
+    /// Path to read the encoding flags from. If omitted or `-`, read from STDIN. STDIN must be `mediainfo` output, not a video file.
+    /// If it is omitted and STDIN is a terminal, this help is printed instead.
+    /// An x265 or ffmpeg command line (in a file, on STDIN or as this argument) is also accepted.
+    pub input: Option<String>,
+
+    /// In the case of no match, print detailed output about the close matches. -vv gives
+    /// more detailed output, including parameters that are missing from the input (shown as `·`)
+    /// or not defined by the presets (shown as `n/a`). -vvv describes each parameter, and prints
+    /// the normalized settings that are matched on STDERR.
+    #[arg(short, long, action = ArgAction::Count)]
+    pub(crate) verbose: u8,
+
+    /// Instead of the preset, print an ffmpeg command that would reproduce the input's settings:
+    /// the closest preset plus the parameters that differ from it, and the other x265 options
+    /// that aren't at their defaults, like `psy-rd` and `keyint`.
+    #[arg(long)]
+    ffmpeg_command: bool,
+
+    /// Instead of the preset, print only the `-x265-params` value that turns the closest preset
+    /// into the input's settings. The preset it applies to is printed on STDERR.
+    #[arg(long, conflicts_with = "ffmpeg_command")]
+    delta: bool,
+
+    /// The x265 encode log of the input video (x265's or ffmpeg's console output). The settings
+    /// the log reports are compared with the settings in the video, and any that differ are
+    /// reported, to catch remuxed or mislabeled files.
+    #[arg(long, value_name = "FILE")]
+    log: Option<String>,
+
+    #[command(flatten)]
+    gate: GateArgs,
+
+    /// How to print the result.
+    #[arg(long, value_enum, default_value = "text", env = "DETERMINE_PRESET_FORMAT", conflicts_with_all = ["ffmpeg_command", "delta"])]
+    pub(crate) format: OutputFormat,
+
+    /// Exit with the preset's position in the preset order as the status (0 for ultrafast to 9
+    /// for placebo), for use in Makefiles and shell conditionals. The other outcomes exit with 100
+    /// plus their usual status, e.g. 102 if several presets match.
+    #[arg(long, conflicts_with_all = ["ffmpeg_command", "delta", "require", "filter"])]
+    exit_preset_code: bool,
+
+    /// Don't offer to walk through the parameters of an ambiguous result or a near miss. The
+    /// offer is only made on a terminal, for a path given as the argument.
+    #[arg(long, env = "DETERMINE_PRESET_NO_INTERACTIVE")]
+    no_interactive: bool,
+}
+
+impl DetermineArgs {
+    /// The status to exit with for an outcome, which is changed by `--exit-preset-code`.
+    pub fn exit_status(&self, exit_code: i32) -> i32 {
+        if self.exit_preset_code && exit_code != exit_code::SUCCESS {
+            exit_code::PRESET_CODE_OFFSET + exit_code
+        } else {
+            exit_code
+        }
+    }
+}
+
+/// Conditions that an input must meet to be accepted. They are also read from the tables of a
+/// policy file (see `policy::Policy`).
+#[derive(clap::Args, serde::Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GateArgs {
+    /// Accept only presets at least as slow as this one, e.g. `--require slow` rejects anything
+    /// encoded with `medium` or faster. If several presets match, all of them must be.
+    #[arg(long, value_name = "PRESET")]
+    pub require: Option<String>,
+
+    /// Accept only presets at most as slow as this one, e.g. `--at-most slower` rejects
+    /// `veryslow` and `placebo`. With `--require`, the preset must be in the range between
+    /// them, like `--require medium --at-most veryslow`.
+    #[arg(long, value_name = "PRESET")]
+    pub at_most: Option<String>,
+
+    /// Accept only inputs that match this condition, like
+    /// `preset >= slow && crf <= 20 && bit_depth == 10`. Names are `preset`, the encoder settings
+    /// and the video's `bit_depth`, `width` and `height`. Combine comparisons with `&&`, `||`, `!`
+    /// and parentheses.
+    #[arg(long, value_name = "EXPRESSION")]
+    pub filter: Option<String>,
+}
+
+impl GateArgs {
+    fn is_empty(&self) -> bool {
+        self.require.is_none() && self.at_most.is_none() && self.filter.is_none()
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print the preset an input was encoded with. This is the default when no subcommand is
+    /// given.
+    Determine(DetermineArgs),
+    /// Print the preset of each video in some files or directories, which are searched
+    /// recursively.
+    Scan {
+        /// The videos or settings dumps, or directories to search for videos. `-` reads a
+        /// settings dump from STDIN.
+        #[arg(required_unless_present = "files_from")]
+        paths: Vec<String>,
+
+        /// Also read the paths from FILE, one per line, or from STDIN if FILE is `-`.
+        #[arg(long, value_name = "FILE")]
+        files_from: Option<String>,
+
+        /// The number of files to read in parallel.
+        #[arg(short, long, default_value_t = 1, env = "DETERMINE_PRESET_JOBS")]
+        jobs: usize,
+
+        /// List only the videos that meet the conditions.
+        #[command(flatten)]
+        gate: GateArgs,
+
+        /// Print only the paths of the videos, e.g. to pass the videos that meet `--filter` to
+        /// `xargs`.
+        #[arg(short = 'l', long)]
+        paths_only: bool,
+
+        /// End each path with a NUL character instead of a newline, for `xargs -0`.
+        #[arg(short = '0', long, requires = "paths_only")]
+        null: bool,
+
+        /// How to print the results.
+        #[arg(long, value_enum, default_value = "text", conflicts_with = "paths_only")]
+        format: ScanFormat,
+
+        /// Check the videos against the conditions for their directories in a TOML policy
+        /// file, like `require = "slow"` under `["Movies/4K"]`, and list only those that
+        /// violate them, with the reason. Exits with status 6 if any do.
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["require", "at_most", "filter", "format"])]
+        policy: Option<String>,
+
+        /// Rename each video whose preset is determined after a template, like
+        /// `"{stem} [x265 {preset}]{ext}"`, in its directory. The placeholders are `stem`, `ext`
+        /// (with the dot), `preset`, `encoder`, `bit_depth`, `width` and `height`.
+        #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["paths_only", "format", "policy"])]
+        rename_template: Option<String>,
+
+        /// With `--rename-template`, list the renames without making them.
+        #[arg(long, requires = "rename_template")]
+        dry_run: bool,
+
+        /// After the list, print the videos that need a human's attention, grouped by why:
+        /// those that couldn't be read or have no settings, that match no preset or several, and
+        /// those that match on fewer than half of their preset's parameters.
+        #[arg(long, conflicts_with_all = ["paths_only", "format", "policy", "rename_template"])]
+        triage: bool,
+
+        /// Write the videos that need attention, as `--triage` lists them, to FILE as a JSON
+        /// array of objects with their `path`, `cause` and `detail`.
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["paths_only", "format", "policy", "rename_template"])]
+        triage_json: Option<String>,
+    },
+    /// Print the parameters of a preset, as they are matched against.
+    Explain {
+        /// The name of the preset, e.g. `slow`.
+        preset: String,
+
+        /// How to print the parameters.
+        #[arg(long, value_enum, default_value = "table")]
+        format: ExplainFormat,
+    },
+    /// List the known presets from the fastest to the slowest, and check the preset table for
+    /// problems. Use with `--presets-file` to validate a presets file.
+    ListPresets,
+    /// Print the parameters of a preset as encoder options, to reproduce it exactly even if the
+    /// encoder's definition of the preset changes.
+    ExportPreset {
+        /// The name of the preset, e.g. `slow`.
+        preset: String,
+
+        /// The kind of options to print.
+        #[arg(long, value_enum, default_value = "cli")]
+        format: ExportFormat,
+    },
+    /// Work out the settings an x265 command line encodes with, by applying the preset, the tune and
+    /// the explicit options the way x265 does. The output can be piped back into determine-preset.
+    Resolve {
+        /// The command line, e.g. `x265 --preset slow --tune grain --rc-lookahead 30`, either as
+        /// separate arguments (after `--`) or as one quoted string.
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Compare the encoder settings of two inputs, showing each input's preset and every
+    /// parameter that differs.
+    Diff {
+        /// The first video, settings dump or command line (`-` for STDIN).
+        first: String,
+        /// The second video, settings dump or command line (`-` for STDIN).
+        second: String,
+    },
+    /// Check that the chunks of a segmented encode (e.g. from Av1an) all used the same settings,
+    /// and report the chunks that differ from the rest.
+    Chunks {
+        /// The chunks: videos, settings dumps, or directories of them. `-` reads a settings dump
+        /// from STDIN.
+        #[arg(required_unless_present = "files_from")]
+        inputs: Vec<String>,
+
+        /// Also read the chunks from FILE, one per line, or from STDIN if FILE is `-`.
+        #[arg(long, value_name = "FILE")]
+        files_from: Option<String>,
+    },
+    /// Run as a daemon that determines presets on request, for media managers' custom scripts.
+    /// Each request is a line with the path of a video or settings dump, sent to the Unix
+    /// socket, and is answered with a line with the JSON object of `--format json`.
+    Daemon {
+        /// The path of the Unix socket to listen on.
+        #[arg(long, value_name = "PATH", default_value = "/tmp/determine-preset.sock")]
+        socket: String,
+
+        /// Also serve Prometheus metrics at `http://ADDRESS/metrics`, e.g. `127.0.0.1:9185`:
+        /// the number of determinations by preset, errors, the time spent on requests, the cache
+        /// hit rate and the scan throughput.
+        #[arg(long, value_name = "ADDRESS")]
+        metrics: Option<String>,
+    },
+    /// Check the matcher against a corpus of samples, to validate a presets file or changes to
+    /// the normalization. Each file in the directory is a sample: a header line like
+    /// `expected: slow` (or a status like `ambiguous` or `no-match`), then the input.
+    Selftest {
+        /// The directory of samples.
+        dir: String,
+    },
+    /// Replace this binary with the latest release from GitHub, for installs outside cargo, and
+    /// download the release's preset table. Needs the `self-update` feature.
+    SelfUpdate {
+        /// Only print whether a newer release is available.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Measure how fast inputs are matched, to make performance regressions in the parser and
+    /// the matcher visible: the determinations per second, and the allocations per
+    /// determination with the `count-allocations` feature.
+    Bench {
+        /// A saved inventory to match: videos, settings dumps or concatenated `mediainfo`
+        /// exports, or directories of them. They are read before the timing starts. Without
+        /// them, a synthetic corpus of `mediainfo` output with the presets' settings is matched.
+        paths: Vec<String>,
+
+        /// The number of inputs in the synthetic corpus.
+        #[arg(long, default_value_t = 10000)]
+        count: usize,
+
+        /// How many times to match the corpus.
+        #[arg(long, default_value_t = 3)]
+        rounds: usize,
+    },
+    /// Describe an encoder parameter and list its value in each preset.
+    ExplainParam {
+        /// The parameter name, as in the preset table (`rdLevel`) or on the x265 command line (`rd`).
+        name: String,
+    },
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// The preset's name, or an error explaining why there is no single match.
+    #[default]
+    Text,
+    /// Shell variable assignments like `PRESET=slow CONFIDENCE=100 ENCODER=x265`, for
+    /// `eval "$(determine-preset video.mkv)"`. They are printed even if there is no single match:
+    /// `STATUS` is `match`, `ambiguous`, `no-match`, `insufficient-input` or `not-derivable`,
+    /// `PRESET` is the match or the closest preset, `CONFIDENCE` is the percentage of parameters
+    /// that match it, and `MATCHES` lists the matching presets.
+    Shell,
+    /// A JSON object with the same fields in lowercase. If there is no single match, or
+    /// determining the preset fails, it has an `error` object with a `code` like `no_match` or
+    /// `io_error` and a `message`, and nothing is printed on STDERR.
+    Json,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ScanFormat {
+    /// A line per video, like `movie.mkv: slow`.
+    #[default]
+    Text,
+    /// A Parquet file, to be redirected to a file and loaded into DuckDB or pandas, with a row
+    /// per video and the columns in `SCAN_COLUMNS`. Needs the `parquet` feature.
+    Parquet,
+}
+
+/// The columns of `scan --format parquet`. `status` is as in `--format json`, or `error` if the
+/// video couldn't be read, and `matches` lists the matching presets, separated by commas.
+#[cfg(feature = "parquet")]
+const SCAN_COLUMNS: &[&str] = &["path", "status", "preset", "matches", "closest", "dolby_vision", "error"];
+
+#[derive(Default, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// x265 command line flags, e.g. `--ctu 64 --ref 4 --no-rect`.
+    #[default]
+    Cli,
+    /// The `key=value:key=value` form used by ffmpeg's `-x265-params` option.
+    X265Params,
+}
+
+#[derive(Default, Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ExplainFormat {
+    /// A table of parameter names and values.
+    #[default]
+    Table,
+    /// x265 command line flags, e.g. `--ctu 64 --no-rect`.
+    Flags,
+    /// A JSON object.
+    Json,
+}
+
+/// The result of determining the preset, for the output formats meant for scripts.
+struct Summary {
+    determination: Determination,
+    /// The matching preset, or the closest one if none match.
+    preset: Option<String>,
+    /// The percentage of the input's parameters that match `preset`.
+    confidence: usize,
+    encoder: String,
+    matches: Vec<String>,
+    /// The HandBrake preset family, if HandBrake encoded the input (see `handbrake`).
+    handbrake_preset: Option<&'static str>,
+    /// The presets that the container's tags name, as `(tag, preset)`, which are unverified.
+    tag_hints: Vec<(String, String)>,
+    details: encoding::Details,
+}
+
+/// The comparison of an input with its closest presets (see `Determiner::comparison`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comparison {
+    /// The presets compared, from the closest, with the percentage of the input's parameters
+    /// that they match.
+    pub presets: Vec<(String, usize)>,
+    pub rows: Vec<ComparisonRow>,
+}
+
+/// A parameter in a `Comparison`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComparisonRow {
+    pub param_name: String,
+    /// The input's value, or `None` if the input doesn't have the parameter.
+    pub input_value: Option<String>,
+    /// The value of each of the presets, or `None` if it doesn't define the parameter, and
+    /// whether it's the input's value.
+    pub preset_values: Vec<(Option<String>, bool)>,
+}
+
+impl Comparison {
+    /// For each preset, the number of the parameters it has the input's value for, and the
+    /// number it has another value for.
+    pub fn counts(&self) -> Vec<(usize, usize)> {
+        (0..self.presets.len())
+            .map(|preset| {
+                let values = self.rows.iter().filter(|row| row.input_value.is_some()).map(|row| &row.preset_values[preset]);
+                let matched = values.clone().filter(|(_, is_match)| *is_match).count();
+                let differed = values.filter(|(value, is_match)| value.is_some() && !is_match).count();
+                (matched, differed)
+            })
+            .collect()
+    }
+}
+
+/// A video that `scan --triage` lists for review, with why.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TriageEntry {
+    pub path: String,
+    pub cause: TriageCause,
+    /// Like the matching presets, the closest preset or the error.
+    pub detail: String,
+}
+
+/// Why a scanned video needs review, in the order `scan --triage` lists them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TriageCause {
+    /// The video or its settings couldn't be read.
+    FailedExtraction,
+    /// The video has no encoder settings, or was encoded by a hardware encoder.
+    NoSettings,
+    NoMatch,
+    Ambiguous,
+    /// A single preset matches, but the input has fewer than half of its parameters.
+    LowConfidence,
+}
+
+impl TriageCause {
+    /// The name in `--triage-json`.
+    pub fn key(self) -> &'static str {
+        match self {
+            TriageCause::FailedExtraction => "failed_extraction",
+            TriageCause::NoSettings => "no_settings",
+            TriageCause::NoMatch => "no_match",
+            TriageCause::Ambiguous => "ambiguous",
+            TriageCause::LowConfidence => "low_confidence",
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            TriageCause::FailedExtraction => "failed extraction",
+            TriageCause::NoSettings => "no settings",
+            TriageCause::NoMatch => "no match",
+            TriageCause::Ambiguous => "ambiguous",
+            TriageCause::LowConfidence => "low confidence",
+        }
+    }
+}
+
+/// The section that `scan --triage` ends with: the videos that need review, grouped by cause,
+/// out of the `scanned` ones.
+fn triage_report(entries: &[TriageEntry], scanned: usize) -> String {
+    if entries.is_empty() {
+        return format!("\nNeeds review: none of {} videos\n", scanned);
+    }
+    let mut report = format!("\nNeeds review: {} of {} videos\n", entries.len(), scanned);
+    for group in entries.chunk_by(|first, second| first.cause == second.cause) {
+        report.push_str(&format!("  {} ({}):\n", group[0].cause.heading(), group.len()));
+        for entry in group {
+            report.push_str(&format!("    {}: {}\n", entry.path, entry.detail));
+        }
+    }
+    report
+}
+
+/// Set with `--format json`, to report errors as JSON on STDOUT.
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Makes `fail` print errors as JSON objects on STDOUT, for `--format json`.
+pub fn set_json_errors(json_errors: bool) {
+    JSON_ERRORS.store(json_errors, Ordering::Relaxed);
+}
+
+/// The machine-readable code for an exit status, in the JSON output and the metrics.
+fn error_code(exit_code: i32) -> &'static str {
+    match exit_code % exit_code::PRESET_CODE_OFFSET {
+        exit_code::AMBIGUOUS => "ambiguous",
+        exit_code::NO_MATCH => "no_match",
+        exit_code::INSUFFICIENT_INPUT => "insufficient_input",
+        exit_code::NOT_DERIVABLE => "not_derivable",
+        exit_code::IO_ERROR => "io_error",
+        exit_code::REJECTED => "rejected",
+        exit_code::EXEC_FORBIDDEN => "exec_forbidden",
+        _ => "error",
+    }
+}
+
+/// The `error` object of the JSON output, with a machine-readable code for the exit status.
+fn json_error(exit_code: i32, message: &str) -> serde_json::Value {
+    serde_json::json!({ "code": error_code(exit_code), "message": message })
+}
+
+/// The JSON object reporting an error that prevented determining the preset.
+fn json_failure(exit_code: i32, message: &str) -> String {
+    serde_json::json!({ "schema_version": schema::SCHEMA_VERSION, "status": "error", "error": json_error(exit_code, message) }).to_string()
+}
+
+/// Reports an error and exits with the given status.
+pub fn fail(error_message: &str, exit_code: i32) -> ! {
+    if JSON_ERRORS.load(Ordering::Relaxed) {
+        println!("{}", json_failure(exit_code, error_message));
+    } else {
+        error!("{}", error_message);
+    }
+    exit(exit_code)
+}
+
+/// Shown in the verbose table for a parameter that is not in the input.
+const MISSING_IN_INPUT: &str = "·";
+/// Shown in the verbose table for a parameter that the preset does not define.
+const NOT_IN_PRESET: &str = "n/a";
+
+pub struct Determiner {
+    cli: Cli,
+    presets: Arc<[presets::Preset]>,
+    index: Arc<matcher::PresetIndex>,
+    matcher: Arc<dyn matcher::Matcher>,
+}
+
+impl Default for Determiner {
+    fn default() -> Self {
+        Determiner::new(Cli::default())
+    }
+}
+
+impl Determiner {
+    pub fn new(cli: Cli) -> Determiner {
+        let (presets, index) = match &cli.presets_file {
+            Some(path) => {
+                let presets: Arc<[presets::Preset]> = presets::load(path)
+                    .unwrap_or_else(|error_message| fail(&error_message, cli.determine.exit_status(exit_code::ERROR)))
+                    .into();
+                let index = Arc::new(matcher::PresetIndex::new(&presets));
+                (presets, index)
+            }
+            // the built-in tables are parsed once, and shared by all determiners
+            None => (presets::x265(), matcher::PresetIndex::x265()),
+        };
+        let matcher = cli.matcher.matcher(cli.tolerance);
+        Determiner { cli, presets, index, matcher }
+    }
+
+    /// The determiner with another matching strategy than `--matcher`'s.
+    pub fn with_matcher(self, matcher: impl matcher::Matcher + 'static) -> Determiner {
+        Determiner { matcher: Arc::new(matcher), ..self }
+    }
+
+    /// A determiner with other options that shares this one's preset tables, instead of loading
+    /// and indexing them again. `presets_file` in `cli` is ignored.
+    pub fn with_options(&self, cli: Cli) -> Determiner {
+        let matcher = cli.matcher.matcher(cli.tolerance);
+        Determiner { cli, presets: Arc::clone(&self.presets), index: Arc::clone(&self.index), matcher }
+    }
+
+    /// Prints the preset of the input, or an error, exiting with the status for the outcome
+    /// (see `exit_code`).
+    pub fn print_preset_from_str(&self, input: &str) {
+        if let Some(log_path) = &self.cli.determine.log {
+            self.check_log(input, log_path);
+        }
+        let result = if self.cli.determine.ffmpeg_command {
+            self.ffmpeg_command(input)
+        } else if self.cli.determine.delta {
+            self.normalized_settings(input).map(|settings| {
+                let (preset_name, overrides) = self.reproduction(&settings);
+                info!("Overrides relative to the {} preset:", preset_name);
+                let x265_params = overrides.iter().map(|(param_name, value)| params::x265_param(param_name, value)).collect::<Vec<_>>();
+                x265_params.join(":")
+            })
+        } else {
+            let settings = self.normalized_settings(input).unwrap_or_else(|error_message| fail(&error_message, self.cli.determine.exit_status(exit_code::ERROR)));
+            if self.cli.determine.verbose >= 3 {
+                info!("{}", self.settings_dump(input, &settings).trim_end());
+            }
+            let determination = self.determination(&settings).for_input(input);
+            self.check_embedded_preset(settings.get("preset").map(String::as_str), &determination);
+            self.check_interlacing(encoding::interlacing(|name| settings.get(name).map(String::as_str)).as_ref(), &determination);
+            let (settings, determination) = self.disambiguate(input, settings, determination);
+            if let Some(family) = handbrake::likely_preset_family(input, &determination) {
+                info!("Encoded by HandBrake, likely with one of its {} presets", family);
+            }
+            if self.cli.determine.verbose >= 1 {
+                self.report_details(&encoding::details(input, |name| settings.get(name).map(String::as_str)), &determination);
+                let unknown_params = self.unknown_params(&settings);
+                // the parameters of other encoders are unknown to x265, but not mangled
+                if !unknown_params.is_empty() && !matches!(determination, Determination::NotDerivable { .. }) {
+                    info!("Unknown parameters ({}), ignored as they are neither in the presets nor x265 options, which can mean mangled metadata: {}", unknown_params.len(), unknown_params.join(", "));
+                }
+                // a preset matches however few of its parameters the input has, so show how much evidence there was
+                if let Some(coverage) = determination.preset().and_then(|preset_name| self.coverage(&settings, preset_name)) {
+                    info!("Evidence: {}", coverage);
+                }
+            }
+            for (tag, preset_name) in self.tag_hints(input) {
+                info!("{}", self.tag_hint_message(&tag, &preset_name, &determination));
+            }
+            let gate = &self.cli.determine.gate;
+            if let Some(summary) = self.formatted_summary(input, &settings, true).filter(|_| gate.is_empty()) {
+                println!("{}", summary);
+                exit(self.cli.determine.exit_status(determination.exit_code()));
+            }
+            match self.preset_or_reason(determination.clone(), input, &settings) {
+                // an undetermined preset can't meet a required one
+                Err(error_message) if gate.is_empty() || ((gate.require.is_some() || gate.at_most.is_some()) && !matches!(determination, Determination::Ambiguous(_))) => {
+                    fail(&error_message, self.cli.determine.exit_status(determination.exit_code()))
+                }
+                Ok(preset_label) if gate.is_empty() => match determination {
+                    Determination::Match(preset_name) if self.cli.determine.exit_preset_code => {
+                        println!("{}", preset_label);
+                        exit(self.speed(&preset_name).expect("The preset was found by name") as i32);
+                    }
+                    _ => Ok(preset_label),
+                },
+                _ => {
+                    let filter = gate.filter.as_deref().map(filter::parse).transpose().unwrap_or_else(|error_message| fail(&error_message, self.cli.determine.exit_status(exit_code::ERROR)));
+                    match self.rejection(gate, filter.as_ref(), input, &settings) {
+                        Ok(None) => Ok(self.formatted_summary(input, &settings, false).unwrap_or_else(|| self.preset_summary(&settings))),
+                        Ok(Some(reason)) => fail(&reason, self.cli.determine.exit_status(exit_code::REJECTED)),
+                        Err(error_message) => Err(error_message),
+                    }
+                }
+            }
+        };
+        match result {
+            Ok(preset_name) => println!("{}", preset_name),
+            Err(error_message) => fail(&error_message, self.cli.determine.exit_status(exit_code::ERROR)),
+        }
+    }
+
+    /// Offers to walk through the parameters that an ambiguous result's presets differ in, or
+    /// that a near miss differs from the closest presets in, asking which of the input's to
+    /// trust and the values of those it doesn't record (see `wizard`). Returns the refined
+    /// settings and their determination, or the given ones if the offer isn't made or taken.
+    fn disambiguate(&self, input: &str, settings: HashMap<String, String>, determination: Determination) -> (HashMap<String, String>, Determination) {
+        let determine = &self.cli.determine;
+        let interactive = !determine.no_interactive
+            && determine.format == OutputFormat::Text
+            && determine.gate.is_empty()
+            && !determine.exit_preset_code
+            && determine.input.as_deref().is_some_and(|path| path != "-")
+            && atty::is(atty::Stream::Stdin)
+            && atty::is(atty::Stream::Stderr);
+        if !interactive {
+            return (settings, determination);
+        }
+        let closest = match determination {
+            Determination::NoMatch => self.closest_match_details(&settings),
+            _ => Vec::new(),
+        };
+        let candidates = wizard::candidates(&determination, &self.presets, &closest);
+        let questions = wizard::questions(&settings, &candidates);
+        if questions.is_empty() {
+            return (settings, determination);
+        }
+        let preset_names = candidates.iter().map(|(preset_name, _)| preset_name.as_str()).collect::<Vec<_>>().join(", ");
+        let offer = match determination {
+            Determination::Ambiguous(_) => format!("The settings match several presets ({}). Walk through the parameters that tell them apart?", preset_names),
+            _ if candidates.len() == 1 => format!("No preset matches, but {} differs in only a few parameters. Walk through them?", preset_names),
+            _ => format!("No preset matches, but {} differ in only a few parameters. Walk through them?", preset_names),
+        };
+        eprint!("{} [y/N] ", offer);
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer).is_err() || !answer.trim().eq_ignore_ascii_case("y") {
+            return (settings, determination);
+        }
+        let answers = match wizard::ask(&questions, std::io::stdin().lock(), std::io::stderr()) {
+            Ok(answers) if answers != wizard::Answers::default() => answers,
+            Ok(_) => return (settings, determination),
+            Err(err) => {
+                warn!("Could not ask about the parameters: {}", err);
+                return (settings, determination);
+            }
+        };
+        if !answers.ignored.is_empty() {
+            info!("To get this result again without the questions, add --ignore {}", answers.ignored.join(","));
+        }
+        let settings = answers.apply(&settings);
+        let determination = self.determination(&settings).for_input(input);
+        (settings, determination)
+    }
+
+    /// Prints the preset of the inputs read from STDIN as they arrive (see `input::Records`). A
+    /// single input is printed like `print_preset_from_str` prints it, and several are listed
+    /// like `scan` lists videos, exiting with the highest status of any of them (see
+    /// `record_status`). The options that print a single result can't be given for several.
+    pub fn print_preset_from_records(&self, mut records: impl Iterator<Item = Result<(String, String), String>>) {
+        let io_status = self.cli.determine.exit_status(exit_code::IO_ERROR);
+        let first = records.next().unwrap_or_else(|| Ok(("STDIN".to_string(), String::new()))).unwrap_or_else(|error_message| fail(&error_message, io_status));
+        let Some(second) = records.next() else {
+            return self.print_preset_from_str(&first.1);
+        };
+        let determine = &self.cli.determine;
+        let single_result_options = [
+            (determine.format != OutputFormat::Text, "--format"),
+            (determine.ffmpeg_command, "--ffmpeg-command"),
+            (determine.delta, "--delta"),
+            (determine.exit_preset_code, "--exit-preset-code"),
+            (determine.log.is_some(), "--log"),
+        ];
+        if let Some((_, option)) = single_result_options.iter().find(|(given, _)| *given) {
+            fail(&format!("{} needs a single input, but STDIN has several; use `scan` to list several videos", option), exit_code::ERROR);
+        }
+        let gate = &determine.gate;
+        let filter = gate.filter.as_deref().map(filter::parse).transpose().unwrap_or_else(|error_message| fail(&error_message, exit_code::ERROR));
+        let mut status = exit_code::SUCCESS;
+        for record in [Ok(first), second].into_iter().chain(records) {
+            let (name, input) = record.unwrap_or_else(|error_message| fail(&error_message, io_status));
+            print!("{}", self.scan_line(&name, Ok(&input), gate, filter.as_ref(), false, false));
+            // each video is printed as soon as it has been read
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+            status = status.max(self.record_status(&name, &input, gate, filter.as_ref()));
+        }
+        exit(status);
+    }
+
+    /// The status that one of several inputs on STDIN would exit with on its own: its
+    /// determination's, or with `--require`, `--at-most` or `--filter`, whether it's accepted.
+    /// A rejected input isn't listed, so why it was rejected is logged.
+    fn record_status(&self, name: &str, input: &str, gate: &GateArgs, filter: Option<&filter::Filter>) -> i32 {
+        let Ok(settings) = self.normalized_settings(input) else {
+            return exit_code::ERROR;
+        };
+        if gate.is_empty() {
+            return self.determination(&settings).for_input(input).exit_code();
+        }
+        match self.rejection(gate, filter, input, &settings) {
+            Ok(None) => exit_code::SUCCESS,
+            Ok(Some(reason)) => {
+                warn!("{}: {}", name, reason);
+                exit_code::REJECTED
+            }
+            Err(_) => exit_code::ERROR,
+        }
+    }
+
+    /// Checks the input against the `--require` preset and the `--filter` condition, and returns
+    /// why it is rejected, if it is. `filter` is the parsed `--filter`.
+    fn rejection(&self, gate: &GateArgs, filter: Option<&filter::Filter>, input: &str, settings: &HashMap<String, String>) -> Result<Option<String>, String> {
+        let matching_presets = match self.determination(settings) {
+            Determination::Match(preset_name) => vec![preset_name],
+            Determination::Ambiguous(matching_presets) => matching_presets,
+            Determination::NoMatch | Determination::InsufficientInput | Determination::NotDerivable { .. } => Vec::new(),
+        };
+        self.check_preset_range(gate)?;
+        if let Some(required_preset) = &gate.require {
+            let required_speed = self.speed(required_preset)?;
+            let required_preset = self.preset_name(required_preset);
+            if matching_presets.is_empty() {
+                return Ok(Some(format!("The preset could not be determined, so it can't be at least {}", required_preset)));
+            }
+            let too_fast = matching_presets.iter()
+                .filter(|preset_name| self.speed(preset_name).is_ok_and(|speed| speed < required_speed))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            if !too_fast.is_empty() {
+                return Ok(Some(format!("The preset {} is faster than the required {}", too_fast.join(" or "), required_preset)));
+            }
+        }
+        if let Some(slowest_preset) = &gate.at_most {
+            let slowest_speed = self.speed(slowest_preset)?;
+            let slowest_preset = self.preset_name(slowest_preset);
+            if matching_presets.is_empty() {
+                return Ok(Some(format!("The preset could not be determined, so it can't be at most {}", slowest_preset)));
+            }
+            let too_slow = matching_presets.iter()
+                .filter(|preset_name| self.speed(preset_name).is_ok_and(|speed| speed > slowest_speed))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            if !too_slow.is_empty() {
+                return Ok(Some(format!("The preset {} is slower than the allowed {}", too_slow.join(" or "), slowest_preset)));
+            }
+        }
+        if let Some(filter) = filter {
+            let mut values = media_properties(input);
+            values.extend(settings.iter().map(|(param_name, value)| (param_name.clone(), value.clone())));
+            let context = filter::Context {
+                preset_order: self.presets.iter().map(|(name, _)| name.as_str()).collect(),
+                matching_presets: &matching_presets,
+                values: &values,
+            };
+            if !filter.matches(&context)? {
+                return Ok(Some(format!("The input doesn't match the filter `{}`", gate.filter.as_deref().unwrap_or_default())));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The settings that are matched against the presets, one per line, for debugging at -vvv.
+    /// The parameters that no preset defines, and so don't affect the match, are marked, and so
+    /// are those at x265's defaults (see `params::DEFAULTS`), which are boilerplate rather than
+    /// choices. The color description's numbers are followed by their names, like `BT.2020`.
+    fn settings_dump(&self, input: &str, settings: &HashMap<String, String>) -> String {
+        let mut dump = "Normalized input settings (- = not defined by the presets, so not compared; (default) = x265's default, not a choice):\n".to_string();
+        let mut parameters = settings.iter().collect::<Vec<_>>();
+        self.sort_params(input, &mut parameters, |(param_name, _)| param_name);
+        for (param_name, value) in parameters {
+            let compared = self.presets.iter().any(|(_, preset_settings)| preset_settings.contains_key(param_name));
+            let note = match encoding::color_name(param_name, value) {
+                Some(color_name) => format!(" ({})", color_name),
+                None if !compared && params::is_default(param_name, value) => " (default)".to_string(),
+                None => String::new(),
+            };
+            dump.push_str(&format!("  {} {}={}{}\n", if compared { ' ' } else { '-' }, param_name, value, note));
+        }
+        dump
+    }
+
+    /// The result in a form for scripts, with the fields described in `OutputFormat`.
+    fn summary(&self, input: &str, settings: &HashMap<String, String>) -> Summary {
+        let details = encoding::details(input, |name| settings.get(name).map(String::as_str));
+        self.summary_of(input, self.determination(settings).for_input(input), details, || self.closest_preset(settings))
+    }
+
+    /// Like `summary`, without copying the input's settings unless none of the presets match.
+    fn summary_from_str(&self, input: &str) -> Result<Summary, String> {
+        let (determination, details) = self.determination_from_str(input)?;
+        Ok(self.summary_of(input, determination, details, || self.closest_preset(&self.normalized_settings(input).expect("The input was parsed before"))))
+    }
+
+    /// The summary of a determination. `closest_preset` is only called if no preset matches.
+    fn summary_of(&self, input: &str, determination: Determination, details: encoding::Details, closest_preset: impl FnOnce() -> (String, usize)) -> Summary {
+        let matching_presets = match &determination {
+            Determination::Match(preset_name) => vec![preset_name.clone()],
+            Determination::Ambiguous(matching_presets) => matching_presets.clone(),
+            Determination::NoMatch | Determination::InsufficientInput | Determination::NotDerivable { .. } => Vec::new(),
+        };
+        let (preset_name, confidence) = match (&determination, matching_presets.first()) {
+            (Determination::InsufficientInput | Determination::NotDerivable { .. }, _) => (None, 0),
+            (_, Some(preset_name)) => (Some(preset_name.clone()), 100),
+            (_, None) => {
+                let (preset_name, confidence) = closest_preset();
+                (Some(preset_name), confidence)
+            }
+        };
+        Summary {
+            preset: preset_name.map(|preset_name| self.preset_label(&preset_name)),
+            confidence,
+            encoder: match &determination {
+                Determination::NotDerivable { encoder } => encoder.clone(),
+                _ => encoder_name(input),
+            },
+            matches: matching_presets.iter().map(|preset_name| self.preset_label(preset_name)).collect(),
+            handbrake_preset: handbrake::likely_preset_family(input, &determination),
+            details,
+            tag_hints: self.tag_hints(input).into_iter().map(|(tag, preset_name)| (tag, self.preset_label(&preset_name))).collect(),
+            determination,
+        }
+    }
+
+    /// The preset closest to the settings, and the percentage of their parameters it matches.
+    fn closest_preset(&self, settings: &HashMap<String, String>) -> (String, usize) {
+        let preset_name = self.closest_matches(settings).remove(0).0;
+        let confidence = self.match_percentage(settings, self.preset(&preset_name).expect("The preset was found by name"));
+        (preset_name, confidence)
+    }
+
+    /// The summary as shell variable assignments, for `--format shell`.
+    fn shell_assignments(&self, summary: &Summary) -> String {
+        format!(
+            "STATUS={} PRESET={} CONFIDENCE={} ENCODER={} MATCHES={} HANDBRAKE_PRESET={}",
+            summary.determination.status(),
+            shell_quote(summary.preset.as_deref().unwrap_or("")),
+            summary.confidence,
+            shell_quote(&summary.encoder),
+            shell_quote(&summary.matches.join(" ")),
+            shell_quote(summary.handbrake_preset.unwrap_or("")),
+        )
+    }
+
+    /// The summary as a JSON object, for `--format json`. With `report_error`, if there is no
+    /// single match, `error` describes why.
+    fn json_summary(&self, summary: &Summary, input: &str, settings: &HashMap<String, String>, report_error: bool) -> String {
+        let mut json = serde_json::json!({
+            "schema_version": schema::SCHEMA_VERSION,
+            "status": summary.determination.status(),
+            "preset": summary.preset,
+            "confidence": summary.confidence,
+            "encoder": summary.encoder,
+            "matches": summary.matches,
+        });
+        let message = match &summary.determination {
+            _ if !report_error => None,
+            Determination::Match(_) => None,
+            Determination::Ambiguous(_) => Some(format!("{}: {}", self.tr("Multiple matching presets found"), summary.matches.join(", "))),
+            Determination::NoMatch => Some(self.tr("No matching presets found").to_string()),
+            Determination::InsufficientInput => Some(self.tr("The input has none of the parameters that the presets set").to_string()),
+            Determination::NotDerivable { encoder } => Some(self.lang().not_derivable_message(encoder)),
+        };
+        if let Some(family) = summary.handbrake_preset {
+            json["handbrake_preset"] = family.into();
+        }
+        if let Some(rate_control) = &summary.details.rate_control {
+            json["rate_control"] = rate_control.as_str().into();
+        }
+        if let Some(profile) = &summary.details.profile {
+            json["profile"] = profile.as_str().into();
+        }
+        if let Some(gop) = &summary.details.gop {
+            json["gop"] = gop.as_str().into();
+        }
+        if let Some(x265_era) = &summary.details.x265_era {
+            json["x265_era"] = x265_era.as_str().into();
+        }
+        if let Some(latency) = &summary.details.latency {
+            json["latency"] = latency.to_string().into();
+        }
+        if let Some(bit_depth) = summary.details.bit_depth {
+            json["bit_depth"] = bit_depth.into();
+        }
+        if let Some(chroma_subsampling) = summary.details.chroma_subsampling {
+            json["chroma_subsampling"] = chroma_subsampling.into();
+        }
+        if let Some(dolby_vision) = &summary.details.dolby_vision {
+            json["dolby_vision"] = serde_json::json!({ "profile": dolby_vision.profile, "layers": dolby_vision.layers });
+        }
+        if let Some(interlacing) = &summary.details.interlacing {
+            json["interlacing"] = serde_json::json!({ "field_order": interlacing.field_order, "field_coding": interlacing.field_coding });
+        }
+        if let Some(vbv) = &summary.details.vbv {
+            json["vbv"] = serde_json::json!({ "maxrate": vbv.maxrate, "bufsize": vbv.bufsize, "strict_cbr": vbv.strict_cbr });
+        }
+        let preset_name = match &summary.determination {
+            Determination::Match(preset_name) => Some(preset_name.as_str()),
+            _ => None,
+        };
+        if let Some(category) = encoding::quality_category(&summary.details, preset_name) {
+            json["quality_category"] = category.into();
+        }
+        if !summary.details.quality_knobs.is_empty() {
+            json["quality_params"] = summary.details.quality_knobs.iter()
+                .map(|(name, value)| serde_json::json!({
+                    "name": name,
+                    "value": value,
+                    "default": encoding::knob_default(name, preset_name),
+                    "is_default": encoding::is_knob_default(name, value, preset_name),
+                }))
+                .collect();
+        }
+        let color = &summary.details.color;
+        if let Some(dynamic_range) = color.dynamic_range() {
+            json["dynamic_range"] = dynamic_range.into();
+            json["color"] = serde_json::json!({
+                "primaries": color.primaries,
+                "transfer": color.transfer,
+                "matrix": color.matrix,
+                "mastering_display": color.mastering_display,
+                "max_cll": color.content_light_level.map(|(max_cll, _)| max_cll),
+                "max_fall": color.content_light_level.map(|(_, max_fall)| max_fall),
+            });
+        }
+        if !summary.tag_hints.is_empty() {
+            json["tag_hints"] = summary.tag_hints.iter().map(|(tag, preset)| serde_json::json!({ "tag": tag, "preset": preset })).collect();
+        }
+        if let Some(raw_settings) = crate::raw_settings(input) {
+            json["raw_settings"] = raw_settings.into();
+        }
+        let mut parameters = settings.iter().collect::<Vec<_>>();
+        self.sort_params(input, &mut parameters, |(param_name, _)| param_name);
+        json["settings"] = parameters.into_iter().map(|(param_name, value)| (param_name.clone(), serde_json::Value::from(value.as_str()))).collect::<serde_json::Map<_, _>>().into();
+        let unknown_params = self.unknown_params(settings);
+        if !unknown_params.is_empty() && !matches!(summary.determination, Determination::NotDerivable { .. }) {
+            json["unknown_params"] = unknown_params.into();
+        }
+        if let Some(coverage) = summary.determination.preset().and_then(|preset_name| self.coverage(settings, preset_name)) {
+            json["coverage"] = serde_json::json!({ "present": coverage.present, "total": coverage.total, "absent": coverage.absent });
+        }
+        if matches!(summary.determination, Determination::NoMatch) {
+            let comparison = self.comparison(input, settings);
+            let closest = self.closest_match_details(settings);
+            let presets = comparison.presets.iter().zip(comparison.counts())
+                .map(|((preset_name, confidence), (matched, differed))| serde_json::json!({
+                    "preset": preset_name,
+                    "confidence": confidence,
+                    "matched": matched,
+                    "differed": differed,
+                    "changes_needed": closest.iter().find(|closest| &closest.preset == preset_name).map_or(0, |closest| closest.changes_needed()),
+                }))
+                .collect::<Vec<_>>();
+            let rows = comparison.rows.iter()
+                .map(|row| serde_json::json!({
+                    "param": row.param_name,
+                    "doc_url": params::doc_url(&row.param_name),
+                    "input": row.input_value,
+                    "presets": row.preset_values.iter().map(|(value, is_match)| serde_json::json!({ "value": value, "matches": is_match })).collect::<Vec<_>>(),
+                }))
+                .collect::<Vec<_>>();
+            json["comparison"] = serde_json::json!({ "presets": presets, "rows": rows });
+            if let Some(estimate) = matcher::speed_estimate(settings, &self.presets) {
+                json["speed_estimate"] = estimate.into();
+            }
+        }
+        if let Some(message) = message {
+            json["error"] = json_error(summary.determination.exit_code(), &message);
+        }
+        json.to_string()
+    }
+
+    /// The summary in the `--format`, for the formats meant for scripts. `report_error` is
+    /// false if the input was accepted by `--require` or `--filter` even without a single match.
+    fn formatted_summary(&self, input: &str, settings: &HashMap<String, String>, report_error: bool) -> Option<String> {
+        match self.cli.determine.format {
+            OutputFormat::Text => None,
+            OutputFormat::Shell => Some(self.shell_assignments(&self.summary(input, settings))),
+            OutputFormat::Json => Some(self.json_summary(&self.summary(input, settings), input, settings, report_error)),
+        }
+    }
+
+    /// How a preset is printed: its name, or its speed with `--numeric`.
+    fn preset_label(&self, preset_name: &str) -> String {
+        match self.speed(preset_name) {
+            Ok(speed) if self.cli.numeric => speed.to_string(),
+            _ => preset_name.to_string(),
+        }
+    }
+
+    /// Checks that the `--require` and `--at-most` presets exist, and that some presets are
+    /// between them.
+    fn check_preset_range(&self, gate: &GateArgs) -> Result<(), String> {
+        let required_speed = gate.require.as_deref().map(|preset_name| self.speed(preset_name)).transpose()?;
+        let slowest_speed = gate.at_most.as_deref().map(|preset_name| self.speed(preset_name)).transpose()?;
+        if let (Some(required_speed), Some(slowest_speed)) = (required_speed, slowest_speed) {
+            if required_speed > slowest_speed {
+                return Err(format!("No preset can be at least {} and at most {}", self.presets[required_speed].0, self.presets[slowest_speed].0));
+            }
+        }
+        Ok(())
+    }
+
+    /// The position of a preset in the preset order, from 0 for the fastest.
+    pub fn speed(&self, preset_name: &str) -> Result<usize, String> {
+        self.preset(preset_name)?;
+        let preset_name = self.preset_name(preset_name);
+        Ok(self.presets.iter().position(|(name, _)| name == preset_name).expect("The preset was found by name"))
+    }
+
+    /// The name of a preset given by name or by its position in the preset order, like `6` for
+    /// `slow`, as x265 also takes them. Names that are neither are returned as they are.
+    fn preset_name<'a>(&'a self, preset: &'a str) -> &'a str {
+        if self.presets.iter().any(|(name, _)| name == preset) {
+            return preset;
+        }
+        match preset.parse::<usize>() {
+            Ok(speed) => self.presets.get(speed).map_or(preset, |(preset_name, _)| preset_name.as_str()),
+            Err(_) => preset,
+        }
+    }
+
+    /// Compares the input's settings with the settings in an encode log, and prints a warning on
+    /// STDERR for each parameter that differs. Exits if the log can't be read or has no settings.
+    fn check_log(&self, input: &str, log_path: &str) {
+        if log_path == "-" && matches!(self.cli.determine.input.as_deref(), None | Some("-")) {
+            fail("The log and the input can't both be read from STDIN", self.cli.determine.exit_status(exit_code::ERROR));
+        }
+        let settings = self.normalized_settings(input).unwrap_or_else(|error_message| fail(&error_message, self.cli.determine.exit_status(exit_code::ERROR)));
+        let log = input::read_input(Some(log_path)).unwrap_or_else(|err| fail(&err.to_string(), self.cli.determine.exit_status(err.exit_code())));
+        let log_settings = encode_log::parse(&log, &self.presets);
+        debug!("Read {} settings from the log {}", log_settings.len(), log_path);
+        if log_settings.is_empty() {
+            fail(&format!("No x265 settings found in the log {}", log_path), self.cli.determine.exit_status(exit_code::INSUFFICIENT_INPUT));
+        }
+        for (param_name, value, log_value) in encode_log::discrepancies(&settings, &log_settings) {
+            warn!("{} is {} in the input, but {} in the log", param_name, value, log_value);
+        }
+    }
+
+    /// Runs a subcommand, printing its output, or an error and exiting if it fails.
+    pub fn run_command(&self, command: &Command) {
+        if let Command::Scan { paths, files_from, jobs, paths_only, null, policy: Some(policy), .. } = command {
+            let (report, violations) = input::with_files_from(paths, files_from.as_deref())
+                .and_then(|paths| self.scan_policy(&paths, *jobs, policy, *paths_only, *null))
+                .unwrap_or_else(|error_message| fail(&error_message, exit_code::ERROR));
+            print!("{}", report);
+            if violations > 0 {
+                exit(self.cli.determine.exit_status(exit_code::REJECTED));
+            }
+            return;
+        }
+        let result = match command {
+            Command::Explain { preset, format } => self.explain(preset, *format),
+            Command::ExportPreset { preset, format } => self.export_preset(preset, *format),
+            Command::ListPresets => Ok(self.list_presets()),
+            Command::ExplainParam { name } => self.explain_param(name),
+            Command::Resolve { command } => self.resolve(command),
+            Command::Diff { first, second } => self.diff(first, second),
+            Command::Chunks { inputs, files_from } => input::with_files_from(inputs, files_from.as_deref()).and_then(|inputs| self.chunks(&inputs)),
+            Command::Scan { paths, files_from, jobs, gate, paths_only, null, format, rename_template, dry_run, triage, triage_json, .. } => input::with_files_from(paths, files_from.as_deref()).and_then(|paths| match format {
+                _ if rename_template.is_some() => self.scan_rename(&paths, *jobs, gate, rename_template.as_deref().unwrap_or_default(), *dry_run),
+                _ if *triage || triage_json.is_some() => self.scan_triaged(&paths, *jobs, gate, *triage, triage_json.as_deref()),
+                ScanFormat::Text => self.scan(&paths, *jobs, gate, *paths_only, *null),
+                ScanFormat::Parquet => self.scan_parquet(&paths, *jobs, gate),
+            }),
+            Command::Daemon { socket, metrics } => self.daemon(socket, metrics.as_deref()),
+            Command::Selftest { dir } => self.selftest(dir),
+            Command::Bench { paths, count, rounds } => self.bench(paths, *count, *rounds),
+            Command::SelfUpdate { check } => self.self_update(*check),
+            Command::Determine(_) => unreachable!("determine is run by print_preset_from_str"),
+        };
+        match result {
+            Ok(output) => print!("{}", output),
+            Err(error_message) => fail(&error_message, exit_code::ERROR),
+        }
+    }
+
+    /// Describes the parameters of the named preset.
+    pub fn explain(&self, preset_name: &str, format: ExplainFormat) -> Result<String, String> {
+        let parameters = self.preset_parameters(preset_name)?;
+        let preset_name = self.preset_name(preset_name);
+
+        Ok(match format {
+            ExplainFormat::Table => {
+                let mut table = Table { header: vec![Cell::new(self.tr("parameter")), Cell::new(preset_name)], ..Table::default() };
+                for (param_name, value) in parameters {
+                    let mut param_cell = Cell::new(param_name.as_str());
+                    param_cell.link = params::doc_url(param_name);
+                    table.rows.push(vec![param_cell, Cell::new(value.as_str())]);
+                }
+                table.render(self.cli.table_style, self.use_color(), self.use_links())
+            }
+            ExplainFormat::Flags => self.export_preset(preset_name, ExportFormat::Cli)?,
+            ExplainFormat::Json => {
+                let parameters = parameters.iter().map(|(param_name, value)| {
+                    // numbers are emitted as JSON numbers, anything else (like `me=star`) as a string:
+                    let value = value.parse::<i64>().map_or_else(|_| serde_json::Value::from(value.as_str()), serde_json::Value::from);
+                    (param_name.to_string(), value)
+                }).collect::<serde_json::Map<_, _>>();
+                let json = serde_json::json!({ "schema_version": schema::SCHEMA_VERSION, "preset": preset_name, "parameters": parameters });
+                format!("{}\n", serde_json::to_string_pretty(&json).expect("JSON serialization can't fail"))
+            }
+        })
+    }
+
+    /// Lists the presets in speed order with their parameter counts, followed by any problems
+    /// found in the preset table.
+    pub fn list_presets(&self) -> String {
+        let source = match &self.cli.presets_file {
+            Some(path) => format!("presets file {}", path),
+            None => "x265 (https://x265.readthedocs.io/en/master/presets.html)".to_string(),
+        };
+        let mut table = Table { header: vec![Cell::new(self.tr("speed")), Cell::new(self.tr("preset")), Cell::new(self.tr("parameters"))], ..Table::default() };
+        for (index, (name, settings)) in self.presets.iter().enumerate() {
+            table.rows.push(vec![Cell::new(index.to_string()), Cell::new(name.as_str()), Cell::new(settings.len().to_string())]);
+        }
+        let mut output = format!("{}:\n\n{}", source, table.render(self.cli.table_style, self.use_color(), false));
+        for warning in presets::validate(&self.presets) {
+            output.push_str(&format!("Warning: {}\n", warning));
+        }
+        output
+    }
+
+    /// Describes a parameter, with its value in each preset and a link to the documentation.
+    pub fn explain_param(&self, name: &str) -> Result<String, String> {
+        let param = params::find(name).ok_or_else(|| format!("Unknown parameter: {}", name))?;
+        let mut output = format!("{} (--{})\n{}\n", param.name, param.option, param.description);
+        let values = self.presets.iter()
+            .filter_map(|(preset_name, settings)| settings.get(param.name).map(|value| format!("{} {}", preset_name, value)))
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            output.push_str("Not set by the presets.\n");
+        } else {
+            output.push_str(&format!("Preset values: {}\n", values.join(", ")));
+        }
+        if let Some(url) = params::doc_url(param.name) {
+            output.push_str(&format!("Documentation: {}\n", url));
+        }
+        Ok(output)
+    }
+
+    /// The options that set all of the preset's parameters explicitly.
+    pub fn export_preset(&self, preset_name: &str, format: ExportFormat) -> Result<String, String> {
+        let parameters = self.preset_parameters(preset_name)?;
+        Ok(match format {
+            ExportFormat::Cli => {
+                let flags = parameters.iter().map(|(param_name, value)| params::x265_args(param_name, value)).collect::<Vec<_>>();
+                format!("{}\n", flags.join(" "))
+            }
+            ExportFormat::X265Params => {
+                let flags = parameters.iter().map(|(param_name, value)| params::x265_param(param_name, value)).collect::<Vec<_>>();
+                format!("{}\n", flags.join(":"))
+            }
+        })
+    }
+
+    /// The named preset's parameters, in the order of the x265 documentation rather than the
+    /// hash order. Parameters that aren't in the documentation (from a presets file) come last.
+    fn preset_parameters(&self, preset_name: &str) -> Result<Vec<(&String, &String)>, String> {
+        Ok(ordered_settings(self.preset(preset_name)?))
+    }
+
+    /// A table of the parameters that differ between two inputs, with the preset of each.
+    pub fn diff(&self, first: &str, second: &str) -> Result<String, String> {
+        let first_settings = self.normalized_settings(&input::read_input(Some(first))?)?;
+        let second_settings = self.normalized_settings(&input::read_input(Some(second))?)?;
+
+        let mut table = Table { header: vec![Cell::new(""), Cell::new(first), Cell::new(second)], ..Table::default() };
+        table.rows.push(vec![
+            Cell::new(self.tr("preset")),
+            Cell::highlighted(self.preset_summary(&first_settings), Highlight::Bold),
+            Cell::highlighted(self.preset_summary(&second_settings), Highlight::Bold),
+        ]);
+
+        // the preset parameters come first, in the documentation's order, then the rest by name
+        let mut param_names = first_settings.keys().chain(second_settings.keys()).collect::<Vec<_>>();
+        param_names.sort_by_key(|param_name| (params::PARAMS.iter().position(|param| param.name == param_name.as_str()).unwrap_or(usize::MAX), *param_name));
+        param_names.dedup();
+        for param_name in param_names {
+            let first_value = first_settings.get(param_name);
+            let second_value = second_settings.get(param_name);
+            if first_value != second_value {
+                let mut param_cell = Cell::new(param_name.as_str());
+                param_cell.link = params::doc_url(param_name);
+                table.rows.push(vec![
+                    param_cell,
+                    Cell::new(first_value.map_or(MISSING_IN_INPUT, |value| value.as_str())),
+                    Cell::new(second_value.map_or(MISSING_IN_INPUT, |value| value.as_str())),
+                ]);
+            }
+        }
+        Ok(table.render(self.cli.table_style, self.use_color(), self.use_links()))
+    }
+
+    /// The preset of each video found in `paths`, one per line, reading `jobs` files at a time.
+    /// With conditions in `gate`, only the videos that meet them are listed. With `paths_only`,
+    /// only the paths are listed, ended by NUL characters with `null`. In either case, the videos
+    /// that can't be read are reported on STDERR instead.
+    pub fn scan(&self, paths: &[String], jobs: usize, gate: &GateArgs, paths_only: bool, null: bool) -> Result<String, String> {
+        let filter = gate.filter.as_deref().map(filter::parse).transpose()?;
+        let lines = self.scan_videos(paths, jobs, gate, |path, input| self.scan_line(path, input, gate, filter.as_ref(), paths_only, null))?;
+        Ok(lines.concat())
+    }
+
+    /// Lists the videos found in `paths` like `scan`, followed by those that need review (see
+    /// `TriageEntry`) with `report`, and writes those to the file `json_path` as JSON. The videos
+    /// that need review are found among all the videos, including those the `gate` doesn't list.
+    pub fn scan_triaged(&self, paths: &[String], jobs: usize, gate: &GateArgs, report: bool, json_path: Option<&str>) -> Result<String, String> {
+        let filter = gate.filter.as_deref().map(filter::parse).transpose()?;
+        let results = self.scan_videos(paths, jobs, gate, |path, input| {
+            let entry = self.triage_entry(path, input.clone());
+            (self.scan_line(path, input, gate, filter.as_ref(), false, false), entry)
+        })?;
+        let scanned = results.len();
+        let (lines, entries): (Vec<_>, Vec<_>) = results.into_iter().unzip();
+        let mut entries = entries.into_iter().flatten().collect::<Vec<_>>();
+        // grouped by cause, and in the order they were found within each group
+        entries.sort_by_key(|entry| entry.cause);
+        if let Some(json_path) = json_path {
+            let json = entries.iter()
+                .map(|entry| serde_json::json!({ "path": entry.path, "cause": entry.cause.key(), "detail": entry.detail }))
+                .collect::<Vec<_>>();
+            std::fs::write(json_path, format!("{}\n", serde_json::Value::from(json)))
+                .map_err(|err| format!("Could not write {}: {}", json_path, err))?;
+        }
+        let mut output = lines.concat();
+        if report {
+            output.push_str(&triage_report(&entries, scanned));
+        }
+        Ok(output)
+    }
+
+    /// Whether a scanned video needs review, and why.
+    fn triage_entry(&self, path: &str, input: Result<&str, String>) -> Option<TriageEntry> {
+        let entry = |cause, detail: String| Some(TriageEntry { path: path.to_string(), cause, detail });
+        let input = match input {
+            Ok(input) => input,
+            Err(error_message) => return entry(TriageCause::FailedExtraction, error_message),
+        };
+        let settings = match self.normalized_settings(input) {
+            Ok(settings) => settings,
+            Err(error_message) => return entry(TriageCause::FailedExtraction, error_message),
+        };
+        match self.determination(&settings).for_input(input) {
+            Determination::Match(preset_name) => {
+                let coverage = self.coverage(&settings, &preset_name)?;
+                (coverage.present * 2 < coverage.total).then(|| TriageEntry {
+                    path: path.to_string(),
+                    cause: TriageCause::LowConfidence,
+                    detail: format!("{}, matched on {}/{} preset parameters", self.preset_label(&preset_name), coverage.present, coverage.total),
+                })
+            }
+            Determination::Ambiguous(matching_presets) => entry(TriageCause::Ambiguous, matching_presets.iter().map(|preset_name| self.preset_label(preset_name)).collect::<Vec<_>>().join(", ")),
+            Determination::NoMatch => {
+                let (preset_name, _) = self.closest_matches(&settings).remove(0);
+                let (_, preset_settings) = self.presets.iter().find(|(name, _)| *name == preset_name).expect("The closest preset exists");
+                entry(TriageCause::NoMatch, format!("{}: {} ({}%)", self.tr("closest"), self.preset_label(&preset_name), self.match_percentage(&settings, preset_settings)))
+            }
+            Determination::InsufficientInput => entry(TriageCause::NoSettings, self.tr("no encoder settings found").to_string()),
+            Determination::NotDerivable { encoder } => entry(TriageCause::NoSettings, self.lang().not_derivable_summary(&encoder)),
+        }
+    }
+
+    /// The videos found in `paths` that violate the policy in the file `policy_path`, one per
+    /// line with the reason, and their number. Videos in no directory of the policy aren't
+    /// checked, and those that can't be read are violations. With `paths_only`, only the paths
+    /// are listed, as in `scan`.
+    pub fn scan_policy(&self, paths: &[String], jobs: usize, policy_path: &str, paths_only: bool, null: bool) -> Result<(String, usize), String> {
+        let policy = policy::load(policy_path)?;
+        let mut filters = HashMap::new();
+        for (dir, gate) in policy.rules() {
+            self.check_preset_range(gate).map_err(|error_message| format!("{}: [{}]: {}", policy_path, dir, error_message))?;
+            let filter = gate.filter.as_deref().map(filter::parse).transpose().map_err(|error_message| format!("{}: [{}]: {}", policy_path, dir, error_message))?;
+            filters.insert(dir, filter);
+        }
+        let violations = self.scan_videos(paths, jobs, &GateArgs::default(), |path, input| {
+            let (dir, gate) = policy.rule(path)?;
+            let violation = input.and_then(|input| {
+                let settings = self.normalized_settings(input)?;
+                self.rejection(gate, filters[dir].as_ref(), input, &settings)
+            });
+            match violation {
+                Ok(None) => None,
+                Ok(Some(_)) | Err(_) if paths_only => Some(format!("{}{}", path, if null { '\0' } else { '\n' })),
+                Ok(Some(reason)) => Some(format!("{}: violates [{}]: {}\n", path, dir, reason)),
+                Err(error_message) => Some(format!("{}: violates [{}]: error: {}\n", path, dir, error_message)),
+            }
+        })?;
+        let violations = violations.into_iter().flatten().collect::<Vec<_>>();
+        Ok((violations.concat(), violations.len()))
+    }
+
+    /// Renames the videos found in `paths` whose preset is determined after `template` (see
+    /// `rename::render`), listing each rename, or only lists them with `dry_run`. With conditions
+    /// in `gate`, only the videos that meet them are renamed. Videos are never renamed over
+    /// existing files, and those that already have their new name are left alone.
+    pub fn scan_rename(&self, paths: &[String], jobs: usize, gate: &GateArgs, template: &str, dry_run: bool) -> Result<String, String> {
+        rename::check(template)?;
+        let filter = gate.filter.as_deref().map(filter::parse).transpose()?;
+        // the new names are worked out in parallel, and the renames made one at a time, so that
+        // two videos given the same name can be told apart
+        let plans = self.scan_videos(paths, jobs, gate, |path, input| {
+            let new_path = input.and_then(|input| {
+                let settings = self.normalized_settings(input)?;
+                if !gate.is_empty() && self.rejection(gate, filter.as_ref(), input, &settings)?.is_some() {
+                    return Ok(None);
+                }
+                let preset_name = match self.determination(&settings).for_input(input) {
+                    Determination::Match(preset_name) => preset_name,
+                    determination => return Err(format!("Not renamed, as the result is {}", determination.status())),
+                };
+                // the videos of a concatenated mediainfo export are named, but aren't files
+                if !Path::new(path).is_file() {
+                    return Err("Not renamed, as it isn't a file".to_string());
+                }
+                let mut values = media_properties(input).into_iter()
+                    .filter_map(|(name, value)| Some((*rename::PLACEHOLDERS.iter().find(|placeholder| **placeholder == name)?, value)))
+                    .collect::<HashMap<_, _>>();
+                values.insert("preset", preset_name);
+                values.extend(Some(encoder_name(input)).filter(|encoder| !encoder.is_empty()).map(|encoder| ("encoder", encoder)));
+                rename::new_path(path, template, values).map(Some)
+            });
+            (path.to_string(), new_path)
+        })?;
+        let mut new_names = HashMap::new();
+        let lines = plans.into_iter().map(|(path, new_path)| match new_path {
+            Ok(None) => String::new(),
+            Ok(Some(new_path)) if new_path == Path::new(&path) => String::new(),
+            Ok(Some(new_path)) => match new_names.get(&new_path) {
+                Some(other_path) => format!("{}: error: Not renamed, as {} is renamed to {} too\n", path, other_path, new_path.display()),
+                None => {
+                    let renamed = if !dry_run {
+                        rename::rename_new(Path::new(&path), &new_path)
+                    } else if new_path.exists() {
+                        Err(std::io::ErrorKind::AlreadyExists.into())
+                    } else {
+                        Ok(())
+                    };
+                    match renamed {
+                        Ok(()) => {
+                            let line = format!("{} -> {}\n", path, new_path.display());
+                            new_names.insert(new_path, path);
+                            line
+                        }
+                        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => format!("{}: error: Not renamed, as {} exists\n", path, new_path.display()),
+                        Err(err) => format!("{}: error: Could not rename it to {}: {}\n", path, new_path.display(), err),
+                    }
+                }
+            },
+            Err(error_message) => format!("{}: error: {}\n", path, error_message),
+        }).collect::<String>();
+        Ok(lines)
+    }
+
+    /// The results of `scan` as a Parquet file, written to STDOUT, with a row per video listed.
+    #[cfg(feature = "parquet")]
+    pub fn scan_parquet(&self, paths: &[String], jobs: usize, gate: &GateArgs) -> Result<String, String> {
+        if atty::is(atty::Stream::Stdout) {
+            return Err("Parquet output is binary, so it's only written to a file or a pipe, like `scan --format parquet videos > videos.parquet`".to_string());
+        }
+        let filter = gate.filter.as_deref().map(filter::parse).transpose()?;
+        // the rows are held until the scan ends, and their statuses, presets and Dolby Vision
+        // profiles repeat across the videos, so each distinct value is kept once
+        let interner = Mutex::new(intern::Interner::default());
+        let rows = self.scan_videos(paths, jobs, gate, |path, input| {
+            let row = self.scan_row(path, input, gate, filter.as_ref())?;
+            let mut interner = interner.lock().expect("A scan thread panicked");
+            Some(row.into_iter().map(|value| value.map(|value| interner.intern(&value))).collect::<Vec<_>>())
+        })?;
+        debug!("The Parquet rows have {} distinct values", interner.lock().expect("A scan thread panicked").len());
+        let file = parquet::write(SCAN_COLUMNS, &rows.into_iter().flatten().collect::<Vec<_>>());
+        std::io::stdout().write_all(&file).map_err(|err| format!("Could not write the Parquet output: {}", err))?;
+        Ok(String::new())
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    pub fn scan_parquet(&self, _paths: &[String], _jobs: usize, _gate: &GateArgs) -> Result<String, String> {
+        Err("Parquet output needs determine-preset to be built with the `parquet` feature".to_string())
+    }
+
+    /// Describes each video found in `paths` with `describe_input`, reading `jobs` files at a
+    /// time, in the order they were found.
+    fn scan_videos<T: Send>(&self, paths: &[String], jobs: usize, gate: &GateArgs, describe_input: impl Fn(&str, Result<&str, String>) -> T + Sync) -> Result<Vec<T>, String> {
+        self.check_preset_range(gate)?;
+        let videos = input::find_videos(paths)?;
+        let describe = |path: &String| {
+            let _span = info_span!("file", path = %path).entered();
+            match input::read_input(Some(path)) {
+                Ok(input) => {
+                    // a concatenated mediainfo export is listed by the videos in it
+                    let videos = input::split_dump(&input);
+                    if videos.is_empty() {
+                        vec![describe_input(path, Ok(&input))]
+                    } else {
+                        videos.into_iter().map(|(name, text)| describe_input(name, Ok(text))).collect()
+                    }
+                }
+                Err(err) => vec![describe_input(path, Err(err.to_string()))],
+            }
+        };
+        let chunk_size = videos.len().div_ceil(jobs.max(1)).max(1);
+        let results = std::thread::scope(|scope| {
+            let threads = videos.chunks(chunk_size)
+                .map(|chunk| scope.spawn(|| chunk.iter().flat_map(describe).collect::<Vec<_>>()))
+                .collect::<Vec<_>>();
+            threads.into_iter().flat_map(|thread| thread.join().expect("A scan thread panicked")).collect()
+        });
+        Ok(results)
+    }
+
+    /// The line that `scan` lists a video with, as described there. Empty if the video isn't
+    /// listed.
+    fn scan_line(&self, path: &str, input: Result<&str, String>, gate: &GateArgs, filter: Option<&filter::Filter>, paths_only: bool, null: bool) -> String {
+        // Dolby Vision is listed with the preset, as it's what HDR checks look for first
+        let dolby_vision = input.as_ref().ok().and_then(|input| encoding::dolby_vision(input));
+        let result = input.and_then(|input| {
+            if gate.is_empty() {
+                return Ok((self.preset_summary_from_str(input)?, None));
+            }
+            let settings = self.normalized_settings(input)?;
+            let rejection = self.rejection(gate, filter, input, &settings)?;
+            let determination = self.determination(&settings).for_input(input);
+            self.check_embedded_preset(settings.get("preset").map(String::as_str), &determination);
+            self.check_interlacing(encoding::interlacing(|name| settings.get(name).map(String::as_str)).as_ref(), &determination);
+            Ok((self.determination_summary(determination, || self.closest_matches(&settings).remove(0).0), rejection))
+        });
+        match result {
+            Ok((_, None)) if paths_only => format!("{}{}", path, if null { '\0' } else { '\n' }),
+            Ok((summary, None)) => match dolby_vision {
+                Some(dolby_vision) => format!("{}: {} ({})\n", path, summary, dolby_vision),
+                None => format!("{}: {}\n", path, summary),
+            },
+            Ok((_, Some(_))) => String::new(),
+            Err(error_message) if gate.is_empty() && !paths_only => format!("{}: error: {}\n", path, error_message),
+            Err(error_message) => {
+                warn!("{}: {}", path, error_message);
+                String::new()
+            }
+        }
+    }
+
+    /// The Parquet row that `scan --format parquet` lists a video with, with a value for each of
+    /// `SCAN_COLUMNS`. `None` if the video isn't listed.
+    #[cfg(feature = "parquet")]
+    fn scan_row(&self, path: &str, input: Result<&str, String>, gate: &GateArgs, filter: Option<&filter::Filter>) -> Option<Vec<Option<String>>> {
+        let dolby_vision = input.as_ref().ok().and_then(|input| encoding::dolby_vision(input)).map(|dolby_vision| dolby_vision.to_string());
+        let result = input.and_then(|input| {
+            // without a gate, the settings are only copied out of the input for the closest preset
+            let (determination, settings) = if gate.is_empty() {
+                (self.determination_from_str(input)?.0, None)
+            } else {
+                let settings = self.normalized_settings(input)?;
+                if self.rejection(gate, filter, input, &settings)?.is_some() {
+                    return Ok(None);
+                }
+                (self.determination(&settings).for_input(input), Some(settings))
+            };
+            let closest = match (&determination, settings) {
+                (Determination::NoMatch, Some(settings)) => Some(self.closest_matches(&settings).remove(0).0),
+                (Determination::NoMatch, None) => Some(self.closest_matches(&self.normalized_settings(input)?).remove(0).0),
+                _ => None,
+            };
+            Ok(Some((determination, closest)))
+        });
+        let (determination, closest) = match result {
+            Ok(listed) => listed?,
+            Err(error_message) if gate.is_empty() => {
+                return Some(vec![Some(path.to_string()), Some("error".to_string()), None, None, None, dolby_vision, Some(error_message)]);
+            }
+            Err(error_message) => {
+                warn!("{}: {}", path, error_message);
+                return None;
+            }
+        };
+        let (preset, matches) = match &determination {
+            Determination::Match(preset_name) => (Some(preset_name.clone()), Some(preset_name.clone())),
+            Determination::Ambiguous(matching_presets) => (None, Some(matching_presets.join(","))),
+            _ => (None, None),
+        };
+        Some(vec![Some(path.to_string()), Some(determination.status().to_string()), preset, matches, closest, dolby_vision, None])
+    }
+
+    /// Updates to the latest release, as described in `Command::SelfUpdate`.
+    #[cfg(feature = "self-update")]
+    pub fn self_update(&self, check: bool) -> Result<String, String> {
+        let release = update::latest_release()?;
+        let current = env!("CARGO_PKG_VERSION");
+        if !check {
+            update::update(&release)
+        } else if update::is_newer(&release.version, current) {
+            Ok(format!("determine-preset {} is available (this is {})\n", release.version, current))
+        } else {
+            Ok(format!("determine-preset {} is the latest version\n", current))
+        }
+    }
+
+    #[cfg(not(feature = "self-update"))]
+    pub fn self_update(&self, _check: bool) -> Result<String, String> {
+        Err("determine-preset was built without the `self-update` feature, so update it the way it was installed, e.g. with `cargo install`".to_string())
+    }
+
+    /// Answers requests on a Unix socket until the process is killed, as described in
+    /// `Command::Daemon`. The presets are only loaded once, so requests are cheap, and files
+    /// that didn't change since they were last requested aren't read again.
+    #[cfg(unix)]
+    pub fn daemon(&self, socket_path: &str, metrics_address: Option<&str>) -> Result<String, String> {
+        let metrics = metrics::Metrics::default();
+        let cache = daemon::ResponseCache::default();
+        let metrics_listener = metrics_address
+            .map(|address| std::net::TcpListener::bind(address).map_err(|err| format!("Could not serve the metrics on {}: {}", address, err)))
+            .transpose()?;
+        std::thread::scope(|scope| {
+            if let Some(listener) = metrics_listener {
+                info!("Serving the metrics at http://{}/metrics", metrics_address.unwrap_or_default());
+                scope.spawn(|| metrics::serve(listener, &metrics));
+            }
+            daemon::serve(socket_path, |request| self.daemon_response(request, &cache, &metrics))
+        })?;
+        Ok(String::new())
+    }
+
+    #[cfg(not(unix))]
+    pub fn daemon(&self, _socket_path: &str, _metrics_address: Option<&str>) -> Result<String, String> {
+        Err("The daemon needs Unix sockets, which this platform doesn't have".to_string())
+    }
+
+    /// The JSON object answering a daemon request for the preset of the input at `path`. The
+    /// cache holds the status, preset and response of the files answered before.
+    #[cfg(unix)]
+    fn daemon_response(&self, path: &str, cache: &daemon::ResponseCache<(&'static str, String, String)>, metrics: &metrics::Metrics) -> String {
+        let _span = info_span!("request", path = %path).entered();
+        let start = std::time::Instant::now();
+        let cached = cache.get(path);
+        metrics.record_cache_lookup(cached.is_some());
+        if let Some((status, preset_name, response)) = cached {
+            metrics.record_determination(status, &preset_name, start.elapsed());
+            return response;
+        }
+        let result = if path == "-" {
+            Err((exit_code::ERROR, "The daemon can't read from STDIN".to_string()))
+        } else {
+            input::read_input(Some(path))
+                .map_err(|err| (err.exit_code(), err.to_string()))
+                .and_then(|input| {
+                    let summary = self.summary_from_str(&input).map_err(|error_message| (exit_code::ERROR, error_message))?;
+                    Ok((summary, input))
+                })
+        };
+        match result {
+            Ok((summary, input)) => {
+                let status = summary.determination.status();
+                let preset_name = summary.preset.clone().unwrap_or_default();
+                metrics.record_determination(status, &preset_name, start.elapsed());
+                let settings = self.normalized_settings(&input).expect("The input was parsed before");
+                let response = self.json_summary(&summary, &input, &settings, true);
+                cache.insert(path, (status, preset_name, response.clone()));
+                response
+            }
+            Err((exit_code, error_message)) => {
+                metrics.record_error(error_code(exit_code), start.elapsed());
+                json_failure(exit_code, &error_message)
+            }
+        }
+    }
+
+    /// Checks the matcher against the samples in a directory (see `Command::Selftest`), and
+    /// prints the ones that fail and how many pass. It fails if any sample fails.
+    pub fn selftest(&self, dir: &str) -> Result<String, String> {
+        if !std::path::Path::new(dir).is_dir() {
+            return Err(format!("{} is not a directory", dir));
+        }
+        let paths = input::expand_directories(&[dir.to_string()])?;
+        let mut failures = Vec::new();
+        for path in &paths {
+            let result = std::fs::read_to_string(path).map_err(|err| format!("could not be read: {}", err)).and_then(|text| {
+                let (expected, input) = selftest::parse_sample(&text)?;
+                let determination = self.determination(&self.normalized_settings(input)?).for_input(input);
+                selftest::check(expected, &determination).map_err(|got| format!("expected {}, got {}", expected, got))
+            });
+            if let Err(error_message) = result {
+                failures.push(format!("FAIL {}: {}\n", path, error_message));
+            }
+        }
+        let summary = format!("{} passed, {} failed", paths.len() - failures.len(), failures.len());
+        if failures.is_empty() {
+            return Ok(format!("{}\n", summary));
+        }
+        Err(format!("{}{}", failures.concat(), summary))
+    }
+
+    /// Matches a corpus `rounds` times, as described in `Command::Bench`, and reports the
+    /// throughput of the fastest round and the allocations per determination.
+    pub fn bench(&self, paths: &[String], count: usize, rounds: usize) -> Result<String, String> {
+        let corpus = if paths.is_empty() {
+            bench::synthetic_corpus(&self.presets, count)
+        } else {
+            let mut corpus = Vec::new();
+            for path in input::find_videos(paths)? {
+                let text = input::read_input(Some(&path))?;
+                let videos = input::split_dump(&text);
+                if videos.is_empty() {
+                    corpus.push(text);
+                } else {
+                    corpus.extend(videos.into_iter().map(|(_, video)| video.to_string()));
+                }
+            }
+            corpus
+        };
+        if corpus.is_empty() {
+            return Err("There are no inputs to match".to_string());
+        }
+        let mut fastest = std::time::Duration::MAX;
+        let (allocations_before, bytes_before) = bench::allocations();
+        bench::count_allocations(true);
+        for _ in 0..rounds.max(1) {
+            let start = std::time::Instant::now();
+            for input in &corpus {
+                // errors are part of the work measured, like in a scan
+                let _ = std::hint::black_box(self.determination_from_str(input));
+            }
+            fastest = fastest.min(start.elapsed());
+        }
+        bench::count_allocations(false);
+        let (allocations_after, bytes_after) = bench::allocations();
+        let determinations = (corpus.len() * rounds.max(1)) as f64;
+        let fastest_of = if rounds > 1 { format!(" (the fastest of {} rounds)", rounds) } else { String::new() };
+        let mut report = format!(
+            "Matched {} inputs in {:.3} s{}: {:.0} determinations/second\n",
+            corpus.len(),
+            fastest.as_secs_f64(),
+            fastest_of,
+            corpus.len() as f64 / fastest.as_secs_f64()
+        );
+        if allocations_after > 0 {
+            report.push_str(&format!(
+                "{:.1} allocations and {:.0} bytes allocated per determination\n",
+                (allocations_after - allocations_before) as f64 / determinations,
+                (bytes_after - bytes_before) as f64 / determinations
+            ));
+        }
+        Ok(report)
+    }
+
+    /// Checks that all chunks of an encode have the same settings. Each parameter's most common
+    /// value is taken as the intended one, and the chunks that have another value are reported.
+    pub fn chunks(&self, inputs: &[String]) -> Result<String, String> {
+        let paths = input::expand_directories(inputs)?;
+        // an encode can have thousands of chunks, with mostly the same settings
+        let mut interner = intern::Interner::default();
+        let chunk_settings = paths.iter()
+            .map(|path| {
+                let settings = self.normalized_settings(&input::read_input(Some(path))?)?;
+                Ok(interner.intern_settings(settings.iter().map(|(param_name, value)| (param_name.as_str(), value.as_str()))))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        debug!("The settings of {} chunks have {} distinct names and values", chunk_settings.len(), interner.len());
+        if chunk_settings.is_empty() {
+            return Err("No chunks found".to_string());
+        }
+
+        let outliers = chunk_outliers(&chunk_settings);
+        if outliers.is_empty() {
+            return Ok(format!("All {} chunks used the same settings ({})\n", paths.len(), self.preset_summary(&intern::owned_settings(&chunk_settings[0]))));
+        }
+        let mut report = format!("{} of {} chunks used different settings:\n", outliers.len(), paths.len());
+        for (chunk, differences) in outliers {
+            report.push_str(&format!("  {} ({})\n", paths[chunk], self.preset_summary(&intern::owned_settings(&chunk_settings[chunk]))));
+            for (param_name, value, usual_value) in differences {
+                report.push_str(&format!("    {}={} (other chunks: {})\n", param_name, value, usual_value));
+            }
+        }
+        Err(report.trim_end().to_string())
+    }
+
+    /// A short description of which preset the settings match, for use in a table cell.
+    fn preset_summary(&self, settings: &HashMap<String, String>) -> String {
+        self.determination_summary(self.determination(settings), || self.closest_matches(settings).remove(0).0)
+    }
+
+    /// Like `preset_summary`, without copying the input's settings unless none of the presets
+    /// match.
+    fn preset_summary_from_str(&self, input: &str) -> Result<String, String> {
+        let (determination, _) = self.determination_from_str(input)?;
+        Ok(self.determination_summary(determination, || {
+            self.closest_matches(&self.normalized_settings(input).expect("The input was parsed before")).remove(0).0
+        }))
+    }
+
+    /// The `preset_summary` of a determination. `closest_preset` is only called if no preset
+    /// matches.
+    fn determination_summary(&self, determination: Determination, closest_preset: impl FnOnce() -> String) -> String {
+        match determination {
+            Determination::Match(preset_name) => self.preset_label(&preset_name),
+            Determination::Ambiguous(matching_presets) => {
+                format!("{}: {}", self.tr("ambiguous"), matching_presets.iter().map(|preset_name| self.preset_label(preset_name)).collect::<Vec<_>>().join(", "))
+            }
+            Determination::NoMatch => format!("{} ({}: {})", self.tr("no match"), self.tr("closest"), closest_preset()),
+            Determination::InsufficientInput => self.tr("no encoder settings found").to_string(),
+            Determination::NotDerivable { encoder } => self.lang().not_derivable_summary(&encoder),
+        }
+    }
+
+    /// The settings an encoder command line resolves to, as `key=value` pairs on one line.
+    pub fn resolve(&self, command: &[String]) -> Result<String, String> {
+        let settings = resolve::resolve(command, &self.presets)?;
+        let pairs = ordered_settings(&settings).iter().map(|(param_name, value)| format!("{}={}", param_name, value)).collect::<Vec<_>>();
+        Ok(format!("{}\n", pairs.join(" ")))
+    }
+
+    /// Looks up a preset's settings by name.
+    fn preset(&self, preset_name: &str) -> Result<&HashMap<String, String>, String> {
+        let name = self.preset_name(preset_name);
+        self.presets.iter()
+            .find(|(preset_name, _)| preset_name == name)
+            .map(|(_, settings)| settings)
+            .ok_or_else(|| format!(
+                "Unknown preset: {}. Known presets: {}, or their numbers from 0 to {}",
+                preset_name,
+                self.presets.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", "),
+                self.presets.len() - 1
+            ))
+    }
+
+    pub fn determine_preset_from_str(&self, input: &str) -> Result<String, String> {
+        // Determine the preset by matching the settings.
+        let settings = self.normalized_settings(input)?;
+        self.preset_or_reason(self.determination(&settings).for_input(input), input, &settings)
+    }
+
+    /// Parses the input into a HashMap of key-value pairs, in the form the preset tables use.
+    /// If the input is an x265 or ffmpeg command line, the settings it would encode with are
+    /// worked out instead. The `--ignore`d parameters are left out.
+    fn normalized_settings(&self, input: &str) -> Result<HashMap<String, String>, String> {
+        let mut settings = matcher::normalized_settings(input, &self.presets)?;
+        for param_name in &self.cli.ignore {
+            settings.remove(params::find(param_name).map_or(param_name.as_str(), |param| param.name));
+        }
+        Ok(settings)
+    }
+
+    /// The preset that is the best starting point for reproducing the settings, and the
+    /// parameters that have to be changed from it. If several presets match exactly, the fastest
+    /// is used.
+    pub fn reproduction(&self, settings: &HashMap<String, String>) -> (String, Vec<(String, String)>) {
+        let preset_name = self.matching_presets(settings).into_iter().next()
+            .unwrap_or_else(|| self.closest_matches(settings).remove(0).0);
+        let preset_settings = self.preset(&preset_name).expect("The preset was found by name");
+        let overrides = params::PARAMS.iter()
+            .filter_map(|param| settings.get_key_value(param.name))
+            .filter(|(param_name, value)| preset_settings.get(*param_name).is_some_and(|preset_value| preset_value != *value))
+            .map(|(param_name, value)| (param_name.to_owned(), value.to_owned()))
+            .collect();
+        (preset_name, overrides)
+    }
+
+    /// An ffmpeg command line that would encode a video with the same settings as the input:
+    /// the closest preset and the tune, the CRF if there is one, the preset's parameters that
+    /// differ from it, and the other x265 options that aren't at their defaults, like `psy-rd`
+    /// and `keyint` (but not those in `params::RUN_OPTIONS`).
+    pub fn ffmpeg_command(&self, input: &str) -> Result<String, String> {
+        let settings = self.normalized_settings(input)?;
+        let (preset_name, overrides) = self.reproduction(&settings);
+        let mut other_settings = settings.iter()
+            .filter(|(param_name, value)| params::is_known(param_name) && !params::is_preset_param(param_name) && !params::RUN_OPTIONS.contains(&param_name.as_str())
+                && !params::is_default(param_name, value) && !encoding::is_knob_default(param_name, value, Some(&preset_name)))
+            .collect::<Vec<_>>();
+        other_settings.sort();
+
+        let source = match &self.cli.determine.input {
+            Some(path) if path != "-" => shell_quote(path),
+            _ => "input.mkv".to_string(),
+        };
+        let mut command = format!("ffmpeg -i {} -c:v libx265 -preset {}", source, preset_name);
+        if let Some(tune) = settings.get("tune") {
+            command.push_str(&format!(" -tune {}", shell_quote(tune)));
+        }
+        if let Some(crf) = settings.get("crf") {
+            command.push_str(&format!(" -crf {}", shell_quote(crf)));
+        }
+        if !overrides.is_empty() || !other_settings.is_empty() {
+            // `:` separates the parameters, so pairs like `deblock=-1:-1` take x265's other form
+            let x265_params = overrides.iter().map(|(param_name, value)| (param_name.as_str(), value.as_str()))
+                .chain(other_settings.iter().map(|(param_name, value)| (param_name.as_str(), value.as_str())))
+                .map(|(param_name, value)| params::x265_param(param_name, &value.replace(':', ",")))
+                .collect::<Vec<_>>();
+            command.push_str(&format!(" -x265-params {}", shell_quote(&x265_params.join(":"))));
+        }
+        command.push_str(" output.mkv");
+        Ok(command)
+    }
+
+    /// Whether to use colors. In `auto` mode, the NO_COLOR, CLICOLOR_FORCE and CLICOLOR
+    /// environment conventions are respected before falling back to checking for a terminal.
+    fn use_color(&self) -> bool {
+        // any value of NO_COLOR disables colors (no-color.org), but CLICOLOR_FORCE=0 doesn't force them
+        let env_set = |name| std::env::var_os(name).is_some_and(|value| !value.is_empty() && value != "0");
+        match self.cli.color {
+            ColorMode::Auto if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) => false,
+            ColorMode::Auto if env_set("CLICOLOR_FORCE") => true,
+            ColorMode::Auto if std::env::var_os("CLICOLOR").is_some_and(|value| value == "0") => false,
+            ColorMode::Auto => atty::is(atty::Stream::Stdout),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+
+    /// Whether to make parameter names into hyperlinks to the documentation.
+    fn use_links(&self) -> bool {
+        match self.cli.hyperlinks {
+            ColorMode::Auto => atty::is(atty::Stream::Stdout) && std::env::var("TERM").map_or(true, |term| term != "dumb"),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+
+    /// The width the output should fit in: the `--width` option, or else the width of the
+    /// terminal if STDOUT is one.
+    fn layout_width(&self) -> Option<usize> {
+        self.cli.width.or_else(|| terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize))
+    }
+
+    /// Gives output for the candidate matches to be compared visually. Each preset column
+    /// is labelled with the percentage of the input's parameters it matches:
+    ///
+    /// ```text
+    ///              | input | slow (75%) | veryslow (50%) | placebo (25%)
+    /// -------------------------------------------------------------------
+    /// merange      | 57    | 57         | 57             | 92
+    /// aq-mode      | 4     | 2          | 2              | 2
+    /// subme        | 3     | 3          | 4              | 5
+    /// b-adapt      | 2     | 2          | 2              | 2
+    /// -------------------------------------------------------------------
+    /// match/differ |       | 3/1        | 2/2            | 1/3
+    /// ```
+    ///
+    /// The footer counts, per preset, how many of the displayed parameters match the input
+    /// and how many differ.
+    ///
+    /// With `-vv`, parameters that are not in both the input and the presets are shown too.
+    /// A parameter missing from the input is shown as `·`: the input may have been truncated,
+    /// but it doesn't contradict any preset. A parameter the preset doesn't define is shown as
+    /// `n/a`: the preset accepts any value for it, so it can't help tell presets apart.
+    ///
+    /// The parameters are in the `--param-order`, where `input` gives the order of the input's.
+    pub fn partially_matching_presets(&self, input: &str, settings: &HashMap<String, String>) -> String {
+        let comparison = self.comparison(input, settings);
+        let mut table = Table::default();
+        table.header.push(Cell::new(""));
+        table.header.push(Cell::new(self.tr("input")));
+        for (preset_name, confidence) in &comparison.presets {
+            // label each preset with how well it matches, so the ranking is visible in the table:
+            table.header.push(Cell::new(format!("{} ({}%)", preset_name, confidence)));
+        }
+
+        for comparison_row in &comparison.rows {
+            let mut param_cell = Cell::new(comparison_row.param_name.as_str());
+            param_cell.link = params::doc_url(&comparison_row.param_name);
+            let input_value = comparison_row.input_value.as_deref().unwrap_or(MISSING_IN_INPUT);
+            let mut row = vec![param_cell, Cell::highlighted(input_value, Highlight::Bold)];
+            for (value, is_match) in &comparison_row.preset_values {
+                row.push(Cell::highlighted(value.as_deref().unwrap_or(NOT_IN_PRESET), if *is_match { Highlight::Match } else { Highlight::None }));
+            }
+            table.rows.push(row);
+        }
+
+        // summarize each column in a footer, counting the matching and differing values among the displayed parameters:
+        let mut footer = vec![Cell::new(self.tr("match/differ")), Cell::new("")];
+        for (matched, differed) in comparison.counts() {
+            footer.push(Cell::new(format!("{}/{}", matched, differed)));
+        }
+        table.footer.push(footer);
+        // and how many of all the input's parameters would have to change to match each preset exactly:
+        let closest = self.closest_match_details(settings);
+        let mut footer = vec![Cell::new(self.tr("changes needed")), Cell::new("")];
+        for (preset_name, _) in &comparison.presets {
+            let changes_needed = closest.iter().find(|closest| &closest.preset == preset_name).map_or(0, |closest| closest.changes_needed());
+            footer.push(Cell::new(changes_needed.to_string()));
+        }
+        table.footer.push(footer);
+
+        // drop the least similar presets if the table would be too wide, but always show the best match
+        if let Some(width) = self.layout_width() {
+            table.fit_to_width(self.cli.table_style, width, 3);
+        }
+
+        // with -vvv, describe each parameter and how its values divide the presets. This column
+        // is long, so it's added after fitting the table to the width, rather than crowding out presets.
+        if self.cli.determine.verbose >= 3 {
+            table.header.push(Cell::new(self.tr("notes")));
+            for row in &mut table.rows {
+                let note = self.parameter_note(&row[0].text);
+                row.push(Cell::new(note));
+            }
+            for row in &mut table.footer {
+                row.push(Cell::new(""));
+            }
+        }
+
+        table.render(self.cli.table_style, self.use_color(), self.use_links())
+    }
+
+    /// The comparison of the input with the closest presets that `partially_matching_presets`
+    /// shows, for rendering it in other forms, like the JSON output. It has the same rows as
+    /// the table at the same verbosity.
+    pub fn comparison(&self, input: &str, settings: &HashMap<String, String>) -> Comparison {
+        let mut preset_enc_params = self.presets.iter().map(|(_, params)| params).next().expect("There must be a preset").keys().collect::<Vec<_>>();
+        self.sort_params(input, &mut preset_enc_params, |param_name| param_name);
+        let mut input_keys = settings.keys().collect::<Vec<_>>();
+        self.sort_params(input, &mut input_keys, |param_name| param_name);
+        // Filter these to not contain keys that don't match:
+        let preset_enc_params = if self.cli.determine.verbose < 2 {
+            preset_enc_params.into_iter().filter(|param_name| input_keys.contains(param_name)).collect::<Vec<_>>()
+        } else {
+            preset_enc_params
+        };
+        let input_keys = if self.cli.determine.verbose < 2 {
+            input_keys.into_iter().filter(|param_name| preset_enc_params.contains(param_name)).collect::<Vec<_>>()
+        } else {
+            input_keys
+        };
+
+        // manipulate the data into more convenient formats:
+        let preset_names = self.closest_matches(settings).into_iter().map(|(preset_name, _)| preset_name).take(3).collect::<Vec<_>>();
+        // select which presets to show, in order of similarity, and filter the encoding params within
+        let presets = preset_names.iter().filter_map(|preset_name| self.presets.iter().find(|(name, _)| name == preset_name)).map(|(preset_name, encoder_values)| {
+            let header = (preset_name.clone(), self.match_percentage(settings, encoder_values));
+            let encoder_values = encoder_values.iter().filter(|(param_name, _)| {
+                preset_enc_params.contains(param_name)
+            }).collect::<HashMap<_, _>>();
+            (header, encoder_values)
+        }).collect::<Vec<_>>();
+        let settings = input_keys.iter().map(|param_name| (*param_name, &settings[*param_name])).collect::<IndexMap<_, _>>();
+
+        // with -vv, the preset parameters that are missing from the input get rows too:
+        let missing_in_input = preset_enc_params.iter().copied().filter(|param_name| !settings.contains_key(param_name));
+        let rows = settings.keys().copied().chain(missing_in_input)
+            .map(|param_name| ComparisonRow {
+                param_name: param_name.clone(),
+                input_value: settings.get(param_name).map(|value| value.to_string()),
+                preset_values: presets.iter()
+                    .map(|(_, preset_values)| {
+                        let value = preset_values.get(param_name).map(|value| value.to_string());
+                        let is_match = value.is_some() && value.as_ref() == settings.get(param_name).copied();
+                        (value, is_match)
+                    })
+                    .collect(),
+            })
+            .collect();
+        Comparison { presets: presets.into_iter().map(|(preset_name, _)| preset_name).collect(), rows }
+    }
+
+    /// Sorts parameters, given by their names, in the `--param-order`. `input` is the text the
+    /// settings were read from, for the `input` order.
+    fn sort_params<T>(&self, input: &str, parameters: &mut [T], param_name: impl Fn(&T) -> &str) {
+        let input_order = match self.cli.param_order {
+            ParamOrder::Input => crate::settings_order(input),
+            ParamOrder::Table | ParamOrder::Name => Vec::new(),
+        };
+        let table_position = |name: &str| params::PARAMS.iter().position(|param| param.name == name).unwrap_or(usize::MAX);
+        parameters.sort_by_cached_key(|parameter| {
+            let name = param_name(parameter);
+            match self.cli.param_order {
+                ParamOrder::Input | ParamOrder::Table => (input_order.iter().position(|input_name| *input_name == name).unwrap_or(usize::MAX), table_position(name), name.to_string()),
+                ParamOrder::Name => (0, 0, name.to_string()),
+            }
+        });
+    }
+
+    /// A one-line description of a parameter, followed by the values it takes across the presets,
+    /// e.g. "... 2: ultrafast-fast, 3: medium, 4: slow, 6: slower-placebo".
+    fn parameter_note(&self, param_name: &str) -> String {
+        // group the consecutive presets that have the same value
+        let mut groups: Vec<(&str, &str, &str)> = Vec::new();
+        for (preset_name, settings) in self.presets.iter() {
+            let Some(value) = settings.get(param_name) else { continue };
+            match groups.last_mut() {
+                Some((group_value, _, last)) if *group_value == value.as_str() => *last = preset_name,
+                _ => groups.push((value, preset_name, preset_name)),
+            }
+        }
+        let distinguishes = match groups.len() {
+            0 => "Not set by the presets.".to_string(),
+            1 => "The same in all presets.".to_string(),
+            _ => groups.iter().map(|(value, first, last)| {
+                if first == last {
+                    format!("{}: {}", value, first)
+                } else {
+                    format!("{}: {}-{}", value, first, last)
+                }
+            }).collect::<Vec<_>>().join(", "),
+        };
+        match params::find(param_name) {
+            Some(param) => format!("{} {}", param.description, distinguishes),
+            None => distinguishes,
+        }
+    }
+
+    /// The presets by how many of the input's parameters they match, as `matcher::closest_matches`
+    /// orders them: ties are listed from the fastest preset to the slowest.
+    pub fn closest_matches(&self, settings: &HashMap<String, String>) -> Vec<(String, usize)> {
+        matcher::closest_matches(settings, &self.presets)
+    }
+
+    /// Like `closest_matches`, with the number of parameters compared with each preset and the
+    /// parameters that differ.
+    pub fn closest_match_details(&self, settings: &HashMap<String, String>) -> Vec<matcher::ClosestMatch> {
+        matcher::closest_match_details(settings, &self.presets)
+    }
+
+    /// How many of a preset's parameters the settings have, as `matcher::coverage`. `None` if
+    /// there is no such preset.
+    pub fn coverage(&self, settings: &HashMap<String, String>, preset_name: &str) -> Option<matcher::Coverage> {
+        let (_, preset_settings) = self.presets.iter().find(|(name, _)| name == preset_name)?;
+        Some(matcher::coverage(settings, preset_settings))
+    }
+
+    /// The percentage of the input parameters defined by the preset that have the same value
+    /// in the preset. Parameters the preset does not define are not counted.
+    pub fn match_percentage(&self, settings: &HashMap<String, String>, preset_settings: &HashMap<String, String>) -> usize {
+        matcher::match_percentage(settings, preset_settings)
+    }
+
+    /// Determines which x265 preset matches the given encoder parameters.
+    pub fn determine_preset(&self, settings: &HashMap<String, String>) -> Result<String, String> {
+        self.preset_or_reason(self.determination(settings), "", settings)
+    }
+
+    /// The label of the matching preset, or why there is no single match. `input` is what the
+    /// settings were read from, for the order of the parameters in the verbose table.
+    fn preset_or_reason(&self, determination: Determination, input: &str, settings: &HashMap<String, String>) -> Result<String, String> {
+        match determination {
+            Determination::Match(preset_name) => Ok(self.preset_label(&preset_name)),
+            Determination::Ambiguous(matching_presets) => Err(format!(
+                "{}: {}",
+                self.tr("Multiple matching presets found"),
+                matching_presets.iter().map(|name| self.colorize(name, Highlight::Match)).collect::<Vec<_>>().join(", ")
+            )),
+            Determination::NoMatch if self.cli.determine.verbose > 0 => {
+                Err(format!("{}. {}:\n\n{}{}", self.tr("No matching presets found"), self.tr("Partial matches"), self.partially_matching_presets(input, settings), self.speed_estimate(settings)))
+            }
+            Determination::NoMatch => {
+                Err(format!("{}. {}:\n{}{}", self.tr("No matching presets found"), self.tr("Closest matches"), self.format_closest_matches(settings), self.speed_estimate(settings)))
+            }
+            determination => matcher::preset_or_reason(determination, settings, &self.presets, self.lang()),
+        }
+    }
+
+    /// Matches the settings against the presets, with the index unless another matcher than
+    /// the exact one is used.
+    pub fn determination(&self, settings: &HashMap<String, String>) -> Determination {
+        if !self.matcher.is_exact() {
+            return matcher::determination_with(&*self.matcher, settings, &self.presets);
+        }
+        self.index.determination(settings.iter().map(|(param_name, value)| (param_name.as_str(), value.as_str())))
+    }
+
+    /// Matches the input like `determination(&normalized_settings(input))`, but borrows the
+    /// settings from the input instead of copying them, unless it is a command line. The scan
+    /// and the daemon match many inputs this way.
+    fn determination_from_str(&self, input: &str) -> Result<(Determination, encoding::Details), String> {
+        let Some(mut settings) = matcher::borrowed_settings(input).filter(|_| self.matcher.is_exact()) else {
+            let settings = self.normalized_settings(input)?;
+            let determination = self.determination(&settings).for_input(input);
+            self.check_embedded_preset(settings.get("preset").map(String::as_str), &determination);
+            let details = encoding::details(input, |name| settings.get(name).map(String::as_str));
+            self.check_interlacing(details.interlacing.as_ref(), &determination);
+            return Ok((determination, details));
+        };
+        let details = encoding::details(input, |name| settings.get(name).copied());
+        for param_name in &self.cli.ignore {
+            settings.remove(params::find(param_name).map_or(param_name.as_str(), |param| param.name));
+        }
+        let embedded_preset = settings.get("preset").copied();
+        let determination = self.index.determination(settings).for_input(input);
+        self.check_embedded_preset(embedded_preset, &determination);
+        self.check_interlacing(details.interlacing.as_ref(), &determination);
+        Ok((determination, details))
+    }
+
+    /// The input's parameters that no preset sets and that aren't x265 options (see
+    /// `params::is_known`), by name. They're ignored by the matching, so they're reported in
+    /// case the input's metadata was stripped or mangled.
+    fn unknown_params<'a>(&self, settings: &'a HashMap<String, String>) -> Vec<&'a str> {
+        let mut unknown_params = settings.keys()
+            .filter(|param_name| !params::is_known(param_name) && !self.presets.iter().any(|(_, preset_settings)| preset_settings.contains_key(*param_name)))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        unknown_params.sort();
+        unknown_params
+    }
+
+    /// Notes what the settings record about the encode besides its preset, for `-v`.
+    fn report_details(&self, details: &encoding::Details, determination: &Determination) {
+        let preset_name = match determination {
+            Determination::Match(preset_name) => Some(preset_name.as_str()),
+            _ => None,
+        };
+        if let Some(category) = encoding::quality_category(details, preset_name) {
+            info!("Category: {}", category);
+        }
+        if let Some(latency) = &details.latency {
+            info!("Latency: {}", latency);
+        }
+        if let Some(rate_control) = &details.rate_control {
+            info!("Rate control: {}", rate_control);
+        }
+        if let Some(profile) = &details.profile {
+            info!("Profile: {}", profile);
+        }
+        if let Some(gop) = &details.gop {
+            info!("GOP: {}", gop);
+        }
+        if let Some(x265_era) = &details.x265_era {
+            info!("Encoder release: {}", x265_era);
+        }
+        if let Some(pixel_format) = details.pixel_format() {
+            info!("Pixel format: {}", pixel_format);
+        }
+        if let Some(dynamic_range) = details.color.describe() {
+            info!("Dynamic range: {}", dynamic_range);
+        }
+        if let Some(dolby_vision) = &details.dolby_vision {
+            info!("{}", dolby_vision);
+        }
+        if let Some(interlacing) = &details.interlacing {
+            info!("Scan: {}", interlacing);
+        }
+        if let Some(vbv) = &details.vbv {
+            info!("VBV: {}; constrained for device or streaming compatibility, which limits the quality at any preset", vbv);
+        }
+        if !details.quality_knobs.is_empty() {
+            info!("Quality parameters that don't depend on the preset:");
+            for (name, value) in &details.quality_knobs {
+                if encoding::is_knob_default(name, value, preset_name) {
+                    info!("  {}={} (default)", name, value);
+                } else {
+                    info!("  {}={} (default: {})", name, value, encoding::knob_default(name, preset_name).unwrap_or("?"));
+                }
+            }
+        }
+    }
+
+    /// Warns if the settings name a preset (with `preset=`, which some tools add to the settings
+    /// string), but match other presets.
+    fn check_embedded_preset(&self, embedded_preset: Option<&str>, determination: &Determination) {
+        if let Some(conflict) = self.embedded_preset_conflict(embedded_preset, determination) {
+            warn!("{}", conflict);
+        }
+    }
+
+    /// The presets that the container's tags name (see `tag_hints`).
+    fn tag_hints(&self, input: &str) -> Vec<(String, String)> {
+        let preset_names = self.presets.iter().map(|(preset_name, _)| preset_name.as_str()).collect::<Vec<_>>();
+        crate::tag_hints(input, &preset_names)
+    }
+
+    /// Describes a preset named by a tag, which is unverified, and whether the settings agree.
+    fn tag_hint_message(&self, tag: &str, preset_name: &str, determination: &Determination) -> String {
+        let agreement = match determination {
+            Determination::Match(matching_preset) if matching_preset == preset_name => "the settings agree",
+            Determination::Ambiguous(matching_presets) if matching_presets.iter().any(|matching_preset| matching_preset == preset_name) => "the settings agree",
+            Determination::Match(_) | Determination::Ambiguous(_) | Determination::NoMatch => "the settings disagree",
+            Determination::InsufficientInput | Determination::NotDerivable { .. } => "there are no settings to check it against",
+        };
+        format!("Unverified hint: the {} tag names the {} preset, and {}", tag, self.preset_label(preset_name), agreement)
+    }
+
+    /// Warns if the encode is interlaced and doesn't match a single preset, as the mismatch may
+    /// come from the interlacing rather than from changed settings.
+    fn check_interlacing(&self, interlacing: Option<&encoding::Interlacing>, determination: &Determination) {
+        if let Some(warning) = self.interlacing_warning(interlacing, determination) {
+            warn!("{}", warning);
+        }
+    }
+
+    /// Why an interlaced encode may not match its preset, if it doesn't match a single one.
+    fn interlacing_warning(&self, interlacing: Option<&encoding::Interlacing>, determination: &Determination) -> Option<String> {
+        if !matches!(determination, Determination::Ambiguous(_) | Determination::NoMatch) {
+            return None;
+        }
+        let interlacing = interlacing?;
+        let mut warning = format!("The encode is {}. Field-based encodes alter several defaults, like the picture height and frame rate x265 works with, so their settings can differ from the preset's", interlacing);
+        if interlacing.field_coding == Some(false) {
+            warning.push_str(". Without field coding, another tool split the frames into fields for x265, and may have changed settings too");
+        }
+        Some(warning)
+    }
+
+    /// How the preset that the settings name disagrees with the presets they match, if it does,
+    /// which means that the metadata was edited, or is stale.
+    fn embedded_preset_conflict(&self, embedded_preset: Option<&str>, determination: &Determination) -> Option<String> {
+        let embedded_preset = embedded_preset?;
+        let preset_name = self.preset_name(embedded_preset);
+        let derived = match determination {
+            Determination::Match(matching_preset) if matching_preset.eq_ignore_ascii_case(preset_name) => return None,
+            Determination::Ambiguous(matching_presets) if matching_presets.iter().any(|matching_preset| matching_preset.eq_ignore_ascii_case(preset_name)) => return None,
+            Determination::Match(matching_preset) => format!("they match {}", matching_preset),
+            Determination::Ambiguous(matching_presets) => format!("they match {}", matching_presets.join(", ")),
+            Determination::NoMatch => "they match none of the presets".to_string(),
+            Determination::InsufficientInput | Determination::NotDerivable { .. } => return None,
+        };
+        Some(format!("The settings name the {} preset, but {}. The metadata may have been edited, or be stale", embedded_preset, derived))
+    }
+
+    /// Lists the presets from most to least similar, one per line, highlighting the closest ones.
+    fn format_closest_matches(&self, settings: &HashMap<String, String>) -> String {
+        let matches = self.closest_match_details(settings);
+        let best_count = matches.first().map_or(0, |closest| closest.matched);
+        let name_width = matches.iter().map(|closest| closest.preset.len()).max().unwrap_or(0);
+        matches.iter().map(|closest| {
+            let highlight = if closest.matched == best_count { Highlight::Match } else { Highlight::None };
+            let padding = " ".repeat(name_width - closest.preset.len());
+            let matching = self.tr(if closest.matched == 1 { "matching parameter" } else { "matching parameters" });
+            format!("  {}{}  {} {}, {} {}", self.colorize(&closest.preset, highlight), padding, closest.matched, matching, closest.changes_needed(), self.tr("to change"))
+        }).collect::<Vec<_>>().join("\n")
+    }
+
+    /// A line on where settings that match no preset fall between the presets, or nothing if
+    /// their parameters don't tell the presets apart.
+    fn speed_estimate(&self, settings: &HashMap<String, String>) -> String {
+        matcher::speed_estimate(settings, &self.presets).map_or_else(String::new, |estimate| format!("\n{}: {}", self.tr("Estimated speed"), estimate))
+    }
+
+    /// Translates an English message into the `--lang`.
+    fn tr(&self, message: &'static str) -> &'static str {
+        self.lang().tr(message)
+    }
+
+    fn lang(&self) -> Lang {
+        self.cli.lang.unwrap_or_default()
+    }
+
+    /// Highlights a bit of text the same way the verbose table does, if colors are enabled.
+    fn colorize(&self, text: &str, highlight: Highlight) -> String {
+        Cell::highlighted(text, highlight).render(self.use_color(), false)
+    }
+
+    /// The names of all presets that match the settings.
+    pub fn matching_presets(&self, settings: &HashMap<String, String>) -> Vec<String> {
+        matcher::matching_presets(settings, &self.presets)
+    }
+
+    /// Checks if the given `input_settings` match a preset's settings.
+    pub fn preset_matches(&self, input_settings: &HashMap<String, String>, preset_settings: &HashMap<String, String>) -> bool {
+        matcher::preset_matches(input_settings, preset_settings)
+    }
+}
+
+/// The chunks (by index) that have a parameter whose value differs from the most common value
+/// among the chunks, with the parameter, the chunk's value and the most common value. A missing
+/// parameter counts as a value.
+fn chunk_outliers(chunk_settings: &[intern::InternedSettings]) -> Vec<(usize, Vec<Difference>)> {
+    let mut param_names = chunk_settings.iter().flat_map(|settings| settings.keys()).collect::<Vec<_>>();
+    param_names.sort();
+    param_names.dedup();
+
+    let mut outliers: Vec<(usize, Vec<Difference>)> = Vec::new();
+    for param_name in param_names {
+        // the values are interned, so they are compared by pointer
+        let values = chunk_settings.iter().map(|settings| settings.get(param_name)).collect::<Vec<_>>();
+        // the most common value; ties go to the value of the earliest chunk
+        let count = |value: &Option<&intern::Symbol>| values.iter().filter(|other| *other == value).count();
+        let usual_value = values.iter().rev().max_by_key(|value| count(value)).expect("There is at least one chunk");
+        let text = |value: &Option<&intern::Symbol>| value.map_or(MISSING_IN_INPUT, |value| value.as_str()).to_string();
+        for (chunk, value) in values.iter().enumerate() {
+            if value != usual_value {
+                let difference = (param_name.to_string(), text(value), text(usual_value));
+                match outliers.iter_mut().find(|(outlier, _)| *outlier == chunk) {
+                    Some((_, differences)) => differences.push(difference),
+                    None => outliers.push((chunk, vec![difference])),
+                }
+            }
+        }
+    }
+    outliers.sort_by_key(|(chunk, _)| *chunk);
+    outliers
+}
+
+/// The settings in the documentation's order of the preset parameters, followed by the other
+/// parameters by name.
+fn ordered_settings(settings: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut parameters = params::PARAMS.iter()
+        .filter_map(|param| settings.get_key_value(param.name))
+        .collect::<Vec<_>>();
+    let mut others = settings.iter().filter(|(param_name, _)| !params::is_preset_param(param_name)).collect::<Vec<_>>();
+    others.sort();
+    parameters.extend(others);
+    parameters
+}
+
+/// Quotes a string for a POSIX shell, if it needs quoting.
+fn shell_quote(text: &str) -> String {
+    if !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=+,".contains(c)) {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+fn test_determiner() -> Determiner {
+    Determiner::new(Cli { color: ColorMode::Never, ..Cli::default() })
+}
+
+#[test]
+fn test_encoding_params() {
+    let input = "Encoding settings                        : cpuid=1111039 / frame-threads=4 / wpp / no-pmode / no-pme / no-psnr / no-ssim / log-level=2 / input-csp=1 / input-res=1860x1080 / interlace=0 / total-frames=0 / level-idc=0 / high-tier=1 / uhd-bd=0 / ref=5 / no-allow-non-conformance / no-repeat-headers / annexb / no-aud / no-eob / no-eos / no-hrd / info / hash=0 / temporal-layers=0 / open-gop / min-keyint=25 / keyint=250 / gop-lookahead=0 / bframes=8 / b-adapt=2 / b-pyramid / bframe-bias=0 / rc-lookahead=40 / lookahead-slices=0 / scenecut=40 / no-hist-scenecut / radl=0 / no-splice / no-intra-refresh / ctu=64 / min-cu-size=8 / rect / amp / max-tu-size=32 / tu-inter-depth=3 / tu-intra-depth=3 / limit-tu=0 / rdoq-level=2 / dynamic-rd=0.00 / no-ssim-rd / signhide / no-tskip / nr-intra=0 / nr-inter=0 / no-constrained-intra / strong-intra-smoothing / max-merge=5 / limit-refs=0 / no-limit-modes / me=3 / subme=4 / merange=57 / temporal-mvp / no-frame-dup / no-hme / weightp / weightb / no-analyze-src-pics / deblock=0:0 / sao / no-sao-non-deblock / rd=6 / selective-sao=4 / no-early-skip / rskip / no-fast-intra / no-tskip-fast / no-cu-lossless / b-intra / no-splitrd-skip / rdpenalty=0 / psy-rd=2.00 / psy-rdoq=1.00 / no-rd-refine / no-lossless / cbqpoffs=0 / crqpoffs=0 / rc=crf / crf=23.0 / qcomp=0.60 / qpstep=4 / stats-write=0 / stats-read=0 / ipratio=1.40 / pbratio=1.30 / aq-mode=2 / aq-strength=1.00 / cutree / zone-count=0 / no-strict-cbr / qg-size=32 / no-rc-grain / qpmax=69 / qpmin=0 / no-const-vbv / sar=0 / overscan=0 / videoformat=5 / range=0 / colorprim=1 / transfer=1 / colormatrix=1 / chromaloc=1 / chromaloc-top=0 / chromaloc-bottom=0 / display-window=0 / cll=0,0 / min-luma=0 / max-luma=1023 / log2-max-poc-lsb=8 / vui-timing-info / vui-hrd-info / slices=1 / no-opt-qp-pps / no-opt-ref-list-length-pps / no-multi-pass-opt-rps / scenecut-bias=0.05 / no-opt-cu-delta-qp / no-aq-motion / no-hdr10 / no-hdr10-opt / no-dhdr10-opt / no-idr-recovery-sei / analysis-reuse-level=0 / analysis-save-reuse-level=0 / analysis-load-reuse-level=0 / scale-factor=0 / refine-intra=0 / refine-inter=0 / refine-mv=1 / refine-ctu-distortion=0 / no-limit-sao / ctu-info=0 / no-lowpass-dct / refine-analysis-type=0 / copy-pic=1 / max-ausize-factor=1.0 / no-dynamic-refine / no-single-sei / no-hevc-aq / no-svt / no-field / qp-adaptation-range=1.00 / scenecut-aware-qp=0conformance-window-offsets / right=0 / bottom=0 / decoder-max-rate=0 / no-vbv-live-multi-pass / no-mcstf / no-sbrc";
+    assert_eq!(Determiner::default().determine_preset_from_str(input), Ok("veryslow".to_string()));
+    let input = "ctu=32 min-cu-size=8";
+    assert_eq!(Determiner::default().determine_preset_from_str(input), Ok("superfast".to_string()));
+    let input = "ctu=32 min-cu-size=8 bframes=8";
+    assert_eq!(test_determiner().determine_preset_from_str(input), Err("No matching presets found. Closest matches:
+  superfast  2 matching parameters, 1 to change
+  slower     2 matching parameters, 1 to change
+  veryslow   2 matching parameters, 1 to change
+  placebo    2 matching parameters, 1 to change
+  ultrafast  1 matching parameter, 2 to change
+  veryfast   1 matching parameter, 2 to change
+  faster     1 matching parameter, 2 to change
+  fast       1 matching parameter, 2 to change
+  medium     1 matching parameter, 2 to change
+  slow       1 matching parameter, 2 to change
+Estimated speed: custom settings, roughly between medium and slow".to_string()));
+    let input = "ctu=32";
+    assert_eq!(test_determiner().determine_preset_from_str(input), Err("Multiple matching presets found: ultrafast, superfast".to_string()));
+    assert_eq!(test_determiner().determination(&parse_string("crf=20")), Determination::InsufficientInput);
+    let determiner = Determiner::new(Cli { numeric: true, ..Cli::default() });
+    assert_eq!(determiner.determine_preset_from_str("ctu=32 min-cu-size=8"), Ok("1".to_string()));
+}
+
+#[test]
+fn test_speed() {
+    assert_eq!(test_determiner().speed("slow"), Ok(6));
+    assert_eq!(test_determiner().speed("6"), Ok(6));
+    assert!(test_determiner().speed("10").is_err());
+}
+
+#[test]
+fn test_rejection() {
+    let superfast = parse_string("ctu=32 min-cu-size=8");
+    let gate = GateArgs { at_most: Some("ultrafast".to_string()), ..GateArgs::default() };
+    assert_eq!(test_determiner().rejection(&gate, None, "", &superfast), Ok(Some("The preset superfast is slower than the allowed ultrafast".to_string())));
+    let gate = GateArgs { require: Some("ultrafast".to_string()), at_most: Some("1".to_string()), ..GateArgs::default() };
+    assert_eq!(test_determiner().record_status("STDIN:1", "ctu=32 min-cu-size=8", &gate, None), exit_code::SUCCESS);
+    let slow = GateArgs { require: Some("slow".to_string()), ..GateArgs::default() };
+    assert_eq!(test_determiner().record_status("STDIN:1", "ctu=32 min-cu-size=8", &slow, None), exit_code::REJECTED);
+    assert_eq!(test_determiner().record_status("STDIN:2", "ctu=32", &GateArgs::default(), None), exit_code::AMBIGUOUS);
+    assert_eq!(test_determiner().rejection(&gate, None, "", &superfast), Ok(None));
+    let gate = GateArgs { require: Some("slow".to_string()), at_most: Some("fast".to_string()), ..GateArgs::default() };
+    assert_eq!(test_determiner().rejection(&gate, None, "", &superfast), Err("No preset can be at least slow and at most fast".to_string()));
+}
+
+#[test]
+fn test_settings_dump() {
+    let determiner = test_determiner();
+    assert!(determiner.settings_dump("rd=4 crf=20", &parse_settings("rd=4 crf=20")).ends_with(":\n    rdLevel=4\n  - crf=20\n"));
+    assert!(determiner.settings_dump("crf=20 rd=4", &parse_settings("crf=20 rd=4")).ends_with(":\n  - crf=20\n    rdLevel=4\n"));
+    let input = "Encoding settings : wpp / rd=4 / deblock=1 / colorprim=1 / psy-rd=2 / crf=28.0";
+    assert!(determiner.settings_dump(input, &parse_settings(input)).ends_with(":\n  - wpp=1 (default)\n    rdLevel=4\n  - deblock=1 (default)\n  - colorprim=1 (BT.709)\n  - psy-rd=2 (default)\n  - crf=28.0 (default)\n"));
+    assert_eq!(determiner.unknown_params(&parse_settings("Encoding settings : cpuid=1 / rd=4 / no-frobnicate / crf=20 / zz=1")), ["frobnicate", "zz"]);
+}
+
+#[test]
+fn test_interlacing_warning() {
+    let determiner = test_determiner();
+    let interlacing = encoding::interlacing(|name| (name == "interlace").then_some("2"));
+    assert_eq!(determiner.interlacing_warning(interlacing.as_ref(), &Determination::Match("slow".to_string())), None);
+    assert!(determiner.interlacing_warning(interlacing.as_ref(), &Determination::NoMatch).is_some_and(|warning| warning.starts_with("The encode is interlaced, bottom field first. ")));
+}
+
+#[test]
+fn test_comparison() {
+    let determiner = test_determiner();
+    let settings = parse_string("ctu=32 min-cu-size=8 bframes=8");
+    let comparison = determiner.comparison("ctu=32 min-cu-size=8 bframes=8", &settings);
+    assert_eq!(comparison.presets[0], ("superfast".to_string(), 66));
+    assert_eq!(comparison.rows.iter().map(|row| row.param_name.as_str()).collect::<Vec<_>>(), ["ctu", "min-cu-size", "bframes"]);
+    assert_eq!(comparison.rows[2].input_value.as_deref(), Some("8"));
+    assert_eq!(comparison.rows[2].preset_values[0], (Some("3".to_string()), false));
+    assert_eq!(comparison.counts()[0], (2, 1));
+}
+
+#[test]
+fn test_summary() {
+    let determiner = test_determiner();
+    let settings = parse_string("ctu=32 min-cu-size=8 bframes=8");
+    let json = determiner.json_summary(&determiner.summary("", &settings), "", &settings, true);
+    assert!(json.contains(&format!(r#"{{"param":"ctu","doc_url":"{}","input":"32","#, params::doc_url("ctu").unwrap())));
+    let summary = determiner.summary("x265 --ctu 32", &settings);
+    assert_eq!(determiner.shell_assignments(&summary), "STATUS=no-match PRESET=superfast CONFIDENCE=66 ENCODER=x265 MATCHES='' HANDBRAKE_PRESET=''");
+    let summary = determiner.summary("ctu=32", &parse_string("ctu=32"));
+    assert_eq!(determiner.json_summary(&summary, "ctu=32", &parse_string("ctu=32"), true), r#"{"schema_version":1,"status":"ambiguous","preset":"ultrafast","confidence":100,"encoder":"","matches":["ultrafast","superfast"],"raw_settings":"ctu=32","settings":{"ctu":"32"},"error":{"code":"ambiguous","message":"Multiple matching presets found: ultrafast, superfast"}}"#);
+    assert_eq!(determiner.preset_summary_from_str("ctu=32 min-cu-size=8 bframes=8"), Ok(determiner.preset_summary(&settings)));
+}
+
+#[test]
+fn test_embedded_preset_conflict() {
+    let determiner = test_determiner();
+    let conflict = determiner.embedded_preset_conflict(Some("slow"), &Determination::Match("veryslow".to_string()));
+    assert_eq!(conflict.as_deref(), Some("The settings name the slow preset, but they match veryslow. The metadata may have been edited, or be stale"));
+    let message = determiner.tag_hint_message("Comment", "slow", &Determination::Match("slower".to_string()));
+    assert_eq!(message, "Unverified hint: the Comment tag names the slow preset, and the settings disagree");
+    assert_eq!(determiner.embedded_preset_conflict(Some("6"), &Determination::Match("slow".to_string())), None);
+    let summary = determiner.summary("x265 --ctu 32", &determiner.normalized_settings("x265 --ctu 32").unwrap());
+    assert_eq!(determiner.summary_from_str("x265 --ctu 32").map(|summary| determiner.shell_assignments(&summary)), Ok(determiner.shell_assignments(&summary)));
+}
+
+#[test]
+fn test_triage() {
+    let determiner = test_determiner();
+    let entry = |input: &str| determiner.triage_entry("a.mkv", Ok(input)).map(|entry| (entry.cause, entry.detail));
+    assert_eq!(entry("ctu=32"), Some((TriageCause::Ambiguous, "ultrafast, superfast".to_string())));
+    assert_eq!(entry("ctu=32 min-cu-size=8 bframes=8"), Some((TriageCause::NoMatch, "closest: superfast (66%)".to_string())));
+    assert_eq!(entry("ctu=32 min-cu-size=8").map(|(cause, _)| cause), Some(TriageCause::LowConfidence));
+    assert_eq!(determiner.triage_entry("b.mkv", Err("unreadable".to_string())).map(|entry| entry.cause), Some(TriageCause::FailedExtraction));
+    let entries = [
+        TriageEntry { path: "a.mkv".to_string(), cause: TriageCause::NoMatch, detail: "closest: slow (92%)".to_string() },
+        TriageEntry { path: "b.mkv".to_string(), cause: TriageCause::NoMatch, detail: "closest: fast (80%)".to_string() },
+        TriageEntry { path: "c.mkv".to_string(), cause: TriageCause::Ambiguous, detail: "ultrafast, superfast".to_string() },
+    ];
+    assert_eq!(triage_report(&entries, 10), "\nNeeds review: 3 of 10 videos\n  no match (2):\n    a.mkv: closest: slow (92%)\n    b.mkv: closest: fast (80%)\n  ambiguous (1):\n    c.mkv: ultrafast, superfast\n");
+    assert_eq!(triage_report(&[], 10), "\nNeeds review: none of 10 videos\n");
+}
+
+#[test]
+fn test_match_percentage() {
+    let determiner = Determiner::default();
+    let settings = parse_string("ctu=32 min-cu-size=8 bframes=8 unknown=1");
+    assert_eq!(determiner.match_percentage(&settings, &parse_string("ctu=32 min-cu-size=8 bframes=3")), 66);
+    assert_eq!(determiner.match_percentage(&settings, &parse_string("ctu=32")), 100);
+    assert_eq!(determiner.match_percentage(&settings, &parse_string("other=1")), 0);
+}
+
+#[test]
+fn test_reproduction() {
+    let determiner = test_determiner();
+    let settings = determiner.normalized_settings("ctu=64 min-cu-size=8 bframes=4 ref=5 rdLevel=4 crf=20.0 subme=3").unwrap();
+    assert_eq!(determiner.reproduction(&settings), ("slow".to_string(), vec![("ref".to_string(), "5".to_string())]));
+    assert_eq!(determiner.ffmpeg_command("ctu=32 min-cu-size=16"), Ok("ffmpeg -i input.mkv -c:v libx265 -preset ultrafast output.mkv".to_string()));
+    let input = "Encoding settings : cpuid=1111039 / frame-threads=4 / ctu=64 / min-cu-size=8 / bframes=4 / ref=5 / rd=4 / subme=3 / rc=crf / crf=20.0 / psy-rd=1.00 / aq-strength=0.80 / keyint=240 / qcomp=0.60 / deblock=-1:-1 / master-display=G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(10000000,1)";
+    let command = "ffmpeg -i input.mkv -c:v libx265 -preset slow -crf 20.0 -x265-params 'ref=5:aq-strength=0.80:deblock=-1,-1:keyint=240:master-display=G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(10000000,1):psy-rd=1.00' output.mkv";
+    assert_eq!(determiner.ffmpeg_command(input).as_deref(), Ok(command));
+}
+
+#[test]
+fn test_chunk_outliers() {
+    let mut interner = intern::Interner::default();
+    let mut chunk = |bframes: &str| interner.intern_settings([("bframes", bframes), ("ref", "4")]);
+    assert!(chunk_outliers(&[chunk("4"), chunk("4")]).is_empty());
+    let outliers = chunk_outliers(&[chunk("4"), chunk("8"), chunk("4")]);
+    assert_eq!(outliers, [(1, vec![("bframes".to_string(), "8".to_string(), "4".to_string())])]);
+}
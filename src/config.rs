@@ -0,0 +1,120 @@
+use std::{env, fs, io::ErrorKind, path::PathBuf};
+
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::Cli;
+
+/// Defaults for the command line options, read from `config.toml`. Options given on the command
+/// line or in `DETERMINE_PRESET_*` environment variables take precedence. The enums are read as
+/// strings, and accept the same values as the command line.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    color: Option<String>,
+    verbose: Option<u8>,
+    width: Option<usize>,
+    hyperlinks: Option<String>,
+    presets_file: Option<String>,
+    table_style: Option<String>,
+    param_order: Option<String>,
+    format: Option<String>,
+    lang: Option<String>,
+    ignore: Option<Vec<String>>,
+    no_exec: Option<bool>,
+    mediainfo: Option<String>,
+    mediainfo_args: Option<Vec<String>>,
+}
+
+/// The path of the configuration file: `$XDG_CONFIG_HOME/determine-preset/config.toml`, or
+/// `~/.config/determine-preset/config.toml`.
+pub fn path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("determine-preset").join("config.toml"))
+}
+
+/// Reads the configuration file. A missing file is the same as an empty one.
+pub fn load() -> Result<Config, String> {
+    let Some(path) = path() else { return Ok(Config::default()) };
+    debug!("Reading the configuration from {}", path.display());
+    match fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|error_message| format!("{}: {}", path.display(), error_message)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(format!("Could not read {}: {}", path.display(), err)),
+    }
+}
+
+pub fn parse(text: &str) -> Result<Config, String> {
+    toml::from_str(crate::strip_bom(text)).map_err(|err| err.message().to_string())
+}
+
+/// Fills in the options that weren't given on the command line from the configuration.
+pub fn apply(config: Config, cli: &mut Cli, matches: &ArgMatches) -> Result<(), String> {
+    if let Some(color) = config.color.filter(|_| !given(matches, "color")) {
+        cli.color = parse_enum("color", &color)?;
+    }
+    if let Some(hyperlinks) = config.hyperlinks.filter(|_| !given(matches, "hyperlinks")) {
+        cli.hyperlinks = parse_enum("hyperlinks", &hyperlinks)?;
+    }
+    if let Some(table_style) = config.table_style.filter(|_| !given(matches, "table_style")) {
+        cli.table_style = parse_enum("table-style", &table_style)?;
+    }
+    if let Some(param_order) = config.param_order.filter(|_| !given(matches, "param_order")) {
+        cli.param_order = parse_enum("param-order", &param_order)?;
+    }
+    if let Some(format) = config.format.filter(|_| !given(matches, "format")) {
+        cli.determine.format = parse_enum("format", &format)?;
+    }
+    if let Some(lang) = config.lang.filter(|_| !given(matches, "lang")) {
+        cli.lang = Some(parse_enum("lang", &lang)?);
+    }
+    if cli.determine.verbose == 0 {
+        cli.determine.verbose = config.verbose.unwrap_or(0);
+    }
+    cli.width = cli.width.or(config.width);
+    cli.presets_file = cli.presets_file.take().or(config.presets_file);
+    if !given(matches, "ignore") {
+        cli.ignore = config.ignore.unwrap_or_default();
+    }
+    // the flag can only forbid running programs, so the file can't allow it again
+    cli.no_exec |= config.no_exec.unwrap_or(false);
+    cli.mediainfo = cli.mediainfo.take().or(config.mediainfo);
+    if !given(matches, "mediainfo_arg") {
+        cli.mediainfo_arg = config.mediainfo_args.unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Whether an option was given on the command line (before or after the subcommand) or in its
+/// environment variable, both of which take precedence over the configuration file.
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    // subcommands that don't have the option don't know its id
+    let known = matches.ids().any(|known| known.as_str() == id);
+    (known && matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable)))
+        || matches.subcommand().is_some_and(|(_, matches)| given(matches, id))
+}
+
+fn parse_enum<T: ValueEnum>(key: &str, value: &str) -> Result<T, String> {
+    T::from_str(value, false).map_err(|_| {
+        let possible_values = T::value_variants().iter()
+            .filter_map(|variant| variant.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect::<Vec<_>>();
+        format!("invalid value '{}' for {} in the configuration file (possible values: {})", value, key, possible_values.join(", "))
+    })
+}
+
+#[test]
+fn test_parse_config() {
+    let config = parse("color = \"never\"\nverbose = 2\nignore = [\"crf\"]\nno-exec = true\n").unwrap();
+    assert_eq!(config.verbose, Some(2));
+    assert_eq!(config.no_exec, Some(true));
+    assert_eq!(config.ignore, Some(vec!["crf".to_string()]));
+    assert!(parse_enum::<crate::TableStyle>("table-style", "github").is_ok());
+    assert!(parse_enum::<crate::TableStyle>("table-style", "fancy").is_err());
+    assert!(parse("colour = \"never\"").is_err());
+}
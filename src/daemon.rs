@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{fs::FileTypeExt, net::{UnixListener, UnixStream}},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use tracing::{debug, info, warn};
+
+/// Listens on a Unix socket and answers requests until the process is killed. Each request is
+/// one line, and is answered with the one line `respond` returns for it. A connection can send
+/// any number of requests, and connections are served in parallel.
+pub fn serve(socket_path: &str, respond: impl Fn(&str) -> String + Sync) -> Result<(), String> {
+    // a socket left behind by a daemon that was killed would make bind fail
+    if fs::symlink_metadata(socket_path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        if UnixStream::connect(socket_path).is_ok() {
+            return Err(format!("Another daemon is already listening on {}", socket_path));
+        }
+        fs::remove_file(socket_path).map_err(|err| format!("Could not remove the stale socket {}: {}", socket_path, err))?;
+    }
+    let listener = UnixListener::bind(socket_path).map_err(|err| format!("Could not listen on {}: {}", socket_path, err))?;
+    info!("Listening on {}", socket_path);
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    scope.spawn(|| {
+                        if let Err(err) = handle_connection(stream, &respond) {
+                            warn!("Connection closed: {}", err);
+                        }
+                    });
+                }
+                Err(err) => warn!("Could not accept a connection on {}: {}", socket_path, err),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// How many responses `ResponseCache` keeps before it starts over.
+const CACHE_CAPACITY: usize = 100_000;
+
+/// The responses to earlier requests by path, for media managers that ask about the same files
+/// on every library scan. A response is only reused while the file's size and modification time
+/// are unchanged.
+pub struct ResponseCache<T> {
+    responses: Mutex<HashMap<String, (SystemTime, u64, T)>>,
+}
+
+impl<T> Default for ResponseCache<T> {
+    fn default() -> Self {
+        ResponseCache { responses: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<T: Clone> ResponseCache<T> {
+    /// The response cached for the file at `path`, if it hasn't changed since.
+    pub fn get(&self, path: &str) -> Option<T> {
+        let (modified, len) = file_version(path)?;
+        let responses = self.responses.lock().expect("A request thread panicked");
+        let (cached_modified, cached_len, response) = responses.get(path)?;
+        (*cached_modified == modified && *cached_len == len).then(|| response.clone())
+    }
+
+    /// Caches the response for the file at `path`, unless its modification time is unknown.
+    pub fn insert(&self, path: &str, response: T) {
+        let Some((modified, len)) = file_version(path) else {
+            return;
+        };
+        let mut responses = self.responses.lock().expect("A request thread panicked");
+        if responses.len() >= CACHE_CAPACITY && !responses.contains_key(path) {
+            responses.clear();
+        }
+        responses.insert(path.to_string(), (modified, len, response));
+    }
+}
+
+fn file_version(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn handle_connection(stream: UnixStream, respond: &impl Fn(&str) -> String) -> std::io::Result<()> {
+    debug!("Accepted a connection");
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let request = line.trim();
+        if request.is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", respond(request))?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_handle_connection() {
+    let (mut client, server) = UnixStream::pair().unwrap();
+    let daemon = std::thread::spawn(move || handle_connection(server, &|request: &str| request.to_uppercase()));
+    client.write_all(b"a.mkv\n\n b.mkv \n").unwrap();
+    client.shutdown(std::net::Shutdown::Write).unwrap();
+    let responses = BufReader::new(client).lines().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(responses, ["A.MKV", "B.MKV"]);
+    daemon.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_response_cache() {
+    let path = std::env::temp_dir().join(format!("determine-preset-test-cache-{}.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let cache = ResponseCache::default();
+    assert_eq!(cache.get(path), None);
+    cache.insert(path, "missing");
+    assert_eq!(cache.get(path), None);
+    fs::write(path, "ctu=64").unwrap();
+    cache.insert(path, "ctu=64");
+    assert_eq!(cache.get(path), Some("ctu=64"));
+    fs::write(path, "ctu=32 bframes=4").unwrap();
+    let changed = cache.get(path);
+    fs::remove_file(path).unwrap();
+    assert_eq!(changed, None);
+}
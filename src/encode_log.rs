@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use crate::{params, presets::Preset, resolve, Difference};
+
+/// The names that the `tools:` line of a log gives some parameters, with their usual names.
+const TOOL_NAMES: &[(&str, &str)] = &[("rdoq", "rdoq-level"), ("tmvp", "temporal-mvp")];
+
+/// Reads the encoder settings from an x265 (or ffmpeg with libx265) encode log. The settings
+/// x265 reports in its `[info]` lines are used, and where the log doesn't report a parameter,
+/// the x265 or ffmpeg command line in the log (if any) is used instead.
+pub fn parse(text: &str, presets: &[Preset]) -> HashMap<String, String> {
+    let mut settings = resolve::find_command_line(text)
+        .and_then(|command_line| resolve::resolve(&[command_line], presets).ok())
+        .unwrap_or_default();
+
+    for line in crate::lines(text) {
+        let Some((_, info)) = line.split_once("x265 [info]: ") else { continue };
+        if let Some(tools) = info.strip_prefix("tools: ") {
+            // `rd=3 signhide tmvp deblock sao`: switches are listed when they are enabled
+            for token in tools.split_whitespace() {
+                let (name, value) = token.split_once('=').unwrap_or((token, "1"));
+                let name = TOOL_NAMES.iter().find(|(tool, _)| *tool == name).map_or(name, |(_, name)| *name);
+                let (name, value) = params::normalize(name, value);
+                settings.insert(name, value);
+            }
+            continue;
+        }
+        let Some((labels, values)) = info.split_once(" : ") else { continue };
+        let values = values.split(" / ").map(str::trim).collect::<Vec<_>>();
+        let names: &[&str] = match labels.trim() {
+            "Coding QT: max CU size, min CU size" => &["ctu", "min-cu-size"],
+            "ME / range / subpel / merge" => &["me", "merange", "subme", "max-merge"],
+            "Keyframe min / max / scenecut / bias" => &["min-keyint", "keyint", "scenecut", "scenecut-bias"],
+            "Lookahead / bframes / badapt" => &["rc-lookahead", "bframes", "b-adapt"],
+            "b-pyramid / weightp / weightb" => &["b-pyramid", "weightp", "weightb"],
+            "AQ: mode / str / qg-size / cu-tree" => &["aq-mode", "aq-strength", "qg-size", "cutree"],
+            "Residual QT: max TU size, max depth" => {
+                // `32 / 1 inter / 1 intra`
+                for value in &values[1..] {
+                    match value.split_once(' ') {
+                        Some((depth, "inter")) => settings.insert("tu-inter".to_string(), depth.to_string()),
+                        Some((depth, "intra")) => settings.insert("tu-intra".to_string(), depth.to_string()),
+                        _ => None,
+                    };
+                }
+                &["max-tu-size"]
+            }
+            "References / ref-limit  cu / depth" | "References / ref-limit cu / depth" => {
+                // `3 / off / on`: the limit-refs bitmask has 1 for depth and 2 for CU
+                if let [_, cu, depth] = values[..] {
+                    let limit_refs = (cu == "on") as u8 * 2 + (depth == "on") as u8;
+                    settings.insert("limit-refs".to_string(), limit_refs.to_string());
+                }
+                &["ref"]
+            }
+            "Rate Control / qCompress" => {
+                // `CRF-28.0 / 0.60`
+                if let Some(crf) = values[0].strip_prefix("CRF-") {
+                    settings.insert("crf".to_string(), crf.to_string());
+                }
+                &["", "qcomp"]
+            }
+            _ => &[],
+        };
+        for (name, value) in names.iter().zip(&values) {
+            if !name.is_empty() {
+                let (name, value) = params::normalize(name, value);
+                settings.insert(name, value);
+            }
+        }
+    }
+    settings
+}
+
+/// The parameters whose values differ between the video and the log, with the video's value and
+/// the log's value. Parameters that only one of them reports are not compared. Numbers are
+/// compared by value, since the log and the video don't always format them the same way.
+pub fn discrepancies(video: &HashMap<String, String>, log: &HashMap<String, String>) -> Vec<Difference> {
+    let mut discrepancies = video.iter()
+        .filter_map(|(name, video_value)| {
+            let log_value = log.get(name)?;
+            let same = video_value == log_value || matches!((video_value.parse::<f64>(), log_value.parse::<f64>()), (Ok(a), Ok(b)) if a == b);
+            (!same).then(|| (name.clone(), video_value.clone(), log_value.clone()))
+        })
+        .collect::<Vec<_>>();
+    discrepancies.sort();
+    discrepancies
+}
+
+#[test]
+fn test_parse_encode_log() {
+    let log = "\
+x265 [info]: HEVC encoder version 3.5
+x265 [info]: Coding QT: max CU size, min CU size : 64 / 8
+x265 [info]: Residual QT: max TU size, max depth : 32 / 1 inter / 1 intra
+x265 [info]: ME / range / subpel / merge         : star / 57 / 3 / 3
+x265 [info]: Lookahead / bframes / badapt        : 25 / 4 / 2
+x265 [info]: References / ref-limit  cu / depth  : 4 / on / on
+x265 [info]: AQ: mode / str / qg-size / cu-tree  : 2 / 1.0 / 32 / 1
+x265 [info]: Rate Control / qCompress            : CRF-20.0 / 0.60
+x265 [info]: tools: rect limit-modes rd=4 rdoq=2 signhide tmvp sao
+";
+    let settings = parse(log, &crate::presets::x265());
+    assert_eq!(settings["me"], "star");
+    assert_eq!(settings["limit-refs"], "3");
+    assert_eq!(settings["tu-intra"], "1");
+    assert_eq!(settings["rdLevel"], "4");
+    assert_eq!((settings["rdoq-level"].as_str(), settings["temporal-mvp"].as_str()), ("2", "1"));
+    assert_eq!(settings["crf"], "20.0");
+    assert_eq!(settings["aq-strength"], "1.0");
+
+    let video = HashMap::from([("crf".to_string(), "20".to_string()), ("aq-strength".to_string(), "1.00".to_string()), ("bframes".to_string(), "8".to_string())]);
+    assert_eq!(discrepancies(&video, &settings), [("bframes".to_string(), "8".to_string(), "4".to_string())]);
+}
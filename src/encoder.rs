@@ -0,0 +1,119 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{encoder_name, lines, matcher, presets::{self, Preset}, Determination};
+
+/// An encoder family whose presets can be determined: how to recognize its videos, read their
+/// settings and get its preset table. x265 is built in as [`X265`]; other encoders can be added
+/// to a [`Registry`] by implementing this trait.
+pub trait EncoderProfile: Send + Sync {
+    /// The encoder's name, like `x265`.
+    fn name(&self) -> &str;
+
+    /// Whether the input (`mediainfo` output, settings or a command line) is from this encoder.
+    fn detect(&self, input: &str) -> bool;
+
+    /// The encoder's version in the input, if it's recorded.
+    fn version(&self, input: &str) -> Option<String>;
+
+    /// The settings in the input, normalized to the names and values of the preset table.
+    fn parse_settings(&self, input: &str) -> Result<HashMap<String, String>, String>;
+
+    /// The presets of a version of the encoder (or of the latest, without one), from the
+    /// fastest to the slowest.
+    fn presets(&self, version: Option<&str>) -> Arc<[Preset]>;
+}
+
+/// The built-in x265 support.
+pub struct X265;
+
+impl EncoderProfile for X265 {
+    fn name(&self) -> &str {
+        "x265"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        encoder_name(input) == "x265"
+    }
+
+    /// The version in the `Writing library`, like `3.5+1-f0c1022b6` for
+    /// `x265 3.5+1-f0c1022b6:[Linux][GCC 9.3.0][64 bit] 10bit`.
+    fn version(&self, input: &str) -> Option<String> {
+        lines(input).find_map(|line| {
+            let (field, value) = line.split_once(" : ")?;
+            let version = value.trim().strip_prefix("x265 ").filter(|_| field.trim() == "Writing library")?;
+            Some(version.split([':', ' ']).next()?.to_string())
+        })
+    }
+
+    fn parse_settings(&self, input: &str) -> Result<HashMap<String, String>, String> {
+        matcher::normalized_settings(input, &presets::x265())
+    }
+
+    /// The x265 presets haven't changed in a way that the table tracks, so every version has
+    /// the same ones.
+    fn presets(&self, _version: Option<&str>) -> Arc<[Preset]> {
+        presets::x265()
+    }
+}
+
+/// The encoders that presets can be determined for. An input is matched against the first
+/// registered encoder that detects it, or x265 if none do, as bare settings strings don't name
+/// their encoder.
+pub struct Registry {
+    profiles: Vec<Arc<dyn EncoderProfile>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry { profiles: vec![Arc::new(X265)] }
+    }
+}
+
+impl Registry {
+    /// Adds an encoder. It is tried before the ones registered earlier, including x265.
+    pub fn register(&mut self, profile: impl EncoderProfile + 'static) {
+        self.profiles.insert(0, Arc::new(profile));
+    }
+
+    /// The encoder of the input.
+    pub fn detect(&self, input: &str) -> &dyn EncoderProfile {
+        let x265 = self.profiles.last().expect("x265 is always registered");
+        self.profiles.iter().find(|profile| profile.detect(input)).unwrap_or(x265).as_ref()
+    }
+
+    /// Determines the preset of the input with its encoder's presets: the encoder's name, and
+    /// the determination.
+    pub fn determination(&self, input: &str) -> Result<(String, Determination), String> {
+        let profile = self.detect(input);
+        let settings = profile.parse_settings(input)?;
+        let presets = profile.presets(profile.version(input).as_deref());
+        Ok((profile.name().to_string(), matcher::determination(&settings, &presets).for_input(input)))
+    }
+}
+
+#[test]
+fn test_registry() {
+    struct Svt;
+    impl EncoderProfile for Svt {
+        fn name(&self) -> &str {
+            "SVT-AV1"
+        }
+        fn detect(&self, input: &str) -> bool {
+            input.contains("SVT-AV1")
+        }
+        fn version(&self, _input: &str) -> Option<String> {
+            None
+        }
+        fn parse_settings(&self, input: &str) -> Result<HashMap<String, String>, String> {
+            Ok(crate::parse_string(input))
+        }
+        fn presets(&self, _version: Option<&str>) -> Arc<[Preset]> {
+            presets::parse("p8 fast=1\np4 fast=0\n").unwrap().into()
+        }
+    }
+    let mut registry = Registry::default();
+    registry.register(Svt);
+    assert_eq!(registry.determination("SVT-AV1 fast=0"), Ok(("SVT-AV1".to_string(), Determination::Match("p4".to_string()))));
+    assert_eq!(registry.determination("ctu=64 bframes=4 rdLevel=4 lookahead-slices=4").map(|(name, _)| name), Ok("x265".to_string()));
+    assert_eq!(X265.version("Writing library : x265 3.5+1-f0c1022b6:[Linux][GCC 9.3.0][64 bit] 10bit\n").as_deref(), Some("3.5+1-f0c1022b6"));
+}
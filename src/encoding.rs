@@ -0,0 +1,789 @@
+use std::fmt;
+
+/// What the settings record about an encode besides its preset.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Details {
+    /// How the bitrate was controlled, like `single-pass CRF 20.0` or `2-pass ABR 5000 kbps`.
+    pub rate_control: Option<String>,
+    /// The HEVC profile, level and tier, like `Main10@L5.1 High tier`.
+    pub profile: Option<String>,
+    /// The GOP structure, like `open GOP, keyint 250, min-keyint 25, scenecut 40, 4 B-frames
+    /// (pyramid)`.
+    pub gop: Option<String>,
+    /// The x265 releases that could have written the settings, like `x265 3.4 or later, before
+    /// 4.0`.
+    pub x265_era: Option<String>,
+    pub latency: Option<Latency>,
+    pub bit_depth: Option<u32>,
+    /// Like `4:2:0`.
+    pub chroma_subsampling: Option<&'static str>,
+    pub color: Color,
+    pub dolby_vision: Option<DolbyVision>,
+    pub interlacing: Option<Interlacing>,
+    pub vbv: Option<Vbv>,
+    /// The values of the `QUALITY_KNOBS` that the settings have.
+    pub quality_knobs: Vec<(&'static str, String)>,
+    /// The values of the `CATEGORY_PARAMS` that the settings have, for `quality_category`.
+    pub category_params: Vec<(&'static str, String)>,
+}
+
+/// The parameters that `quality_category` looks at besides the preset and the latency.
+const CATEGORY_PARAMS: &[&str] = &["crf"];
+
+/// A one-line classification of an encode, for users who don't know the parameters: its
+/// category, and why, like `archival-grade (slow preset, CRF 18)`. Encodes configured for
+/// streaming are in their `Latency` class, `low-latency streaming` or `streaming`, whose
+/// reasons the latency gives; slow presets at a low CRF are `archival-grade`; fast presets or a
+/// high CRF are `speed-focused`; the rest `balanced`.
+pub fn quality_category(details: &Details, preset_name: Option<&str>) -> Option<String> {
+    let number = |name: &str| details.category_params.iter().find(|(param, _)| *param == name)?.1.parse::<f64>().ok();
+    let crf = number("crf");
+    let mut reasons = Vec::new();
+    if let Some(preset_name) = preset_name {
+        reasons.push(format!("{} preset", preset_name));
+    }
+    if let Some(crf) = crf {
+        reasons.push(format!("CRF {}", crf));
+    }
+    let streaming = details.latency.as_ref().filter(|latency| latency.class != "offline quality");
+    let category = if let Some(latency) = streaming {
+        if reasons.is_empty() {
+            return Some(latency.class.to_string());
+        }
+        latency.class
+    } else {
+        let slow = matches!(preset_name, Some("slow" | "slower" | "veryslow" | "placebo"));
+        let fast = matches!(preset_name, Some("ultrafast" | "superfast" | "veryfast" | "faster"));
+        match crf {
+            _ if preset_name.is_none() && crf.is_none() => return None,
+            Some(crf) if slow && crf <= 20.0 => "archival-grade",
+            Some(crf) if crf >= 28.0 => "speed-focused",
+            _ if fast => "speed-focused",
+            _ => "balanced",
+        }
+    };
+    Some(format!("{} ({})", category, reasons.join(", ")))
+}
+
+/// Parameters that affect the quality independently of the preset, so that two encodes with
+/// the same preset can look very different, with their x265 defaults.
+pub const QUALITY_KNOBS: &[(&str, &str)] = &[("psy-rd", "2.00"), ("psy-rdoq", "0.00"), ("aq-strength", "1.00"), ("qcomp", "0.60"), ("deblock", "0:0")];
+
+/// The x265 default of a parameter in `QUALITY_KNOBS`, with a preset if it's known: the slow
+/// presets turn on `psy-rdoq`.
+pub fn knob_default(name: &str, preset_name: Option<&str>) -> Option<&'static str> {
+    match (name, preset_name) {
+        ("psy-rdoq", Some("slow" | "slower" | "veryslow" | "placebo")) => Some("1.00"),
+        (name, _) => QUALITY_KNOBS.iter().find(|(knob, _)| *knob == name).map(|(_, default)| *default),
+    }
+}
+
+/// Whether a parameter in `QUALITY_KNOBS` is at its default (see `params::same_value`).
+pub fn is_knob_default(name: &str, value: &str, preset_name: Option<&str>) -> bool {
+    knob_default(name, preset_name).is_some_and(|default| crate::params::same_value(name, value, default))
+}
+
+/// The Dolby Vision metadata of a video, which mediainfo reports in `HDR format`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DolbyVision {
+    /// Like `5`, `7` or `8.1`. Profile 8 is numbered by the format its base layer is compatible
+    /// with: `8.1` for HDR10, `8.2` for SDR and `8.4` for HLG.
+    pub profile: Option<String>,
+    /// The layers, like `BL+RPU`, or `BL+EL+RPU` with an enhancement layer.
+    pub layers: Option<String>,
+}
+
+impl fmt::Display for DolbyVision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Dolby Vision")?;
+        if let Some(profile) = &self.profile {
+            write!(f, " profile {}", profile)?;
+        }
+        if let Some(layers) = &self.layers {
+            write!(f, ", {}", layers)?;
+        }
+        Ok(())
+    }
+}
+
+/// How an interlaced encode was coded, from x265's `interlace` and `field` settings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interlacing {
+    /// `top field first` or `bottom field first`.
+    pub field_order: &'static str,
+    /// Whether x265 split the frames into fields itself (`field`, since x265 3.5), rather than
+    /// being given the fields as pictures. `None` if the settings don't say.
+    pub field_coding: Option<bool>,
+}
+
+impl fmt::Display for Interlacing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "interlaced, {}", self.field_order)?;
+        match self.field_coding {
+            Some(true) => f.write_str(", with field coding"),
+            Some(false) => f.write_str(", without field coding (no-field)"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The video buffering verifier limits of an encode, which constrain the bitrate for devices or
+/// streaming, and so the quality at any preset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vbv {
+    /// `vbv-maxrate`, in kbps.
+    pub maxrate: u32,
+    /// `vbv-bufsize`, in kbits, or `None` if the settings don't say.
+    pub bufsize: Option<u32>,
+    /// Whether the bitrate is kept constant (`strict-cbr`), rather than only capped.
+    pub strict_cbr: bool,
+}
+
+impl fmt::Display for Vbv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} kbps max", self.maxrate)?;
+        if let Some(bufsize) = self.bufsize {
+            write!(f, " with a {} kbit buffer ({:.1} s)", bufsize, f64::from(bufsize) / f64::from(self.maxrate))?;
+        }
+        if self.strict_cbr {
+            f.write_str(", strict CBR")?;
+        }
+        Ok(())
+    }
+}
+
+/// The color description and HDR metadata of an encode, with friendly names like `BT.2020` and
+/// `PQ`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Color {
+    pub primaries: Option<&'static str>,
+    pub transfer: Option<&'static str>,
+    pub matrix: Option<&'static str>,
+    /// Whether the SMPTE ST 2086 mastering display metadata (`master-display`) was written.
+    pub mastering_display: bool,
+    /// The maximum content and frame-average light levels (`max-cll`), in nits.
+    pub content_light_level: Option<(u32, u32)>,
+    /// Whether x265 was told to signal HDR10 (`hdr10`, or `hdr` before x265 3.4).
+    pub hdr10: bool,
+}
+
+/// The color primaries, by their number in H.273 (which x265 writes in its settings string),
+/// their x265 option value, and their friendly name.
+const PRIMARIES: &[(&str, &str, &str)] = &[
+    ("1", "bt709", "BT.709"),
+    ("4", "bt470m", "BT.470 System M"),
+    ("5", "bt470bg", "BT.601 PAL"),
+    ("6", "smpte170m", "BT.601 NTSC"),
+    ("7", "smpte240m", "SMPTE 240M"),
+    ("8", "film", "Generic film"),
+    ("9", "bt2020", "BT.2020"),
+    ("10", "smpte428", "SMPTE ST 428"),
+    ("11", "smpte431", "DCI-P3"),
+    ("12", "smpte432", "Display P3"),
+    ("22", "ebu3213", "EBU Tech 3213"),
+];
+
+/// The transfer characteristics, like `PRIMARIES`.
+const TRANSFERS: &[(&str, &str, &str)] = &[
+    ("1", "bt709", "BT.709"),
+    ("4", "bt470m", "BT.470 System M"),
+    ("5", "bt470bg", "BT.470 System B/G"),
+    ("6", "smpte170m", "BT.601"),
+    ("7", "smpte240m", "SMPTE 240M"),
+    ("8", "linear", "Linear"),
+    ("9", "log100", "Logarithmic (100:1)"),
+    ("10", "log316", "Logarithmic (316.22777:1)"),
+    ("11", "iec61966-2-4", "xvYCC"),
+    ("12", "bt1361e", "BT.1361"),
+    ("13", "iec61966-2-1", "sRGB"),
+    ("14", "bt2020-10", "BT.2020 (10-bit)"),
+    ("15", "bt2020-12", "BT.2020 (12-bit)"),
+    ("16", "smpte2084", "PQ"),
+    ("17", "smpte428", "SMPTE ST 428"),
+    ("18", "arib-std-b67", "HLG"),
+];
+
+/// The matrix coefficients, like `PRIMARIES`.
+const MATRICES: &[(&str, &str, &str)] = &[
+    ("0", "gbr", "RGB"),
+    ("1", "bt709", "BT.709"),
+    ("4", "fcc", "FCC 73.682"),
+    ("5", "bt470bg", "BT.601 PAL"),
+    ("6", "smpte170m", "BT.601 NTSC"),
+    ("7", "smpte240m", "SMPTE 240M"),
+    ("8", "ycgco", "YCgCo"),
+    ("9", "bt2020nc", "BT.2020 non-constant"),
+    ("10", "bt2020c", "BT.2020 constant"),
+    ("11", "smpte2085", "SMPTE ST 2085"),
+    ("12", "chroma-derived-nc", "Chromaticity-derived non-constant"),
+    ("13", "chroma-derived-c", "Chromaticity-derived constant"),
+    ("14", "ictcp", "ICtCp"),
+];
+
+/// The friendly name of the value of `colorprim`, `transfer` or `colormatrix`, given by its
+/// number in H.273 or its x265 option value, like `BT.2020` for `colorprim=9`. `None` for
+/// other parameters and for unspecified or unknown values.
+pub fn color_name(name: &str, value: &str) -> Option<&'static str> {
+    let table = match name {
+        "colorprim" => PRIMARIES,
+        "transfer" => TRANSFERS,
+        "colormatrix" => MATRICES,
+        _ => return None,
+    };
+    table.iter().find(|(number, option, _)| *number == value || *option == value).map(|(_, _, name)| *name)
+}
+
+impl Color {
+    /// `SDR`, `HDR10` or `HLG`, or `None` if nothing is known about the color. PQ is reported as
+    /// HDR10 even without the static metadata, which players then assume.
+    pub fn dynamic_range(&self) -> Option<&'static str> {
+        match self.transfer {
+            Some("PQ") => Some("HDR10"),
+            Some("HLG") => Some("HLG"),
+            Some(_) => Some("SDR"),
+            None if self.hdr10 || self.mastering_display || self.content_light_level.is_some() => Some("HDR10"),
+            None if self.primaries.is_some() || self.matrix.is_some() => Some("SDR"),
+            None => None,
+        }
+    }
+
+    /// Like `HDR10 (BT.2020 primaries, PQ transfer, mastering display metadata, MaxCLL 1000 nits,
+    /// MaxFALL 400 nits)`.
+    pub fn describe(&self) -> Option<String> {
+        let dynamic_range = self.dynamic_range()?;
+        let mut parts = Vec::new();
+        if let Some(primaries) = self.primaries {
+            parts.push(format!("{} primaries", primaries));
+        }
+        if let Some(transfer) = self.transfer {
+            parts.push(format!("{} transfer", transfer));
+        }
+        if let Some(matrix) = self.matrix {
+            parts.push(format!("{} matrix", matrix));
+        }
+        if self.mastering_display {
+            parts.push("mastering display metadata".to_string());
+        }
+        if let Some((max_cll, max_fall)) = self.content_light_level {
+            parts.push(format!("MaxCLL {} nits, MaxFALL {} nits", max_cll, max_fall));
+        }
+        if parts.is_empty() {
+            return Some(dynamic_range.to_string());
+        }
+        Some(format!("{} ({})", dynamic_range, parts.join(", ")))
+    }
+}
+
+impl Details {
+    /// The bit depth and chroma subsampling, like `10-bit 4:2:0`, as far as they are known.
+    pub fn pixel_format(&self) -> Option<String> {
+        match (self.bit_depth, self.chroma_subsampling) {
+            (Some(bit_depth), Some(chroma_subsampling)) => Some(format!("{}-bit {}", bit_depth, chroma_subsampling)),
+            (Some(bit_depth), None) => Some(format!("{}-bit", bit_depth)),
+            (None, Some(chroma_subsampling)) => Some(chroma_subsampling.to_string()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// The details of an encode from its settings and the `mediainfo` output or command line they
+/// were read from. `setting` looks up a setting by its normalized name, so that both owned and
+/// borrowed settings can be read.
+pub fn details<'a>(input: &str, setting: impl Fn(&str) -> Option<&'a str> + Copy) -> Details {
+    let bit_depth = bit_depth(input, setting);
+    let chroma_subsampling = chroma_subsampling(input, setting);
+    Details {
+        rate_control: rate_control(setting),
+        profile: profile(setting, bit_depth, chroma_subsampling),
+        gop: gop(setting),
+        x265_era: x265_era(setting),
+        latency: latency(setting),
+        bit_depth,
+        chroma_subsampling,
+        color: color(input, setting),
+        dolby_vision: dolby_vision(input),
+        interlacing: interlacing(setting),
+        vbv: vbv(setting),
+        // x264's parameters of the same names have other defaults
+        quality_knobs: if crate::encoder_name(input) == crate::x264::ENCODER {
+            Vec::new()
+        } else {
+            QUALITY_KNOBS.iter().filter_map(|(name, _)| Some((*name, setting(name)?.to_string()))).collect()
+        },
+        category_params: CATEGORY_PARAMS.iter().filter_map(|name| Some((*name, setting(name)?.to_string()))).collect(),
+    }
+}
+
+/// The Dolby Vision metadata in `mediainfo` output, like `Dolby Vision, Version 1.0, Profile
+/// 8.1, dvhe.08.06, BL+RPU, HDR10 compatible`. Older versions of mediainfo don't write the
+/// profile, so it's read from the codec string (`dvhe.08`) and the compatibility.
+pub fn dolby_vision(input: &str) -> Option<DolbyVision> {
+    let hdr_format = crate::lines(input).find_map(|line| {
+        let (field, value) = line.split_once(" : ")?;
+        (field.trim() == "HDR format" && value.contains("Dolby Vision")).then_some(value)
+    })?;
+    let parts = hdr_format.split(['/', ',']).map(str::trim).collect::<Vec<_>>();
+    let named_profile = parts.iter().find_map(|part| part.strip_prefix("Profile ")).map(str::to_string);
+    let codec_profile = || {
+        let codec = parts.iter().find(|part| ["dvhe.", "dvh1.", "dvav.", "dva1.", "dav1."].iter().any(|prefix| part.starts_with(prefix)))?;
+        let profile = codec.split('.').nth(1)?.parse::<u32>().ok()?;
+        let compatibility = match profile {
+            8 if parts.contains(&"HDR10 compatible") => ".1",
+            8 if parts.contains(&"SDR compatible") => ".2",
+            8 if parts.contains(&"HLG compatible") => ".4",
+            _ => "",
+        };
+        Some(format!("{}{}", profile, compatibility))
+    };
+    Some(DolbyVision {
+        profile: named_profile.or_else(codec_profile),
+        layers: parts.iter().find(|part| part.starts_with("BL") && part.ends_with("RPU")).map(|layers| layers.to_string()),
+    })
+}
+
+/// How the encode was interlaced, from `interlace` (`1` or `tff` for top field first, `2` or
+/// `bff` for bottom field first), or `None` if it's progressive.
+pub fn interlacing<'a>(setting: impl Fn(&str) -> Option<&'a str>) -> Option<Interlacing> {
+    let field_order = match setting("interlace")? {
+        "1" | "tff" => "top field first",
+        "2" | "bff" => "bottom field first",
+        _ => return None,
+    };
+    Some(Interlacing { field_order, field_coding: setting("field").map(|value| value != "0") })
+}
+
+/// The VBV limits, from `vbv-maxrate`, `vbv-bufsize` and `strict-cbr`. `None` if the bitrate
+/// isn't capped (`vbv-maxrate` is missing or `0`).
+pub fn vbv<'a>(setting: impl Fn(&str) -> Option<&'a str>) -> Option<Vbv> {
+    let maxrate = setting("vbv-maxrate")?.parse::<u32>().ok().filter(|maxrate| *maxrate > 0)?;
+    Some(Vbv {
+        maxrate,
+        bufsize: setting("vbv-bufsize").and_then(|bufsize| bufsize.parse::<u32>().ok()).filter(|bufsize| *bufsize > 0),
+        strict_cbr: setting("strict-cbr").is_some_and(|strict_cbr| strict_cbr != "0"),
+    })
+}
+
+/// The color description and HDR metadata, from the settings, or else from mediainfo's
+/// `Color primaries`, `Transfer characteristics`, `Matrix coefficients`, `Mastering display
+/// color primaries` and `Maximum Content Light Level`.
+pub fn color<'a>(input: &str, setting: impl Fn(&str) -> Option<&'a str> + Copy) -> Color {
+    let reported = |field_name: &str| {
+        crate::lines(input).find_map(|line| {
+            let (field, value) = line.split_once(" : ")?;
+            (field.trim() == field_name).then(|| value.trim().to_string())
+        })
+    };
+    let lookup = |table: &[(&str, &str, &'static str)], setting_name: &str, field_name: &str| {
+        let by_field = || reported(field_name).and_then(|value| table.iter().find(|(_, _, name)| value.starts_with(name)).map(|(_, _, name)| *name));
+        setting(setting_name).and_then(|value| color_name(setting_name, value)).or_else(by_field)
+    };
+    let light_levels = setting("max-cll").or_else(|| setting("cll")).map(str::to_string).or_else(|| {
+        // e.g. `1000 cd/m2`
+        let level = |field_name: &str| reported(field_name)?.split_whitespace().next().map(str::to_string);
+        Some(format!("{},{}", level("Maximum Content Light Level")?, level("Maximum Frame-Average Light Level").unwrap_or_default()))
+    });
+    let content_light_level = light_levels.and_then(|light_levels| {
+        let (max_cll, max_fall) = light_levels.split_once(',')?;
+        Some((max_cll.trim().parse().ok()?, max_fall.trim().parse().unwrap_or(0)))
+    });
+    let enabled = |name: &str| setting(name).is_some_and(|value| value == "1");
+    Color {
+        primaries: lookup(PRIMARIES, "colorprim", "Color primaries"),
+        transfer: lookup(TRANSFERS, "transfer", "Transfer characteristics"),
+        matrix: lookup(MATRICES, "colormatrix", "Matrix coefficients"),
+        mastering_display: setting("master-display").is_some_and(|value| !value.is_empty()) || reported("Mastering display color primaries").is_some(),
+        content_light_level: content_light_level.filter(|(max_cll, max_fall)| (*max_cll, *max_fall) != (0, 0)),
+        hdr10: enabled("hdr10") || enabled("hdr"),
+    }
+}
+
+/// The bit depth of the video: mediainfo's `Bit depth`, or else the depth of the profile, the
+/// `10bit` that x265 appends to its version in the `Writing library`, or `output-depth`.
+pub fn bit_depth<'a>(input: &str, setting: impl Fn(&str) -> Option<&'a str> + Copy) -> Option<u32> {
+    if let Some(bit_depth) = crate::media_properties(input).get("bit_depth").and_then(|bit_depth| bit_depth.parse().ok()) {
+        return Some(bit_depth);
+    }
+    let profile_depth = setting("profile").map(|profile| match profile.trim_end_matches("-intra") {
+        "main10" => 10,
+        "main12" | "main422-12" | "main444-12" => 12,
+        "main422-10" | "main444-10" => 10,
+        "main444-16" => 16,
+        _ => 8,
+    });
+    let library_depth = || {
+        crate::lines(input).find_map(|line| {
+            let (field, value) = line.split_once(" : ")?;
+            if field.trim() != "Writing library" {
+                return None;
+            }
+            value.split_whitespace().find_map(|word| word.strip_suffix("bit")?.parse().ok())
+        })
+    };
+    profile_depth.or_else(library_depth).or_else(|| setting("output-depth")?.parse().ok())
+}
+
+/// The chroma subsampling of the video: mediainfo's `Chroma subsampling`, or else `input-csp`
+/// (by number, as in x265's settings string, or by name, as on its command line), or the
+/// subsampling of the profile.
+pub fn chroma_subsampling<'a>(input: &str, setting: impl Fn(&str) -> Option<&'a str> + Copy) -> Option<&'static str> {
+    const SUBSAMPLINGS: [&str; 4] = ["4:0:0", "4:2:0", "4:2:2", "4:4:4"];
+    let reported = crate::lines(input).find_map(|line| {
+        let (field, value) = line.split_once(" : ")?;
+        // e.g. `4:2:0 (Type 2)`
+        (field.trim() == "Chroma subsampling").then(|| SUBSAMPLINGS.into_iter().find(|subsampling| value.trim().starts_with(subsampling)))?
+    });
+    let input_csp = || match setting("input-csp")? {
+        "0" | "i400" => Some(SUBSAMPLINGS[0]),
+        "1" | "i420" => Some(SUBSAMPLINGS[1]),
+        "2" | "i422" => Some(SUBSAMPLINGS[2]),
+        "3" | "i444" => Some(SUBSAMPLINGS[3]),
+        _ => None,
+    };
+    let profile_subsampling = || {
+        let profile = setting("profile")?;
+        Some(if profile.starts_with("main422") {
+            SUBSAMPLINGS[2]
+        } else if profile.starts_with("main444") {
+            SUBSAMPLINGS[3]
+        } else {
+            SUBSAMPLINGS[1]
+        })
+    };
+    reported.or_else(input_csp).or_else(profile_subsampling)
+}
+
+/// How the bitrate was controlled: the mode (`rc`, with its `crf`, `bitrate` or `qp`) and the
+/// passes. x265 records a first pass with `stats-write`, a last pass with `stats-read`, and the
+/// passes between with both; on a command line, they are `--pass 1`, `2` and `3`. `None` if
+/// the settings don't say.
+pub fn rate_control<'a>(setting: impl Fn(&str) -> Option<&'a str> + Copy) -> Option<String> {
+    let mode = match setting("rc") {
+        Some("crf") => format!("CRF {}", setting("crf").unwrap_or("?")),
+        Some("abr") => format!("ABR {} kbps", setting("bitrate").unwrap_or("?")),
+        Some("cqp") => format!("constant QP {}", setting("qp").unwrap_or("?")),
+        Some(mode) => mode.to_uppercase(),
+        None => match (setting("crf"), setting("bitrate")) {
+            (Some(crf), _) => format!("CRF {}", crf),
+            (None, Some(bitrate)) => format!("ABR {} kbps", bitrate),
+            (None, None) => return None,
+        },
+    };
+    let enabled = |name: &str| setting(name).is_some_and(|value| value.parse::<u32>().is_ok_and(|value| value > 0));
+    let multi_pass_opt = enabled("multi-pass-opt-analysis") || enabled("multi-pass-opt-distortion");
+    let pass = setting("pass");
+    let stats_write = enabled("stats-write") || matches!(pass, Some("1" | "3"));
+    let stats_read = enabled("stats-read") || matches!(pass, Some("2" | "3"));
+    let passes = match (stats_write, stats_read) {
+        (true, true) => "middle pass of a multi-pass",
+        (true, false) => "first pass of a multi-pass",
+        (false, true) if multi_pass_opt => "multi-pass",
+        (false, true) => "2-pass",
+        (false, false) => "single-pass",
+    };
+    Some(format!("{} {}", passes, mode))
+}
+
+/// The GOP structure, from `open-gop`, `keyint` (`-1` for an infinite GOP), `min-keyint`,
+/// `scenecut` (`0` if it's off), `bframes` and `b-pyramid`, which decide where a video can be
+/// seeked to. `None` if the settings have none of them.
+pub fn gop<'a>(setting: impl Fn(&str) -> Option<&'a str>) -> Option<String> {
+    let mut parts = Vec::new();
+    match setting("open-gop") {
+        Some("0") => parts.push("closed GOP".to_string()),
+        Some(_) => parts.push("open GOP".to_string()),
+        None => {}
+    }
+    match setting("keyint") {
+        Some("-1") => parts.push("infinite keyint".to_string()),
+        Some(keyint) => parts.push(format!("keyint {}", keyint)),
+        None => {}
+    }
+    if let Some(min_keyint) = setting("min-keyint") {
+        parts.push(format!("min-keyint {}", min_keyint));
+    }
+    match setting("scenecut") {
+        Some("0") => parts.push("no scenecut".to_string()),
+        Some(scenecut) => parts.push(format!("scenecut {}", scenecut)),
+        None => {}
+    }
+    match setting("bframes") {
+        Some("0") => parts.push("no B-frames".to_string()),
+        Some(bframes) if setting("b-pyramid").is_some_and(|pyramid| pyramid != "0") => parts.push(format!("{} B-frames (pyramid)", bframes)),
+        Some(bframes) => parts.push(format!("{} B-frames", bframes)),
+        None => {}
+    }
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+/// Whether an encode was configured for streaming or offline, and why.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Latency {
+    /// `low-latency streaming`, `streaming` or `offline quality`.
+    pub class: &'static str,
+    /// Like `no B-frames` and `VBV-capped`.
+    pub reasons: Vec<String>,
+}
+
+impl fmt::Display for Latency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.class, self.reasons.join(", "))
+    }
+}
+
+/// Whether the encode was configured for low-latency streaming, streaming or offline quality,
+/// and why, like `low-latency streaming (no B-frames, 0 frames of lookahead, VBV-capped, 1
+/// frame thread, 4 slices)`. Only the signals of `--tune zerolatency` make an encode
+/// low-latency: no B-frames and no lookahead, or the VBV with a single frame thread. The fast
+/// presets' short lookahead doesn't, as they're used offline too. The rest capped by the VBV
+/// are for streaming, and the others offline. The VBV's limits are in `Vbv`. `None` if the settings have neither `bframes`, `rc-lookahead` nor the
+/// VBV.
+pub fn latency<'a>(setting: impl Fn(&str) -> Option<&'a str>) -> Option<Latency> {
+    let number = |name: &str| setting(name)?.parse::<f64>().ok();
+    let (bframes, lookahead) = (number("bframes"), number("rc-lookahead"));
+    let maxrate = number("vbv-maxrate").filter(|maxrate| *maxrate > 0.0);
+    if bframes.is_none() && lookahead.is_none() && maxrate.is_none() {
+        return None;
+    }
+    let frame_threads = number("frame-threads").filter(|threads| *threads > 0.0);
+    let slices = number("slices").filter(|slices| *slices > 1.0);
+    let mut reasons = Vec::new();
+    match bframes {
+        Some(0.0) => reasons.push("no B-frames".to_string()),
+        Some(bframes) => reasons.push(format!("{} B-frames", bframes)),
+        None => {}
+    }
+    if let Some(lookahead) = lookahead {
+        reasons.push(format!("{} frames of lookahead", lookahead));
+    }
+    reasons.push(if maxrate.is_some() { "VBV-capped" } else { "no VBV" }.to_string());
+    match frame_threads {
+        Some(1.0) => reasons.push("1 frame thread".to_string()),
+        Some(threads) => reasons.push(format!("{} frame threads", threads)),
+        None => {}
+    }
+    if let Some(slices) = slices {
+        reasons.push(format!("{} slices", slices));
+    }
+    let class = if (bframes == Some(0.0) && lookahead == Some(0.0)) || (maxrate.is_some() && frame_threads == Some(1.0)) {
+        "low-latency streaming"
+    } else if maxrate.is_some() {
+        "streaming"
+    } else {
+        "offline quality"
+    };
+    Some(Latency { class, reasons })
+}
+
+/// Parameters by the x265 release that added them, from the oldest.
+const X265_RELEASES: &[(&str, &[&str])] = &[
+    ("2.7", &["gop-lookahead", "radl", "lowpass-dct"]),
+    ("2.8", &["hevc-aq", "idr-recovery-sei"]),
+    ("3.0", &["svt"]),
+    ("3.1", &["frame-dup", "hme"]),
+    ("3.4", &["hist-scenecut", "scenecut-aware-qp"]),
+    ("3.5", &["vbv-live-multi-pass"]),
+    ("4.0", &["mcstf", "sbrc"]),
+];
+
+/// The x265 releases that could have written the settings, like `x265 3.4 or later, before
+/// 4.0`, from the parameters that were added in later releases (`X265_RELEASES`), as the
+/// version in the `Writing library` is often stripped. x265 writes all its options, starting
+/// with `cpuid`, so if the settings have `cpuid`, a missing parameter means an older release.
+/// `None` if the settings say neither.
+pub fn x265_era<'a>(setting: impl Fn(&str) -> Option<&'a str> + Copy) -> Option<String> {
+    let has_release = |params: &[&str]| params.iter().any(|name| setting(name).is_some());
+    let since = X265_RELEASES.iter().rposition(|(_, params)| has_release(params));
+    let before = setting("cpuid").and_then(|_| {
+        let newer = since.map_or(0, |since| since + 1);
+        X265_RELEASES[newer..].iter().position(|(_, params)| !has_release(params)).map(|position| newer + position)
+    });
+    match (since, before) {
+        (Some(since), Some(before)) => Some(format!("x265 {} or later, before {}", X265_RELEASES[since].0, X265_RELEASES[before].0)),
+        (Some(since), None) => Some(format!("x265 {} or later", X265_RELEASES[since].0)),
+        (None, Some(before)) => Some(format!("x265 before {}", X265_RELEASES[before].0)),
+        (None, None) => None,
+    }
+}
+
+/// The HEVC profile, level and tier, like `Main10@L5.1 High tier`. The profile is the one
+/// given with `--profile`, or else the one x265 picks for the bit depth and the chroma
+/// subsampling. The level is `level-idc`, which x265 records as `51` or `5.1` (or
+/// `0` if it picked the level itself). `uhd-bd` is noted, as it constrains the encode to the
+/// UHD Blu-ray specification. `None` if the settings say none of these.
+pub fn profile<'a>(setting: impl Fn(&str) -> Option<&'a str> + Copy, bit_depth: Option<u32>, chroma_subsampling: Option<&str>) -> Option<String> {
+    let profile = match setting("profile") {
+        Some(profile) => Some(profile_name(profile)),
+        None => bit_depth.map(|bit_depth| match (chroma_subsampling.unwrap_or("4:2:0"), bit_depth) {
+            ("4:0:0" | "4:2:0", 8) => "Main".to_string(),
+            ("4:0:0" | "4:2:0", _) => format!("Main{}", bit_depth),
+            ("4:2:2", _) => format!("Main 4:2:2 {}", bit_depth.max(10)),
+            (_, 8) => "Main 4:4:4".to_string(),
+            (_, _) => format!("Main 4:4:4 {}", bit_depth),
+        }),
+    };
+    let level = setting("level-idc").and_then(|level| match level.parse::<f64>().ok()? {
+        level if level <= 0.0 => None,
+        // e.g. 51 for 5.1
+        level if level >= 10.0 => Some(format!("{:.1}", level / 10.0)),
+        level => Some(format!("{:.1}", level)),
+    });
+    let tier = match setting("high-tier") {
+        Some("1") => "High",
+        _ => "Main",
+    };
+    let summary = match (profile, level) {
+        (Some(profile), Some(level)) => Some(format!("{}@L{} {} tier", profile, level, tier)),
+        (Some(profile), None) => Some(profile),
+        (None, Some(level)) => Some(format!("L{} {} tier", level, tier)),
+        (None, None) => None,
+    };
+    match (summary, setting("uhd-bd") == Some("1")) {
+        (Some(summary), true) => Some(format!("{} (UHD Blu-ray)", summary)),
+        (None, true) => Some("UHD Blu-ray".to_string()),
+        (summary, false) => summary,
+    }
+}
+
+/// The name of a profile given with `--profile`, like `Main 4:4:4 10` for `main444-10`.
+fn profile_name(profile: &str) -> String {
+    let (profile, intra) = match profile.strip_suffix("-intra") {
+        Some(profile) => (profile, " Intra"),
+        None => (profile, ""),
+    };
+    let name = match profile {
+        "main" => "Main",
+        "main10" => "Main10",
+        "main12" => "Main12",
+        "mainstillpicture" | "msp" => "Main Still Picture",
+        "main422-10" => "Main 4:2:2 10",
+        "main422-12" => "Main 4:2:2 12",
+        "main444-8" => "Main 4:4:4",
+        "main444-10" => "Main 4:4:4 10",
+        "main444-12" => "Main 4:4:4 12",
+        "main444-16" => "Main 4:4:4 16",
+        profile => profile,
+    };
+    format!("{}{}", name, intra)
+}
+
+/// Looks up the settings like the callers of the classifiers do.
+#[cfg(test)]
+fn lookup<'a>(settings: &'a std::collections::HashMap<String, String>) -> impl Fn(&str) -> Option<&'a str> + Copy {
+    |name| settings.get(name).map(String::as_str)
+}
+
+#[test]
+fn test_rate_control() {
+    let settings = crate::parse_string("rc=abr bitrate=5000 stats-write=0 stats-read=2");
+    assert_eq!(rate_control(lookup(&settings)).as_deref(), Some("2-pass ABR 5000 kbps"));
+    let settings = crate::parse_string("rc=crf crf=20.0 stats-write=0 stats-read=0");
+    assert_eq!(details("", lookup(&settings)).rate_control.as_deref(), Some("single-pass CRF 20.0"));
+    assert_eq!(rate_control(|name| (name == "stats-write").then_some("1")), None);
+    assert_eq!(rate_control(|name| [("crf", "18"), ("pass", "1")].iter().find(|(key, _)| *key == name).map(|(_, value)| *value)).as_deref(), Some("first pass of a multi-pass CRF 18"));
+}
+
+#[test]
+fn test_gop() {
+    let settings = crate::parse_settings("Encoding settings : open-gop / min-keyint=25 / keyint=250 / bframes=4 / b-pyramid / scenecut=40");
+    assert_eq!(gop(lookup(&settings)).as_deref(), Some("open GOP, keyint 250, min-keyint 25, scenecut 40, 4 B-frames (pyramid)"));
+    let settings = crate::parse_settings("Encoding settings : no-open-gop / keyint=-1 / bframes=0 / scenecut=0");
+    assert_eq!(gop(lookup(&settings)).as_deref(), Some("closed GOP, infinite keyint, no scenecut, no B-frames"));
+    assert_eq!(gop(|_| None), None);
+}
+
+#[test]
+fn test_x265_era() {
+    let settings = crate::parse_settings("Encoding settings : cpuid=1111039 / gop-lookahead=0 / radl=0 / no-hevc-aq / no-svt / no-frame-dup / no-hme / no-hist-scenecut / scenecut-aware-qp=0");
+    assert_eq!(x265_era(lookup(&settings)).as_deref(), Some("x265 3.4 or later, before 3.5"));
+    let settings = crate::parse_settings("Encoding settings : cpuid=1111039 / ctu=64 / bframes=4");
+    assert_eq!(x265_era(lookup(&settings)).as_deref(), Some("x265 before 2.7"));
+    // without cpuid, the settings could be partial
+    let settings = crate::parse_string("ctu=64 mcstf=0");
+    assert_eq!(x265_era(lookup(&settings)).as_deref(), Some("x265 4.0 or later"));
+    assert_eq!(x265_era(|_| None), None);
+}
+
+#[test]
+fn test_latency() {
+    let settings = crate::parse_string("bframes=0 rc-lookahead=0 vbv-maxrate=5000 vbv-bufsize=5000 frame-threads=1 slices=4");
+    assert_eq!(latency(lookup(&settings)).map(|latency| latency.to_string()).as_deref(), Some("low-latency streaming (no B-frames, 0 frames of lookahead, VBV-capped, 1 frame thread, 4 slices)"));
+    let settings = crate::parse_string("bframes=4 rc-lookahead=20 vbv-maxrate=8000 vbv-bufsize=16000 frame-threads=4 slices=1");
+    assert_eq!(latency(lookup(&settings)).map(|latency| latency.to_string()).as_deref(), Some("streaming (4 B-frames, 20 frames of lookahead, VBV-capped, 4 frame threads)"));
+    let settings = crate::parse_string("bframes=8 rc-lookahead=40 vbv-maxrate=0");
+    assert_eq!(latency(lookup(&settings)).map(|latency| latency.to_string()).as_deref(), Some("offline quality (8 B-frames, 40 frames of lookahead, no VBV)"));
+    // the fast presets' short lookahead isn't a streaming setup
+    let settings = crate::parse_string("ctu=32 bframes=3 b-adapt=0 rc-lookahead=5 scenecut=0 ref=1 crf=28");
+    assert_eq!(latency(lookup(&settings)).map(|latency| latency.class), Some("offline quality"));
+    let settings = crate::parse_string("bframes=0 rc-lookahead=20 vbv-maxrate=8000 frame-threads=4 slices=4");
+    assert_eq!(latency(lookup(&settings)).map(|latency| latency.class), Some("streaming"));
+    assert_eq!(latency(|_| None), None);
+}
+
+#[test]
+fn test_vbv() {
+    let settings = crate::parse_settings("Encoding settings : vbv-maxrate=8000 / vbv-bufsize=16000 / strict-cbr");
+    assert_eq!(vbv(lookup(&settings)).map(|vbv| vbv.to_string()).as_deref(), Some("8000 kbps max with a 16000 kbit buffer (2.0 s), strict CBR"));
+    let settings = crate::parse_settings("Encoding settings : vbv-maxrate=0 / vbv-bufsize=0 / no-strict-cbr");
+    assert_eq!(vbv(lookup(&settings)), None);
+}
+
+#[test]
+fn test_profile() {
+    let settings = crate::parse_string("input-csp=1 level-idc=51 high-tier=1 uhd-bd=0");
+    let main10 = details("Video\nBit depth : 10 bits\n", lookup(&settings));
+    assert_eq!((main10.profile.as_deref(), main10.pixel_format().as_deref()), (Some("Main10@L5.1 High tier"), Some("10-bit 4:2:0")));
+    assert_eq!(profile(|name| (name == "profile").then_some("main444-10"), None, None).as_deref(), Some("Main 4:4:4 10"));
+    assert_eq!(profile(|name| (name == "level-idc").then_some("4.1"), Some(8), None).as_deref(), Some("Main@L4.1 Main tier"));
+    let input = "Video\nWriting library : x265 3.5+1-f0c1022b6:[Linux][GCC 9.3.0][64 bit] 12bit\nChroma subsampling : 4:2:2\n";
+    assert_eq!((bit_depth(input, |_| None), chroma_subsampling(input, |_| None)), (Some(12), Some("4:2:2")));
+    assert_eq!(chroma_subsampling("", |name| (name == "profile").then_some("main444-10")), Some("4:4:4"));
+}
+
+#[test]
+fn test_color() {
+    let settings = crate::parse_string("colorprim=9 transfer=16 colormatrix=9 master-display=G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(10000000,1) max-cll=1000,400");
+    let description = "HDR10 (BT.2020 primaries, PQ transfer, BT.2020 non-constant matrix, mastering display metadata, MaxCLL 1000 nits, MaxFALL 400 nits)";
+    assert_eq!(color("", lookup(&settings)).describe().as_deref(), Some(description));
+    let input = "Video\nColor primaries : BT.2020\nTransfer characteristics : HLG\n";
+    assert_eq!(color(input, |_| None).describe().as_deref(), Some("HLG (BT.2020 primaries, HLG transfer)"));
+    assert_eq!(color("", |name| (name == "colorprim").then_some("bt709")).dynamic_range(), Some("SDR"));
+    assert_eq!((color_name("colorprim", "9"), color_name("transfer", "16"), color_name("colormatrix", "9")), (Some("BT.2020"), Some("PQ"), Some("BT.2020 non-constant")));
+    assert_eq!((color_name("colorprim", "2"), color_name("crf", "9")), (None, None));
+}
+
+#[test]
+fn test_dolby_vision() {
+    let input = "Video\nHDR format : Dolby Vision, Version 1.0, dvhe.08.06, BL+RPU, HDR10 compatible / SMPTE ST 2086, HDR10 compatible\n";
+    assert_eq!(dolby_vision(input).map(|dolby_vision| dolby_vision.to_string()).as_deref(), Some("Dolby Vision profile 8.1, BL+RPU"));
+    assert_eq!(dolby_vision("Video\nHDR format : SMPTE ST 2086, HDR10 compatible\n"), None);
+}
+
+#[test]
+fn test_interlacing() {
+    let settings = crate::parse_settings("Encoding settings : interlace=1 / no-field");
+    assert_eq!(interlacing(lookup(&settings)).map(|interlacing| interlacing.to_string()).as_deref(), Some("interlaced, top field first, without field coding (no-field)"));
+    assert_eq!(interlacing(|name| (name == "interlace").then_some("0")), None);
+}
+
+#[test]
+fn test_knob_default() {
+    assert!(is_knob_default("psy-rd", "2", None));
+    assert!(is_knob_default("deblock", "0,0", None) && !is_knob_default("deblock", "-1:-1", None));
+    assert!(is_knob_default("psy-rdoq", "1.00", Some("slow")) && !is_knob_default("psy-rdoq", "1.00", Some("medium")));
+}
+
+#[test]
+fn test_quality_category() {
+    let settings = crate::parse_string("crf=18.0 bframes=8 rc-lookahead=40 vbv-maxrate=0");
+    let archival = details("", lookup(&settings));
+    assert_eq!(quality_category(&archival, Some("veryslow")).as_deref(), Some("archival-grade (veryslow preset, CRF 18)"));
+    assert_eq!(quality_category(&archival, Some("medium")).as_deref(), Some("balanced (medium preset, CRF 18)"));
+    let settings = crate::parse_string("bframes=4 rc-lookahead=20 vbv-maxrate=8000 vbv-bufsize=16000 frame-threads=4 slices=1");
+    assert_eq!(quality_category(&details("", lookup(&settings)), Some("medium")).as_deref(), Some("streaming (medium preset)"));
+    let settings = crate::parse_string("crf=23 bframes=0 rc-lookahead=0");
+    assert_eq!(quality_category(&details("", lookup(&settings)), Some("fast")).as_deref(), Some("low-latency streaming (fast preset, CRF 23)"));
+    let settings = crate::parse_string("ctu=32 bframes=3 b-adapt=0 rc-lookahead=5 scenecut=0 ref=1 crf=28");
+    assert_eq!(quality_category(&details("", lookup(&settings)), Some("ultrafast")).as_deref(), Some("speed-focused (ultrafast preset, CRF 28)"));
+    let settings = crate::parse_string("bframes=3 rc-lookahead=10 crf=28");
+    assert_eq!(quality_category(&details("", lookup(&settings)), Some("superfast")).as_deref(), Some("speed-focused (superfast preset, CRF 28)"));
+}
@@ -0,0 +1,129 @@
+use tracing::info;
+
+use crate::{lines, params};
+
+/// Whether text is ffprobe's default output, like that of `ffprobe -show_streams`: sections
+/// like `[STREAM]` ... `[/STREAM]`, with `key=value` lines like `codec_type=video`.
+pub fn is_ffprobe(text: &str) -> bool {
+    lines(text).any(|line| line.trim() == "[STREAM]") && lines(text).any(|line| line.trim().starts_with("codec_type="))
+}
+
+/// Rewrites ffprobe's default output as the `mediainfo` fields it has, so that it's read like
+/// `mediainfo` output. `None` if the text isn't from ffprobe.
+///
+/// The first HEVC stream gives the video's fields, and the container's tags (from
+/// `-show_format`) give the `General` ones. ffprobe doesn't read the settings that x265 writes
+/// into the stream, so the encoding settings are taken from a tag of the stream that has x265
+/// parameters, like those that some tools write into Matroska files.
+pub fn to_mediainfo(text: &str) -> Option<String> {
+    if !is_ffprobe(text) {
+        return None;
+    }
+    let mut sections: Vec<(&str, Vec<(&str, &str)>)> = Vec::new();
+    for line in lines(text) {
+        let line = line.trim();
+        if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            if !section.starts_with('/') {
+                sections.push((section, Vec::new()));
+            }
+            continue;
+        }
+        if let (Some((_, fields)), Some((key, value))) = (sections.last_mut(), line.split_once('=')) {
+            fields.push((key, value.trim()));
+        }
+    }
+    let video = sections.iter()
+        .find(|(section, fields)| *section == "STREAM" && fields.contains(&("codec_name", "hevc")))
+        .map(|(_, fields)| fields.as_slice())
+        .unwrap_or_default();
+
+    let mut general = vec!["General".to_string()];
+    for (_, fields) in sections.iter().filter(|(section, _)| *section == "FORMAT") {
+        for (key, value) in fields {
+            let Some(tag) = key.strip_prefix("TAG:") else { continue };
+            let mediainfo_field = match tag.to_lowercase().as_str() {
+                "encoder" => "Writing application",
+                "encoded_by" => "Encoded by",
+                "comment" => "Comment",
+                "description" => "Description",
+                name if name.contains("preset") => tag,
+                _ => continue,
+            };
+            general.push(format!("{} : {}", mediainfo_field, value));
+        }
+    }
+
+    let mut fields = vec!["Video".to_string()];
+    if !video.is_empty() {
+        fields.push("Format : HEVC".to_string());
+    }
+    let mut settings = None;
+    for (key, value) in video {
+        match *key {
+            "width" => fields.push(format!("Width : {} pixels", value)),
+            "height" => fields.push(format!("Height : {} pixels", value)),
+            // e.g. `24000/1001`
+            "r_frame_rate" => {
+                if let Some((numerator, denominator)) = value.split_once('/').and_then(|(numerator, denominator)| Some((numerator.parse::<f64>().ok()?, denominator.parse::<f64>().ok()?))) {
+                    if denominator != 0.0 {
+                        fields.push(format!("Frame rate : {:.3} FPS", numerator / denominator));
+                    }
+                }
+            }
+            // e.g. `yuv420p10le`
+            "pix_fmt" => {
+                let bit_depth = value.trim_end_matches("le").trim_end_matches("be").rsplit_once('p').map_or("8", |(_, bits)| if bits.is_empty() { "8" } else { bits });
+                fields.push(format!("Bit depth : {} bits", bit_depth));
+                if let Some(chroma) = CHROMA.iter().find(|(prefix, _)| value.starts_with(prefix)).map(|(_, chroma)| chroma) {
+                    fields.push(format!("Chroma subsampling : {}", chroma));
+                }
+            }
+            "color_primaries" => fields.extend(lookup(PRIMARIES, value).map(|primaries| format!("Color primaries : {}", primaries))),
+            "color_transfer" => fields.extend(lookup(TRANSFERS, value).map(|transfer| format!("Transfer characteristics : {}", transfer))),
+            "color_space" => fields.extend(lookup(MATRICES, value).map(|matrix| format!("Matrix coefficients : {}", matrix))),
+            _ => {
+                let Some(tag) = key.strip_prefix("TAG:") else { continue };
+                if tag.eq_ignore_ascii_case("encoder") {
+                    fields.push(format!("Writing library : {}", value));
+                } else if settings.is_none() && has_params(value) {
+                    settings = Some(value);
+                }
+            }
+        }
+    }
+    match settings {
+        Some(settings) => fields.push(format!("Encoding settings : {}", settings)),
+        None => info!("ffprobe's output doesn't have the encoder settings, so the preset can only be determined from a video's tags. Pass the video itself to read its settings with mediainfo"),
+    }
+    Some(format!("{}\n\n{}\n", general.join("\n"), fields.join("\n")))
+}
+
+/// Whether a tag's value has `key=value` settings of x265 parameters.
+fn has_params(value: &str) -> bool {
+    value.split([' ', '/', ':']).any(|pair| pair.split_once('=').is_some_and(|(name, _)| params::find(name).is_some()))
+}
+
+/// The chroma subsampling of ffmpeg's pixel formats, by the start of their names.
+const CHROMA: &[(&str, &str)] = &[("yuv420", "4:2:0"), ("yuv422", "4:2:2"), ("yuv444", "4:4:4"), ("gray", "4:0:0")];
+
+/// The `mediainfo` names of ffmpeg's color primaries.
+const PRIMARIES: &[(&str, &str)] = &[("bt2020", "BT.2020"), ("bt709", "BT.709"), ("bt470bg", "BT.601 PAL"), ("smpte170m", "BT.601 NTSC"), ("smpte432", "Display P3")];
+
+/// The transfer functions, like `PRIMARIES`.
+const TRANSFERS: &[(&str, &str)] = &[("smpte2084", "PQ"), ("arib-std-b67", "HLG"), ("iec61966-2-1", "sRGB"), ("bt709", "BT.709"), ("smpte170m", "BT.601")];
+
+/// The color spaces (matrix coefficients), like `PRIMARIES`.
+const MATRICES: &[(&str, &str)] = &[("bt2020nc", "BT.2020 non-constant"), ("bt2020c", "BT.2020 constant"), ("bt709", "BT.709"), ("bt470bg", "BT.601"), ("smpte170m", "BT.601")];
+
+fn lookup(table: &[(&str, &'static str)], value: &str) -> Option<&'static str> {
+    table.iter().find(|(name, _)| *name == value).map(|(_, name)| *name)
+}
+
+#[test]
+fn test_ffprobe() {
+    let text = "[STREAM]\nindex=0\ncodec_name=hevc\ncodec_type=video\nwidth=3840\nheight=2160\npix_fmt=yuv420p10le\ncolor_space=bt2020nc\ncolor_transfer=smpte2084\ncolor_primaries=bt2020\nr_frame_rate=24000/1001\nTAG:ENCODER=Lavc60.31.102 libx265\nTAG:X265_SETTINGS=ctu=64 / rect / no-amp / bframes=8\n[/STREAM]\n[STREAM]\nindex=1\ncodec_name=aac\ncodec_type=audio\n[/STREAM]\n[FORMAT]\nTAG:encoder=Lavf60.16.100\nTAG:comment=x265 slow\n[/FORMAT]\n";
+    let mediainfo = to_mediainfo(text).unwrap();
+    assert_eq!(mediainfo, "General\nWriting application : Lavf60.16.100\nComment : x265 slow\n\nVideo\nFormat : HEVC\nWidth : 3840 pixels\nHeight : 2160 pixels\nBit depth : 10 bits\nChroma subsampling : 4:2:0\nMatrix coefficients : BT.2020 non-constant\nTransfer characteristics : PQ\nColor primaries : BT.2020\nFrame rate : 23.976 FPS\nWriting library : Lavc60.31.102 libx265\nEncoding settings : ctu=64 / rect / no-amp / bframes=8\n");
+    assert_eq!(crate::parse_settings(&mediainfo).get("amp").map(String::as_str), Some("0"));
+    assert_eq!(to_mediainfo("ctu=64 bframes=8\n"), None);
+}
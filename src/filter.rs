@@ -0,0 +1,228 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+/// A condition on the determination and settings of an input, like
+/// `preset >= slow && crf <= 20 && bit_depth == 10`.
+///
+/// Conditions compare a name with a value, and are combined with `&&`, `||`, `!` and
+/// parentheses. `preset` is compared by speed, so `preset >= slow` means `slow` or slower; if
+/// several presets match, all of them must satisfy the comparison. Other names are settings
+/// (`crf`, `aq-mode`, `rd` or `rdLevel`) or properties of the video (`bit_depth`, `width`,
+/// `height`), and are compared as numbers where both sides are numbers. A comparison with a name
+/// that the input doesn't have is false.
+#[derive(Debug, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Compare(String, Operator, String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// What a filter is evaluated against.
+pub struct Context<'a> {
+    /// The names of all presets, from the fastest to the slowest.
+    pub preset_order: Vec<&'a str>,
+    /// The presets that match the input.
+    pub matching_presets: &'a [String],
+    /// The input's settings and video properties.
+    pub values: &'a HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Operator(Operator),
+    Word(String),
+}
+
+pub fn parse(text: &str) -> Result<Filter, String> {
+    let tokens = tokenize(text)?;
+    let mut position = 0;
+    let filter = parse_or(&tokens, &mut position)?;
+    match tokens.get(position) {
+        None => Ok(filter),
+        Some(token) => Err(format!("Unexpected {} in the filter", describe(token))),
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' | '|' => {
+                if chars.next_if_eq(&c).is_none() {
+                    return Err(format!("Expected {}{} in the filter", c, c));
+                }
+                if c == '&' { Token::And } else { Token::Or }
+            }
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::NotEqual),
+            '!' => Token::Not,
+            '=' => {
+                chars.next_if_eq(&'='); // `=` and `==` are the same
+                Token::Operator(Operator::Equal)
+            }
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::LessOrEqual),
+            '<' => Token::Operator(Operator::Less),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::GreaterOrEqual),
+            '>' => Token::Operator(Operator::Greater),
+            '\'' | '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => word.push(next),
+                        None => return Err(format!("Unterminated {} quote in the filter", c)),
+                    }
+                }
+                Token::Word(word)
+            }
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some(next) = chars.next_if(|next| is_word_char(*next)) {
+                    word.push(next);
+                }
+                Token::Word(word)
+            }
+            c => return Err(format!("Unexpected character {} in the filter", c)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-.:,".contains(c)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => format!("'{}'", word),
+        Token::And => "&&".to_string(),
+        Token::Or => "||".to_string(),
+        Token::Not => "!".to_string(),
+        Token::Open => "(".to_string(),
+        Token::Close => ")".to_string(),
+        Token::Operator(_) => "comparison".to_string(),
+    }
+}
+
+fn parse_or(tokens: &[Token], position: &mut usize) -> Result<Filter, String> {
+    let mut filter = parse_and(tokens, position)?;
+    while tokens.get(*position) == Some(&Token::Or) {
+        *position += 1;
+        filter = Filter::Or(Box::new(filter), Box::new(parse_and(tokens, position)?));
+    }
+    Ok(filter)
+}
+
+fn parse_and(tokens: &[Token], position: &mut usize) -> Result<Filter, String> {
+    let mut filter = parse_unary(tokens, position)?;
+    while tokens.get(*position) == Some(&Token::And) {
+        *position += 1;
+        filter = Filter::And(Box::new(filter), Box::new(parse_unary(tokens, position)?));
+    }
+    Ok(filter)
+}
+
+fn parse_unary(tokens: &[Token], position: &mut usize) -> Result<Filter, String> {
+    *position += 1;
+    match tokens.get(*position - 1) {
+        Some(Token::Not) => Ok(Filter::Not(Box::new(parse_unary(tokens, position)?))),
+        Some(Token::Open) => {
+            let filter = parse_or(tokens, position)?;
+            if tokens.get(*position) != Some(&Token::Close) {
+                return Err("Expected ) in the filter".to_string());
+            }
+            *position += 1;
+            Ok(filter)
+        }
+        Some(Token::Word(name)) => {
+            let (Some(Token::Operator(operator)), Some(Token::Word(value))) = (tokens.get(*position), tokens.get(*position + 1)) else {
+                return Err(format!("Expected a comparison like {} == value in the filter", name));
+            };
+            *position += 2;
+            Ok(Filter::Compare(name.clone(), *operator, value.clone()))
+        }
+        Some(token) => Err(format!("Unexpected {} in the filter", describe(token))),
+        None => Err("Unexpected end of the filter".to_string()),
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, context: &Context) -> Result<bool, String> {
+        match self {
+            Filter::And(left, right) => Ok(left.matches(context)? && right.matches(context)?),
+            Filter::Or(left, right) => Ok(left.matches(context)? || right.matches(context)?),
+            Filter::Not(filter) => Ok(!filter.matches(context)?),
+            Filter::Compare(name, operator, value) if name == "preset" => {
+                let speed = |preset_name: &str| context.preset_order.iter().position(|name| *name == preset_name);
+                // presets can also be given by their position, like x265 takes them
+                let value_speed = speed(value)
+                    .or_else(|| value.parse::<usize>().ok().filter(|speed| *speed < context.preset_order.len()))
+                    .ok_or_else(|| format!("Unknown preset in the filter: {}", value))?;
+                Ok(!context.matching_presets.is_empty() && context.matching_presets.iter().all(|preset_name| {
+                    speed(preset_name).is_some_and(|speed| operator.holds(speed.cmp(&value_speed)))
+                }))
+            }
+            Filter::Compare(name, operator, value) => {
+                let (name, value) = crate::params::normalize(name, value);
+                let Some(actual) = context.values.get(&name) else { return Ok(false) };
+                match (actual.parse::<f64>(), value.parse::<f64>()) {
+                    (Ok(actual), Ok(value)) => Ok(actual.partial_cmp(&value).is_some_and(|ordering| operator.holds(ordering))),
+                    _ => match operator {
+                        Operator::Equal => Ok(*actual == value),
+                        Operator::NotEqual => Ok(*actual != value),
+                        _ => Err(format!("{} can only be compared with == or != in the filter", name)),
+                    },
+                }
+            }
+        }
+    }
+}
+
+impl Operator {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Operator::Equal => ordering == Ordering::Equal,
+            Operator::NotEqual => ordering != Ordering::Equal,
+            Operator::Less => ordering == Ordering::Less,
+            Operator::LessOrEqual => ordering != Ordering::Greater,
+            Operator::Greater => ordering == Ordering::Greater,
+            Operator::GreaterOrEqual => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[test]
+fn test_filter() {
+    let values = HashMap::from([("crf".to_string(), "18.0".to_string()), ("bit_depth".to_string(), "10".to_string()), ("me".to_string(), "star".to_string())]);
+    let matching_presets = ["slower".to_string(), "veryslow".to_string()];
+    let context = Context { preset_order: vec!["medium", "slow", "slower", "veryslow"], matching_presets: &matching_presets, values: &values };
+    let matches = |text: &str| parse(text).and_then(|filter| filter.matches(&context));
+    assert_eq!(matches("preset >= slow && crf <= 20 && bit_depth == 10"), Ok(true));
+    assert_eq!(matches("preset > slower"), Ok(false)); // not all matching presets are
+    assert_eq!(matches("preset >= 2"), Ok(true));
+    assert_eq!(matches("!(crf < 18) && (me = 3 || rd > 4)"), Ok(true));
+    assert_eq!(matches("aq-mode != 2"), Ok(false)); // not in the input
+    assert!(matches("me < star").is_err());
+    assert!(matches("preset >= fastest").is_err());
+    assert!(parse("crf <= 20 &&").is_err());
+    assert!(parse("(crf <= 20").is_err());
+}
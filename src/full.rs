@@ -0,0 +1,90 @@
+use crate::lines;
+
+/// The internal names that `mediainfo --Full --Language=raw` prints the fields that are read
+/// with, and the names `mediainfo` prints them with by default.
+const RAW_FIELDS: &[(&str, &str)] = &[
+    ("Encoded_Library_Settings", "Encoding settings"),
+    ("CompleteName", "Complete name"),
+    ("Encoded_Application", "Writing application"),
+    ("Encoded_Library", "Writing library"),
+    ("Encoded_By", "Encoded by"),
+    ("BitDepth", "Bit depth"),
+    ("ChromaSubsampling", "Chroma subsampling"),
+    ("colour_primaries", "Color primaries"),
+    ("transfer_characteristics", "Transfer characteristics"),
+    ("matrix_coefficients", "Matrix coefficients"),
+    ("HDR_Format", "HDR format"),
+];
+
+/// The default name of a `mediainfo` field, which is the field itself unless it's an internal
+/// name.
+pub fn default_name(field: &str) -> &str {
+    RAW_FIELDS.iter().find(|(raw_name, _)| *raw_name == field).map_or(field, |(_, name)| name)
+}
+
+/// Whether a line of `mediainfo` output has (a part of) the encoder settings.
+pub fn is_settings_line(line: &str) -> bool {
+    line.split_once(" : ").is_some_and(|(field, _)| default_name(field.trim()) == "Encoding settings")
+}
+
+/// Reassembles the output of `mediainfo --Full`, which can print the encoder settings on
+/// several lines, one or more settings to a line, into a single `Encoding settings` line, and
+/// renames the internal field names of `--Language=raw` to the default ones. `None` if the text
+/// is already in the default form.
+///
+/// `--Full` can print the settings both whole and split up, so repeated settings are kept once.
+pub fn reassemble(text: &str) -> Option<String> {
+    let settings_lines = lines(text).filter(|line| is_settings_line(line)).count();
+    let raw = lines(text).any(|line| line.split_once(" : ").is_some_and(|(field, _)| default_name(field.trim()) != field.trim()));
+    if settings_lines < 2 && !raw {
+        return None;
+    }
+
+    let mut output = Vec::new();
+    // the index in `output` of the settings line of the current section, and its settings
+    let mut settings: Option<(usize, Vec<&str>)> = None;
+    for line in lines(text) {
+        let Some((field, value)) = line.split_once(" : ") else {
+            // a section header ends the settings of the previous section
+            finish(&mut output, settings.take());
+            output.push(line.to_string());
+            continue;
+        };
+        let name = default_name(field.trim());
+        if name == "Encoding settings" {
+            let (_, section_settings) = settings.get_or_insert_with(|| {
+                output.push(String::new());
+                (output.len() - 1, Vec::new())
+            });
+            for setting in value.split(" / ").map(str::trim).filter(|setting| !setting.is_empty()) {
+                if !section_settings.contains(&setting) {
+                    section_settings.push(setting);
+                }
+            }
+            continue;
+        }
+        if name == field.trim() {
+            output.push(line.to_string());
+        } else {
+            output.push(format!("{} : {}", name, value.trim()));
+        }
+    }
+    finish(&mut output, settings);
+    Some(output.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+fn finish(output: &mut [String], settings: Option<(usize, Vec<&str>)>) {
+    if let Some((index, settings)) = settings {
+        output[index] = format!("Encoding settings : {}", settings.join(" / "));
+    }
+}
+
+#[test]
+fn test_full() {
+    let text = "General\nCompleteName : a.mkv\n\nVideo\nWidth : 1920\nEncoded_Library : x265 3.5\nEncoded_Library_Settings : cpuid=1111039 / ctu=64\nEncoded_Library_Settings : rect / no-amp\nEncoded_Library_Settings : ctu=64\nBitDepth : 10\n\nAudio\nFormat : AAC\n";
+    let mediainfo = reassemble(text).unwrap();
+    assert_eq!(mediainfo, "General\nComplete name : a.mkv\n\nVideo\nWidth : 1920\nWriting library : x265 3.5\nEncoding settings : cpuid=1111039 / ctu=64 / rect / no-amp\nBit depth : 10\n\nAudio\nFormat : AAC\n");
+    assert_eq!(crate::parse_settings(&mediainfo).get("amp").map(String::as_str), Some("0"));
+    assert_eq!(reassemble("General\nComplete name : a.mkv\n\nVideo\nEncoding settings : ctu=64\n"), None);
+    assert_eq!(reassemble("Encoding settings : ctu=64\nEncoding settings : rect\n").unwrap(), "Encoding settings : ctu=64 / rect\n");
+}
@@ -0,0 +1,47 @@
+use crate::{lines, Determination};
+
+/// HandBrake's built-in preset families, by the x265 preset they encode with. The General
+/// presets (like `HQ 1080p30 Surround`) keep their encoder preset when the video encoder is
+/// switched to H.265, and the Matroska `H.265 MKV` presets use x265's default.
+const PRESET_FAMILIES: &[(&str, &str)] = &[
+    ("veryfast", "Very Fast"),
+    ("fast", "Fast"),
+    ("medium", "H.265 MKV"),
+    ("slow", "HQ"),
+    ("veryslow", "Super HQ"),
+];
+
+/// Whether `mediainfo` output is of a video that HandBrake encoded, which it records in the
+/// `Writing application` or `Encoded by` tag, like `HandBrake 1.6.1 2023012300`.
+pub fn is_handbrake(input: &str) -> bool {
+    lines(input)
+        .filter_map(|line| line.split_once(" : "))
+        .any(|(field, value)| matches!(field.trim(), "Writing application" | "Encoded by") && value.trim_start().starts_with("HandBrake"))
+}
+
+/// The family of HandBrake presets that encode with an x265 preset, like `HQ` for `slow`. The
+/// presets of a family differ in their resolution and audio, which don't show in the x265
+/// settings.
+pub fn preset_family(x265_preset: &str) -> Option<&'static str> {
+    PRESET_FAMILIES.iter().find(|(preset_name, _)| *preset_name == x265_preset).map(|(_, family)| *family)
+}
+
+/// The HandBrake preset family that likely encoded the input: the family of the matching
+/// preset, if HandBrake encoded it.
+pub fn likely_preset_family(input: &str, determination: &Determination) -> Option<&'static str> {
+    match determination {
+        Determination::Match(preset_name) if is_handbrake(input) => preset_family(preset_name),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_handbrake() {
+    assert!(is_handbrake("General\nWriting application : HandBrake 1.6.1 2023012300\n"));
+    assert!(!is_handbrake("General\nWriting application : Lavf60.16.100\n"));
+    assert_eq!(preset_family("slow"), Some("HQ"));
+    assert_eq!(preset_family("placebo"), None);
+    let handbrake = "General\nEncoded by : HandBrake 1.7.2\n";
+    assert_eq!(likely_preset_family(handbrake, &Determination::Match("veryslow".to_string())), Some("Super HQ"));
+    assert_eq!(likely_preset_family(handbrake, &Determination::Ambiguous(vec!["slow".to_string(), "slower".to_string()])), None);
+}
@@ -0,0 +1,99 @@
+use std::env;
+
+use crate::x264;
+
+/// The language of the messages and table headers.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Lang {
+    #[default]
+    En,
+    Es,
+    De,
+}
+
+/// The translated messages: the English text, which is also the key, then Spanish and German.
+/// Messages that aren't listed are printed in English. The command line tool and the bindings
+/// both take their messages from here.
+const MESSAGES: &[[&str; 3]] = &[
+    ["Multiple matching presets found", "Se encontraron varios presets coincidentes", "Mehrere passende Presets gefunden"],
+    ["No matching presets found", "No se encontró ningún preset coincidente", "Kein passendes Preset gefunden"],
+    ["Closest matches", "Coincidencias más cercanas", "Nächste Treffer"],
+    ["Partial matches", "Coincidencias parciales", "Teilweise Treffer"],
+    ["matching parameter", "parámetro coincidente", "übereinstimmender Parameter"],
+    ["matching parameters", "parámetros coincidentes", "übereinstimmende Parameter"],
+    ["The input has none of the parameters that the presets set", "La entrada no tiene ninguno de los parámetros que fijan los presets", "Die Eingabe hat keinen der Parameter, die die Presets setzen"],
+    ["no encoder settings found", "no se encontraron ajustes del codificador", "keine Encoder-Einstellungen gefunden"],
+    ["The video was encoded by a hardware encoder, which doesn't record its settings", "El vídeo se codificó con un codificador por hardware, que no guarda sus ajustes", "Das Video wurde mit einem Hardware-Encoder kodiert, der seine Einstellungen nicht speichert"],
+    ["hardware encoder", "codificador por hardware", "Hardware-Encoder"],
+    ["The video was encoded by x264, whose settings can't be matched against the x265 presets", "El vídeo se codificó con x264, cuyos ajustes no se pueden comparar con los presets de x265", "Das Video wurde mit x264 kodiert, dessen Einstellungen nicht mit den x265-Presets verglichen werden können"],
+    ["not x265", "no es x265", "kein x265"],
+    ["ambiguous", "ambiguo", "mehrdeutig"],
+    ["no match", "sin coincidencia", "kein Treffer"],
+    ["closest", "más cercano", "am nächsten"],
+    ["parameter", "parámetro", "Parameter"],
+    ["parameters", "parámetros", "Parameter"],
+    ["preset", "preset", "Preset"],
+    ["speed", "velocidad", "Tempo"],
+    ["input", "entrada", "Eingabe"],
+    ["match/differ", "coincide/difiere", "gleich/anders"],
+    ["changes needed", "cambios necesarios", "nötige Änderungen"],
+    ["to change", "por cambiar", "zu ändern"],
+    ["notes", "notas", "Hinweise"],
+    ["Estimated speed", "Velocidad estimada", "Geschätztes Tempo"],
+    ["The settings are NULL", "Los ajustes son NULL", "Die Einstellungen sind NULL"],
+    ["The settings aren't valid UTF-8", "Los ajustes no son UTF-8 válido", "Die Einstellungen sind kein gültiges UTF-8"],
+];
+
+impl Lang {
+    /// The language of the locale in `LC_ALL`, `LC_MESSAGES` or `LANG`, like `de_DE.UTF-8`.
+    pub fn from_env() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| Lang::from_locale(&locale))
+            .unwrap_or_default()
+    }
+
+    fn from_locale(locale: &str) -> Option<Lang> {
+        match locale.get(..2)? {
+            "es" => Some(Lang::Es),
+            "de" => Some(Lang::De),
+            _ => Some(Lang::En),
+        }
+    }
+
+    /// Translates an English message.
+    pub fn tr(self, message: &'static str) -> &'static str {
+        let column = match self {
+            Lang::En => return message,
+            Lang::Es => 1,
+            Lang::De => 2,
+        };
+        MESSAGES.iter().find(|translations| translations[0] == message).map_or(message, |translations| translations[column])
+    }
+
+    /// Why the settings of a video encoded by `encoder` can't be matched, for
+    /// `Determination::NotDerivable`.
+    pub fn not_derivable_message(self, encoder: &str) -> String {
+        if encoder == x264::ENCODER {
+            return self.tr("The video was encoded by x264, whose settings can't be matched against the x265 presets").to_string();
+        }
+        format!("{} ({})", self.tr("The video was encoded by a hardware encoder, which doesn't record its settings"), encoder)
+    }
+
+    /// Like `hardware encoder: NVENC` or `not x265: x264`, for a list of videos.
+    pub fn not_derivable_summary(self, encoder: &str) -> String {
+        format!("{}: {}", self.tr(if encoder == x264::ENCODER { "not x265" } else { "hardware encoder" }), encoder)
+    }
+}
+
+#[test]
+fn test_translations() {
+    assert_eq!(Lang::from_locale("de_DE.UTF-8"), Some(Lang::De));
+    assert_eq!(Lang::from_locale("C"), None);
+    assert_eq!(Lang::Es.tr("input"), "entrada");
+    assert_eq!(Lang::De.tr("an untranslated message"), "an untranslated message");
+    assert_eq!(Lang::Es.not_derivable_summary("NVENC"), "codificador por hardware: NVENC");
+    assert!(MESSAGES.iter().all(|translations| translations.iter().all(|message| !message.is_empty())));
+}
@@ -0,0 +1,652 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{stdin, BufRead, BufReader, Cursor, Read, StdinLock},
+    path::Path,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+};
+
+use content_inspector::inspect;
+use tracing::debug;
+
+use crate::{exit_code, ffprobe, full, resolve, vlc};
+
+/// Set with `--no-exec`, to forbid running external programs like `mediainfo`.
+static NO_EXEC: AtomicBool = AtomicBool::new(false);
+
+/// The `mediainfo` to run, set with `--mediainfo`, and the arguments to pass it before the
+/// path, set with `--mediainfo-arg`. Without a path, `mediainfo` is found in the `PATH`.
+static MEDIAINFO: RwLock<Option<(String, Vec<String>)>> = RwLock::new(None);
+
+/// Sets the `mediainfo` binary to run, instead of the one in the `PATH`, and extra arguments to
+/// pass it, for containers and unusual installs.
+pub fn set_mediainfo(path: Option<String>, args: Vec<String>) {
+    let path = path.unwrap_or_else(|| "mediainfo".to_string());
+    *MEDIAINFO.write().expect("The mediainfo setting is never poisoned") = Some((path, args));
+}
+
+/// The `mediainfo` binary to run, and the arguments to pass it before the path.
+fn mediainfo() -> (String, Vec<String>) {
+    MEDIAINFO.read().expect("The mediainfo setting is never poisoned").clone().unwrap_or_else(|| ("mediainfo".to_string(), Vec::new()))
+}
+
+/// Ends the error of reading an input that needs an external program while they're forbidden.
+const NO_EXEC_ERROR: &str = "but running external programs is forbidden by --no-exec";
+
+/// Makes reading videos, which needs `mediainfo`, fail with an error instead of running it,
+/// for `--no-exec`.
+pub fn set_no_exec(no_exec: bool) {
+    NO_EXEC.store(no_exec, Ordering::Relaxed);
+}
+
+/// An error reading an input, with its message, by the exit status it gives.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReadError {
+    /// The input couldn't be read, or `mediainfo` couldn't be run on it.
+    Io(String),
+    /// The input is a video, whose settings are read with `mediainfo`, but `--no-exec` forbids
+    /// running it.
+    ExecForbidden(String),
+}
+
+impl ReadError {
+    /// `IO_ERROR` or `EXEC_FORBIDDEN`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ReadError::Io(_) => exit_code::IO_ERROR,
+            ReadError::ExecForbidden(_) => exit_code::EXEC_FORBIDDEN,
+        }
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(error_message) | ReadError::ExecForbidden(error_message) => f.write_str(error_message),
+        }
+    }
+}
+
+impl From<String> for ReadError {
+    fn from(error_message: String) -> ReadError {
+        ReadError::Io(error_message)
+    }
+}
+
+/// For the callers that report every error the same way.
+impl From<ReadError> for String {
+    fn from(err: ReadError) -> String {
+        err.to_string()
+    }
+}
+
+/// Fails if external programs are forbidden, before running `mediainfo` on a video.
+fn check_exec(path: &str, start: &[u8]) -> Result<(), ReadError> {
+    if NO_EXEC.load(Ordering::Relaxed) {
+        return Err(ReadError::ExecForbidden(format!("{} is {}, and its settings are read with mediainfo, {}", path, describe_binary(start), NO_EXEC_ERROR)));
+    }
+    Ok(())
+}
+
+/// Reads the text to determine the preset from. `input` is a path, `-` or `None` for STDIN, or
+/// an encoder command line. Text files are read as `mediainfo` output (see `as_mediainfo`), and
+/// other files are assumed to be videos and passed through `mediainfo`.
+pub fn read_input(input: Option<&str>) -> Result<String, ReadError> {
+    let text = match input {
+        // an encoder command line given instead of a path
+        Some(input) if !Path::new(input).exists() && resolve::find_command_line(input).is_some() => return Ok(input.to_string()),
+        Some(input) if input != "-" => read_path(input)?,
+        _ => read_stdin()?,
+    };
+    Ok(as_mediainfo(text))
+}
+
+/// Rewrites text in the forms that are read like `mediainfo` output: VLC's codec information
+/// (see `vlc::to_mediainfo`), ffprobe's default output (see `ffprobe::to_mediainfo`), and
+/// `mediainfo --Full` output (see `full::reassemble`). Other text is kept as it is.
+pub fn as_mediainfo(text: String) -> String {
+    vlc::to_mediainfo(&text).or_else(|| ffprobe::to_mediainfo(&text)).or_else(|| full::reassemble(&text)).unwrap_or(text)
+}
+
+fn read_stdin() -> Result<String, String> {
+    let (mut buffer, mut stdin) = stdin_text()?;
+    stdin.read_to_end(&mut buffer).map_err(|err| format!("Could not read from stdin: {}", err))?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// The start of STDIN, and STDIN to read the rest from. An error if STDIN isn't text.
+fn stdin_text() -> Result<(Vec<u8>, StdinLock<'static>), String> {
+    let mut stdin = stdin().lock();
+    let mut buffer = Vec::with_capacity(1024);
+    (&mut stdin).take(1024).read_to_end(&mut buffer).map_err(|err| format!("Could not read from stdin: {}", err))?;
+    // mediainfo needs a file it can seek in, so a video can't be piped in
+    if video_format(&buffer).is_some() || !inspect(&buffer).is_text() {
+        return Err(format!("STDIN is {}, not text. Pass the path of the video instead", describe_binary(&buffer)));
+    }
+    Ok((buffer, stdin))
+}
+
+/// Reads STDIN as it arrives, one input at a time, as described in `Records`, so that
+/// `mediainfo ~/Videos/*.mkv | determine-preset` lists each video.
+pub fn read_stdin_records() -> Result<Records<impl BufRead>, String> {
+    let (start, stdin) = stdin_text()?;
+    Ok(Records::new(BufReader::new(Cursor::new(start).chain(stdin))))
+}
+
+/// The inputs in a stream, with their names. Inputs are separated by:
+///
+/// * the `General` header that starts the `mediainfo` output of each video. A video is returned
+///   as soon as its `Encoding settings` have been read (on the line after them, as
+///   `mediainfo --Full` can split them over several lines), and only its lines up to there are
+///   kept.
+/// * lines starting with `---`, which can name the next input, like `--- movie.mkv`.
+/// * blank lines after a bare settings string (see `is_settings_string`). Blank lines in other
+///   text, like a `mediainfo` excerpt without its `General` header, VLC's codec information or
+///   an x265 log, separate its sections rather than inputs.
+///
+/// Inputs are rewritten with `as_mediainfo`. An input is named by its `---` line, or else its
+/// `Complete name`, or else its position, like `STDIN:2`.
+pub struct Records<R> {
+    reader: R,
+    /// The start of the next input, read while finding the end of the previous one.
+    next_text: String,
+    /// The name of the next input from its `---` line.
+    next_name: Option<String>,
+    /// Whether the current input is `mediainfo` output.
+    mediainfo: bool,
+    /// Whether the current input is VLC's codec information.
+    vlc: bool,
+    /// Set on the lines of a video's `Encoding settings`.
+    settings: bool,
+    /// Set after a video's `Encoding settings`, until the next input starts.
+    skipping: bool,
+    count: usize,
+    finished: bool,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(reader: R) -> Records<R> {
+        Records { reader, next_text: String::new(), next_name: None, mediainfo: false, vlc: false, settings: false, skipping: false, count: 0, finished: false }
+    }
+
+    /// Ends the current input, if it has any text.
+    fn record(&mut self, text: String, next_name: Option<String>) -> Option<(String, String)> {
+        if text.trim().is_empty() {
+            self.next_name = next_name.or(self.next_name.take());
+            return None;
+        }
+        self.count += 1;
+        self.vlc = false;
+        self.settings = false;
+        let text = as_mediainfo(text);
+        let complete_name = || {
+            crate::lines(&text).find_map(|line| line.split_once(" : ").filter(|(field, _)| field.trim() == "Complete name").map(|(_, name)| name.trim().to_string()))
+        };
+        let name = std::mem::replace(&mut self.next_name, next_name).or_else(complete_name).unwrap_or_else(|| format!("STDIN:{}", self.count));
+        Some((name, text))
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<(String, String), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let read_error = |err| format!("Could not read from stdin: {}", err);
+        if self.finished {
+            return None;
+        }
+        let mut text = std::mem::take(&mut self.next_text);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match self.reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(err) => return Some(Err(read_error(err))),
+            }
+            let line = String::from_utf8_lossy(&line);
+            let trimmed = crate::strip_bom(line.trim_end());
+            if let Some(marker_name) = trimmed.strip_prefix("---") {
+                self.skipping = false;
+                self.mediainfo = false;
+                let marker_name = Some(marker_name.trim().to_string()).filter(|name| !name.is_empty());
+                if let Some(record) = self.record(std::mem::take(&mut text), marker_name) {
+                    return Some(Ok(record));
+                }
+                continue;
+            }
+            // the header of the first section of each video
+            if trimmed == "General" {
+                self.skipping = false;
+                self.mediainfo = true;
+                if let Some(record) = self.record(std::mem::take(&mut text), None) {
+                    // the previous input had no encoding settings
+                    self.next_text = line.into_owned();
+                    return Some(Ok(record));
+                }
+                text.push_str(&line);
+                continue;
+            }
+            if self.skipping {
+                continue;
+            }
+            let settings = self.mediainfo && full::is_settings_line(&line);
+            if self.settings && !settings {
+                self.skipping = true;
+                return self.record(text, None).map(Ok);
+            }
+            self.settings = settings;
+            self.vlc |= vlc::is_stream_header(trimmed);
+            if trimmed.is_empty() && !self.mediainfo && !self.vlc && is_settings_string(&text) {
+                if let Some(record) = self.record(std::mem::take(&mut text), None) {
+                    return Some(Ok(record));
+                }
+                continue;
+            }
+            text.push_str(&line);
+        }
+        self.finished = true;
+        self.record(text, None).map(Ok)
+    }
+}
+
+/// Whether the text is only settings, like `ctu=64 bframes=4` or `ctu=64 / rect / no-amp`, on
+/// one or more lines: every word is a `key=value` pair, a switch or a `/` separator, and at
+/// least one is a pair.
+fn is_settings_string(text: &str) -> bool {
+    let is_name = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    let words = || text.split_whitespace().map(crate::strip_bom);
+    words().any(|word| word.contains('='))
+        && words().all(|word| word == "/" || word.split_once('=').map_or(is_name(word), |(name, _)| is_name(name)))
+}
+
+fn read_path(path: &str) -> Result<String, ReadError> {
+    let read_error = |err| format!("Could not read from {}: {}", path, err);
+    let mut file = File::open(path).map_err(|err| format!("Failed to open {} for reading: {}", path, err))?;
+
+    // Read the file start to determine if it is mediainfo output or a video
+    let mut buffer = Vec::with_capacity(1024);
+    (&mut file).take(1024).read_to_end(&mut buffer).map_err(read_error)?;
+    if video_format(&buffer).is_none() && inspect(&buffer).is_text() {
+        if !is_mediainfo(&buffer) {
+            debug!("Reading the start of {} as text", path);
+            file.take(TEXT_PREFIX + 1 - buffer.len() as u64).read_to_end(&mut buffer).map_err(read_error)?;
+            return Ok(text_prefix(buffer));
+        }
+        #[cfg(feature = "mmap")]
+        if file.metadata().is_ok_and(|metadata| metadata.len() >= MMAP_THRESHOLD) {
+            debug!("Reading the settings lines of {}", path);
+            return Ok(read_large_dump(&file, path)?);
+        }
+        // an export can have many videos, whose settings are all needed
+        debug!("Reading {} as mediainfo output", path);
+        file.read_to_end(&mut buffer).map_err(read_error)?;
+        return Ok(String::from_utf8_lossy(&buffer).into_owned());
+    }
+
+    // run mediainfo and use its output
+    check_exec(path, &buffer)?;
+    let (mediainfo, args) = mediainfo();
+    debug!("Running {} on {}, which is {}", mediainfo, path, describe_binary(&buffer));
+    let output = Command::new(&mediainfo)
+        .args(&args)
+        .arg(path)
+        .output()
+        .map_err(|err| mediainfo_error(&mediainfo, path, &buffer, err))?;
+    debug!("mediainfo exited with {} and printed {} bytes", output.status, output.stdout.len());
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The container or stream format of a video, from the magic bytes at the start of the file.
+fn video_format(start: &[u8]) -> Option<&'static str> {
+    // the sync byte `G` starts every packet of a transport stream, which is 192 bytes long with
+    // a 4 byte prefix in M2TS. At least three packets are checked, as text can start with `G`.
+    let transport_stream = |packets: &[u8], packet_size| packets.len() > 2 * packet_size && packets.iter().step_by(packet_size).all(|byte| *byte == 0x47);
+    match start {
+        [0x1a, 0x45, 0xdf, 0xa3, ..] => Some("a Matroska or WebM video"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("an MP4 or QuickTime video"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'A', b'V', b'I', b' ', ..] => Some("an AVI video"),
+        [0, 0, 0, 1, ..] | [0, 0, 1, ..] => Some("a raw video stream"),
+        _ if transport_stream(start, 188) || transport_stream(start.get(4..).unwrap_or_default(), 192) => Some("an MPEG transport stream"),
+        _ => None,
+    }
+}
+
+fn describe_binary(start: &[u8]) -> &'static str {
+    video_format(start).unwrap_or("a binary file")
+}
+
+fn mediainfo_error(mediainfo: &str, path: &str, start: &[u8], err: std::io::Error) -> String {
+    format!("{} is {}, and its settings are read with {}, which could not be run: {}", path, describe_binary(start), mediainfo, err)
+}
+
+/// Text files other than mediainfo output are read up to this size. Settings strings, command
+/// lines, encode logs and ffprobe output have the settings near their start, so a large file,
+/// like a log of every frame, isn't read to the end.
+const TEXT_PREFIX: u64 = 1024 * 1024;
+
+/// The text read from the start of a file, which is at most `TEXT_PREFIX` bytes long and one
+/// more if the file is longer. The line cut off by the limit is dropped.
+fn text_prefix(mut start: Vec<u8>) -> String {
+    if start.len() as u64 > TEXT_PREFIX {
+        start.truncate(TEXT_PREFIX as usize);
+        let end = start.iter().rposition(|byte| *byte == b'\n').map_or(0, |newline| newline + 1);
+        start.truncate(end);
+    }
+    String::from_utf8_lossy(&start).into_owned()
+}
+
+/// Whether text starts like `mediainfo` output, with the header of the `General` section.
+fn is_mediainfo(start: &[u8]) -> bool {
+    start.strip_prefix(b"\xef\xbb\xbf").unwrap_or(start).starts_with(b"General")
+}
+
+/// Text files at least this large that start like mediainfo output are memory-mapped, and only
+/// the lines that are read from them are kept, so exports of many videos don't have to fit in
+/// memory.
+#[cfg(feature = "mmap")]
+const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// The mediainfo fields that are read from the input. `Complete name` separates the videos of
+/// a concatenated export.
+#[cfg(feature = "mmap")]
+const DUMP_FIELDS: &[&str] = &[
+    "Complete name",
+    "Writing application",
+    "Writing library",
+    "Encoded by",
+    "Encoder",
+    "Comment",
+    "Encoding settings",
+    "Width",
+    "Height",
+    "Bit depth",
+    "Chroma subsampling",
+    "Color primaries",
+    "Transfer characteristics",
+    "Matrix coefficients",
+    "Mastering display color primaries",
+    "Maximum Content Light Level",
+    "Maximum Frame-Average Light Level",
+    "HDR format",
+];
+
+#[cfg(feature = "mmap")]
+fn read_large_dump(file: &File, path: &str) -> Result<String, String> {
+    // SAFETY: the map is only read, and dropped before the file is closed. A dump that is
+    // changed while it is mapped can give wrong settings, as it could if it were read.
+    let map = unsafe { memmap2::Mmap::map(file) }.map_err(|err| format!("Could not map {} into memory: {}", path, err))?;
+    Ok(dump_lines(&map))
+}
+
+/// The lines of mediainfo output that have the fields in `DUMP_FIELDS`, and the section
+/// headers, which tell the container's tags from the video's.
+#[cfg(feature = "mmap")]
+fn dump_lines(dump: &[u8]) -> String {
+    let mut text = String::new();
+    for line in dump.split(|byte| *byte == b'\n' || *byte == b'\r') {
+        let line = String::from_utf8_lossy(line);
+        let kept = match line.split_once(" : ") {
+            Some((field, _)) => DUMP_FIELDS.contains(&full::default_name(field.trim())),
+            None => !line.trim().is_empty(),
+        };
+        if kept {
+            text.push_str(&line);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Splits a concatenated mediainfo export into the videos in it, at their `Complete name`
+/// fields (or the `General` headers before them): the name of each video, and its part of the
+/// text. Empty unless there are several videos.
+pub fn split_dump(text: &str) -> Vec<(&str, &str)> {
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    let mut general_start = None;
+    for line in text.split_inclusive('\n') {
+        match line.split_once(" : ") {
+            Some((field, name)) if field.trim() == "Complete name" => starts.push((general_start.unwrap_or(offset), name.trim())),
+            Some(_) => {}
+            None => general_start = (line.trim() == "General").then_some(offset),
+        }
+        offset += line.len();
+    }
+    if starts.len() < 2 {
+        return Vec::new();
+    }
+    starts.iter().enumerate()
+        .map(|(video, (start, name))| {
+            let end = starts.get(video + 1).map_or(text.len(), |(next_start, _)| *next_start);
+            (*name, &text[*start..end])
+        })
+        .collect()
+}
+
+/// Like `read_input`, but doesn't block the executor thread while reading files and running
+/// `mediainfo`, for services that embed the determiner.
+#[cfg(feature = "async")]
+pub async fn read_input_async(input: Option<&str>) -> Result<String, ReadError> {
+    use tokio::io::AsyncReadExt;
+
+    let text = match input {
+        Some(input) if !tokio::fs::try_exists(input).await.unwrap_or(false) && resolve::find_command_line(input).is_some() => return Ok(input.to_string()),
+        Some(input) if input != "-" => read_path_async(input).await?,
+        _ => {
+            let mut buffer = String::new();
+            tokio::io::stdin().read_to_string(&mut buffer).await.map_err(|err| format!("Could not read from stdin: {}", err))?;
+            buffer
+        }
+    };
+    Ok(as_mediainfo(text))
+}
+
+#[cfg(feature = "async")]
+async fn read_path_async(path: &str) -> Result<String, ReadError> {
+    use tokio::io::AsyncReadExt;
+
+    let read_error = |err| format!("Could not read from {}: {}", path, err);
+    let mut file = tokio::fs::File::open(path).await.map_err(|err| format!("Failed to open {} for reading: {}", path, err))?;
+
+    let mut buffer = Vec::with_capacity(1024);
+    (&mut file).take(1024).read_to_end(&mut buffer).await.map_err(read_error)?;
+    if video_format(&buffer).is_none() && inspect(&buffer).is_text() {
+        if !is_mediainfo(&buffer) {
+            debug!("Reading the start of {} as text", path);
+            file.take(TEXT_PREFIX + 1 - buffer.len() as u64).read_to_end(&mut buffer).await.map_err(read_error)?;
+            return Ok(text_prefix(buffer));
+        }
+        debug!("Reading {} as mediainfo output", path);
+        file.read_to_end(&mut buffer).await.map_err(read_error)?;
+        return Ok(String::from_utf8_lossy(&buffer).into_owned());
+    }
+
+    check_exec(path, &buffer)?;
+    let (mediainfo, args) = mediainfo();
+    debug!("Running {} on {}, which is {}", mediainfo, path, describe_binary(&buffer));
+    let output = tokio::process::Command::new(&mediainfo)
+        .args(&args)
+        .arg(path)
+        .output()
+        .await
+        .map_err(|err| mediainfo_error(&mediainfo, path, &buffer, err))?;
+    debug!("mediainfo exited with {} and printed {} bytes", output.status, output.stdout.len());
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The `paths` given as arguments, followed by those listed in the `--files-from` file, one per
+/// line. The list is read from STDIN if `files_from` is `-`. As STDIN can only be read once, `-`
+/// can be at most one of the paths, and not one if the list is read from STDIN.
+pub fn with_files_from(paths: &[String], files_from: Option<&str>) -> Result<Vec<String>, String> {
+    let mut paths = paths.to_vec();
+    if let Some(list_path) = files_from {
+        let list = match list_path {
+            "-" => read_stdin()?,
+            _ => std::fs::read_to_string(list_path).map_err(|err| format!("Failed to read the list of files {}: {}", list_path, err))?,
+        };
+        paths.extend(crate::lines(&list).map(str::trim).filter(|path| !path.is_empty()).map(str::to_string));
+    }
+    let stdin_reads = paths.iter().filter(|path| *path == "-").count() + usize::from(files_from == Some("-"));
+    if stdin_reads > 1 {
+        return Err("STDIN (`-`) can only be read once".to_string());
+    }
+    Ok(paths)
+}
+
+/// Expands the directories among `inputs` into the files they contain, sorted by name, so a
+/// directory of chunks can be given in place of the chunks themselves.
+pub fn expand_directories(inputs: &[String]) -> Result<Vec<String>, String> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        if !Path::new(input).is_dir() {
+            paths.push(input.clone());
+            continue;
+        }
+        let entries = std::fs::read_dir(input).map_err(|err| format!("Could not read the directory {}: {}", input, err))?;
+        let mut files = entries
+            .map(|entry| entry.map(|entry| entry.path()).map_err(|err| format!("Could not read the directory {}: {}", input, err)))
+            .filter(|path| path.as_ref().map_or(true, |path| path.is_file()))
+            .map(|path| path.map(|path| path.to_string_lossy().into_owned()))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.sort();
+        paths.extend(files);
+    }
+    Ok(paths)
+}
+
+/// The file extensions of the videos that `find_videos` looks for in directories.
+const VIDEO_EXTENSIONS: &[&str] = &["mkv", "mp4", "m4v", "mov", "ts", "m2ts", "hevc", "h265", "265", "webm", "avi"];
+
+/// Finds the videos in `paths`, searching directories recursively. Files named directly are
+/// always included; files found in directories only if they have a video extension.
+pub fn find_videos(paths: &[String]) -> Result<Vec<String>, String> {
+    let mut videos = Vec::new();
+    for path in paths {
+        if Path::new(path).is_dir() {
+            find_videos_in(Path::new(path), &mut videos)?;
+        } else {
+            videos.push(path.clone());
+        }
+    }
+    Ok(videos)
+}
+
+fn find_videos_in(dir: &Path, videos: &mut Vec<String>) -> Result<(), String> {
+    let read_error = |err| format!("Could not read the directory {}: {}", dir.display(), err);
+    let mut entries = std::fs::read_dir(dir).map_err(read_error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_error)?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            find_videos_in(&path, videos)?;
+        } else if path.extension().is_some_and(|extension| VIDEO_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())) {
+            videos.push(path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_read_input_async() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let path = std::env::temp_dir().join(format!("determine-preset-test-{}.txt", std::process::id()));
+    std::fs::write(&path, "ctu=64 bframes=4").unwrap();
+    let text = runtime.block_on(read_input_async(path.to_str()));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(text, Ok("ctu=64 bframes=4".to_string()));
+    assert_eq!(runtime.block_on(read_input_async(Some("x265 --preset slow"))), Ok("x265 --preset slow".to_string()));
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_split_dump() {
+    let dump = "General\nComplete name : a.mkv\nFormat : Matroska\n\nVideo\nWidth : 1 920 pixels\nEncoding settings : ctu=64\nGeneral\nComplete name : b.mkv\r\nEncoding settings : ctu=32\n";
+    let text = dump_lines(dump.as_bytes());
+    assert_eq!(text, "General\nComplete name : a.mkv\nVideo\nWidth : 1 920 pixels\nEncoding settings : ctu=64\nGeneral\nComplete name : b.mkv\nEncoding settings : ctu=32\n");
+    let videos = [("a.mkv", "General\nComplete name : a.mkv\nVideo\nWidth : 1 920 pixels\nEncoding settings : ctu=64\n"), ("b.mkv", "General\nComplete name : b.mkv\nEncoding settings : ctu=32\n")];
+    assert_eq!(split_dump(&text), videos);
+    assert_eq!(split_dump("Complete name : a.mkv\nComplete name : b.mkv\n"), [("a.mkv", "Complete name : a.mkv\n"), ("b.mkv", "Complete name : b.mkv\n")]);
+    assert!(split_dump("General\nComplete name : a.mkv\n").is_empty());
+}
+
+#[test]
+fn test_video_format() {
+    assert_eq!(video_format(&[0x1a, 0x45, 0xdf, 0xa3, 0x01]), Some("a Matroska or WebM video"));
+    assert_eq!(video_format(b"\0\0\0\x20ftypisom"), Some("an MP4 or QuickTime video"));
+    let mut transport_stream = [0xff; 1024];
+    for packet in transport_stream.chunks_mut(188) {
+        packet[0] = b'G';
+    }
+    assert_eq!(video_format(&transport_stream), Some("an MPEG transport stream"));
+    let mut mediainfo = format!("General\n{:369}", "").into_bytes();
+    mediainfo[188] = b'G';
+    assert_eq!(video_format(&mediainfo), None);
+
+    let log = format!("x265 [info]: tools: rd=3\n{}", "frame 1\n".repeat(TEXT_PREFIX as usize / 8));
+    let start = text_prefix(log.into_bytes());
+    assert!(start.starts_with("x265 [info]: tools: rd=3\nframe 1\n") && start.ends_with("frame 1\n"));
+    assert!(start.len() as u64 <= TEXT_PREFIX);
+    assert_eq!(text_prefix(b"ctu=64".to_vec()), "ctu=64");
+}
+
+#[test]
+fn test_read_error() {
+    let path = std::env::temp_dir().join(format!("determine-preset-test-{}.mkv", std::process::id()));
+    std::fs::write(&path, [0x1a, 0x45, 0xdf, 0xa3, 0x01]).unwrap();
+    set_no_exec(true);
+    let result = read_input(path.to_str());
+    set_no_exec(false);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(ReadError::ExecForbidden(_))));
+    assert_eq!(result.unwrap_err().exit_code(), exit_code::EXEC_FORBIDDEN);
+    let missing = read_input(path.to_str()).unwrap_err();
+    assert_eq!((missing.exit_code(), String::from(missing).starts_with("Failed to open")), (exit_code::IO_ERROR, true));
+}
+
+#[test]
+fn test_records() {
+    let stream = "General\nComplete name : a.mkv\n\nVideo\nEncoding settings : ctu=64\n\nAudio\nFormat : AAC\nGeneral\r\nComplete name : b.mkv\nGeneral\nComplete name : c.mkv\nEncoding settings : ctu=32\n";
+    let records = Records::new(stream.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+    let names = records.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["a.mkv", "b.mkv", "c.mkv"]);
+    assert_eq!(records[0].1, "General\nComplete name : a.mkv\n\nVideo\nEncoding settings : ctu=64\n");
+    let records = Records::new("ctu=64\nbframes=4\n".as_bytes()).collect::<Vec<_>>();
+    assert_eq!(records, [Ok(("STDIN:1".to_string(), "ctu=64\nbframes=4\n".to_string()))]);
+    let stream = "ctu=64\n\n\nctu=32\n--- c.txt\nctu=16\n---\nGeneral\n\nVideo\nEncoding settings : ctu=8\n";
+    let records = Records::new(stream.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+    let names = records.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["STDIN:1", "STDIN:2", "c.txt", "STDIN:4"]);
+    assert_eq!(records[3].1, "General\n\nVideo\nEncoding settings : ctu=8\n");
+    let stream = "Stream 0\n    Codec: HEVC\n    Type: Video\n\nStream 1\n    Type: Audio\n--- b\nctu=8\n";
+    let records = Records::new(stream.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records[0], ("STDIN:1".to_string(), "General\n\nVideo\nFormat : HEVC\n".to_string()));
+    let stream = "General\nCompleteName : a.mkv\n\nVideo\nEncoded_Library_Settings : ctu=64\nEncoded_Library_Settings : rect\nBitDepth : 10\n\nAudio\nFormat : AAC\n";
+    let records = Records::new(stream.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records, [("a.mkv".to_string(), "General\nComplete name : a.mkv\n\nVideo\nEncoding settings : ctu=64 / rect\n".to_string())]);
+    // a pasted excerpt and a log are one input despite their blank lines
+    let stream = "Video\nFormat : HEVC\nEncoding settings : ctu=32 / min-cu-size=8\n\nAudio\nFormat : AAC\n";
+    assert_eq!(Records::new(stream.as_bytes()).count(), 1);
+    let stream = "x265 [info]: HEVC encoder version 3.5\n\nx265 [info]: frame I: 1\n";
+    assert_eq!(Records::new(stream.as_bytes()).count(), 1);
+    assert!(is_settings_string("ctu=64 / rect / no-amp\nbframes=4\n"));
+    assert!(!is_settings_string("x265 --preset slow --crf 20\n"));
+}
+
+#[test]
+fn test_with_files_from() {
+    let list_path = std::env::temp_dir().join(format!("determine-preset-test-list-{}.txt", std::process::id()));
+    std::fs::write(&list_path, "b.mkv\r\n\n  c.mkv\n-\n").unwrap();
+    let paths = with_files_from(&["a.mkv".to_string()], list_path.to_str());
+    let twice = with_files_from(&["-".to_string()], list_path.to_str());
+    std::fs::remove_file(&list_path).unwrap();
+    assert_eq!(paths, Ok(vec!["a.mkv".to_string(), "b.mkv".to_string(), "c.mkv".to_string(), "-".to_string()]));
+    assert_eq!(twice, Err("STDIN (`-`) can only be read once".to_string()));
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+};
+
+/// A string interned by an `Interner`. Symbols from the same interner are equal only if they
+/// are the same allocation, so comparing and hashing them doesn't look at the text. Symbols
+/// from different interners must not be compared.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+/// Settings whose names and values are interned, for holding the settings of many files at once.
+pub type InternedSettings = HashMap<Symbol, Symbol>;
+
+/// Keeps one copy of each string, so that the settings or scan results of tens of thousands of
+/// files take about as much memory as the distinct strings in them.
+#[derive(Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(string) = self.strings.get(text) {
+            return Symbol(Arc::clone(string));
+        }
+        let string: Arc<str> = Arc::from(text);
+        self.strings.insert(Arc::clone(&string));
+        Symbol(string)
+    }
+
+    pub fn intern_settings<'a>(&mut self, settings: impl IntoIterator<Item = (&'a str, &'a str)>) -> InternedSettings {
+        settings.into_iter().map(|(name, value)| (self.intern(name), self.intern(value))).collect()
+    }
+
+    /// The number of distinct strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+}
+
+/// Copies interned settings back to the form the rest of the crate uses.
+pub fn owned_settings(settings: &InternedSettings) -> HashMap<String, String> {
+    settings.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<u8>().hash(state);
+    }
+}
+
+/// Symbols are ordered by their text, which is consistent with `Eq` for symbols from one interner.
+impl Ord for Symbol {
+    fn cmp(&self, other: &Symbol) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Symbol) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+#[test]
+fn test_interner() {
+    let mut interner = Interner::default();
+    let first = interner.intern_settings([("ctu", "64"), ("bframes", "4")]);
+    let second = interner.intern_settings([("ctu", "64"), ("bframes", "8")]);
+    assert_eq!(interner.len(), 5);
+    assert_eq!(first[&interner.intern("ctu")], second[&interner.intern("ctu")]);
+    assert_ne!(first[&interner.intern("bframes")], second[&interner.intern("bframes")]);
+    assert_eq!(owned_settings(&second)["bframes"], "8");
+}
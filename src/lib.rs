@@ -1,316 +1,362 @@
-use std::{cmp::max, collections::HashMap, io::{stderr, Write}, process::exit};
-
-use atty;
-use colored::Colorize;
-use clap::{ArgAction, Parser};
-
-#[derive(Default, Clone, Debug, clap::ValueEnum)]
-enum ColorMode {
-    #[default]
-    Auto,
-    Always,
-    Never,
+use std::collections::HashMap;
+
+#[cfg(feature = "cli")]
+pub mod bench;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "cli")]
+pub mod config;
+#[cfg(all(feature = "cli", unix))]
+mod daemon;
+#[cfg(feature = "cli")]
+mod encode_log;
+pub mod encoder;
+pub mod encoding;
+pub mod ffprobe;
+#[cfg(feature = "cli")]
+mod filter;
+pub mod full;
+pub mod handbrake;
+pub mod i18n;
+pub mod input;
+#[cfg(feature = "cli")]
+mod intern;
+#[cfg(feature = "cli")]
+pub mod logging;
+pub mod matcher;
+#[cfg(all(feature = "cli", unix))]
+mod metrics;
+pub mod params;
+#[cfg(all(feature = "cli", feature = "parquet"))]
+mod parquet;
+#[cfg(feature = "cli")]
+pub mod policy;
+pub mod presets;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "cli")]
+mod rename;
+pub mod resolve;
+#[cfg(feature = "cli")]
+pub mod schema;
+#[cfg(feature = "cli")]
+mod selftest;
+#[cfg(feature = "cli")]
+mod table;
+#[cfg(feature = "self-update")]
+mod update;
+pub mod vlc;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "cli")]
+mod wizard;
+pub mod x264;
+
+#[cfg(feature = "cli")]
+pub use cli::*;
+#[cfg(feature = "cli")]
+pub use table::TableStyle;
+
+/// A parameter whose value differs between two sources: its name and the two values.
+pub type Difference = (String, String, String);
+
+/// The exit statuses, so scripts can tell the outcomes apart.
+pub mod exit_code {
+    /// Exactly one preset matches, or a subcommand succeeded.
+    pub const SUCCESS: i32 = 0;
+    /// Any other error, including invalid arguments.
+    pub const ERROR: i32 = 1;
+    /// Several presets match.
+    pub const AMBIGUOUS: i32 = 2;
+    /// No preset matches.
+    pub const NO_MATCH: i32 = 3;
+    /// The input has none of the parameters that the presets set, e.g. it isn't an x265 video.
+    pub const INSUFFICIENT_INPUT: i32 = 4;
+    /// The input couldn't be read, or `mediainfo` couldn't be run.
+    pub const IO_ERROR: i32 = 5;
+    /// The input doesn't meet the `--require`, `--at-most` or `--filter` condition, or a video
+    /// violates the policy of `scan --policy`.
+    pub const REJECTED: i32 = 6;
+    /// The video was encoded by a hardware encoder, which doesn't record its settings, or by
+    /// x264, whose settings don't compare with the x265 presets.
+    pub const NOT_DERIVABLE: i32 = 7;
+    /// The input is a video, whose settings are read with `mediainfo`, but `--no-exec` forbids
+    /// running it.
+    pub const EXEC_FORBIDDEN: i32 = 8;
+    /// With `--exit-preset-code`, added to the statuses above, so they can't be mistaken for a
+    /// preset's position.
+    pub const PRESET_CODE_OFFSET: i32 = 100;
 }
 
-#[derive(Parser, Default)]
-/// Read x265 encoding flags (for example from the output of `mediainfo`),
-/// and print which preset the video was encoded with.
-pub struct Cli {
-    /// Path to read the encoding flags from. If omitted or `-`, read from STDIN. STDIN must be `mediainfo` output, not a video file.
-    pub input: Option<String>,
-
-    /// In the case of no match, colors are used to show close matches
-    /// in verbose mode.
-    #[arg(short, long, num_args(0..=1), default_value = "auto", default_missing_value = "auto")]
-    color: ColorMode,
-
-    /// In the case of no match, print detailed output about the close matches. -vv gives
-    /// more detailed output.
-    #[arg(short, long, action = ArgAction::Count)]
-    verbose: u8,
+/// The outcome of matching settings against the presets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Determination {
+    /// Exactly one preset matches.
+    Match(String),
+    /// Several presets match, from the fastest to the slowest.
+    Ambiguous(Vec<String>),
+    NoMatch,
+    /// The settings have none of the parameters that the presets set, so every preset matches.
+    InsufficientInput,
+    /// The video was encoded by a hardware encoder, like NVENC, which doesn't record its
+    /// settings, so there are none to match, or by x264 (see `x264::ENCODER`), whose settings
+    /// can't be matched against the x265 presets.
+    NotDerivable { encoder: String },
 }
 
-#[derive(Default)]
-pub struct Determiner {
-    cli: Cli,
-    presets: Vec<(String, HashMap<String, String>)>,
-}
-
-impl Determiner {
-    pub fn new(cli: Cli) -> Determiner {
-        Determiner {
-            cli,
-            // Preset configurations from: https://x265.readthedocs.io/en/master/presets.html
-            presets: vec![
-                (
-                    "ultrafast".to_string(),
-                    parse_string("ctu=32 min-cu-size=16 bframes=3 b-adapt=0 rc-lookahead=5 lookahead-slices=8 scenecut=0 ref=1 limit-refs=0 me=dia merange=57 subme=0 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=1 recursion-skip=1 fast-intra=1 b-intra=0 sao=0 signhide=0 weightp=0 weightb=0 aq-mode=0 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
-                ),
-                (
-                    "superfast".to_string(),
-                    parse_string("ctu=32 min-cu-size=8 bframes=3 b-adapt=0 rc-lookahead=10 lookahead-slices=8 scenecut=40 ref=1 limit-refs=0 me=hex merange=57 subme=1 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=1 recursion-skip=1 fast-intra=1 b-intra=0 sao=0 signhide=1 weightp=0 weightb=0 aq-mode=0 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
-                ),
-                (
-                    "veryfast".to_string(),
-                    parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=0 rc-lookahead=15 lookahead-slices=8 scenecut=40 ref=2 limit-refs=3 me=hex merange=57 subme=1 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=1 recursion-skip=1 fast-intra=1 b-intra=0 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
-                ),
-                (
-                    "faster".to_string(),
-                    parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=0 rc-lookahead=15 lookahead-slices=8 scenecut=40 ref=2 limit-refs=3 me=hex merange=57 subme=2 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=1 recursion-skip=1 fast-intra=1 b-intra=0 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
-                ),
-                (
-                    "fast".to_string(),
-                    parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=0 rc-lookahead=15 lookahead-slices=8 scenecut=40 ref=3 limit-refs=3 me=hex merange=57 subme=2 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=0 recursion-skip=1 fast-intra=1 b-intra=0 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
-                ),
-                (
-                    "medium".to_string(),
-                    parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=2 rc-lookahead=20 lookahead-slices=8 scenecut=40 ref=3 limit-refs=1 me=hex merange=57 subme=2 rect=0 amp=0 limit-modes=0 max-merge=3 early-skip=1 recursion-skip=1 fast-intra=0 b-intra=1 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=3 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
-                ),
-                (
-                    // Note: these are not stable/unchanging. I saw a "slow" video with lookahead-slices=6. I'm not sure which version was used to encode it.
-                    "slow".to_string(),
-                    parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=2 rc-lookahead=25 lookahead-slices=4 scenecut=40 ref=4 limit-refs=3 me=star merange=57 subme=3 rect=1 amp=0 limit-modes=1 max-merge=3 early-skip=0 recursion-skip=1 fast-intra=0 b-intra=0 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=4 rdoq-level=2 tu-intra=1 tu-inter=1 limit-tu=0"),
-                ),
-                (
-                    "slower".to_string(),
-                    parse_string("ctu=64 min-cu-size=8 bframes=8 b-adapt=2 rc-lookahead=40 lookahead-slices=1 scenecut=40 ref=5 limit-refs=1 me=star merange=57 subme=4 rect=1 amp=1 limit-modes=1 max-merge=4 early-skip=0 recursion-skip=1 fast-intra=0 b-intra=1 sao=1 signhide=1 weightp=1 weightb=1 aq-mode=2 cuTree=1 rdLevel=6 rdoq-level=2 tu-intra=3 tu-inter=3 limit-tu=4"),
-                ),
-                (
-                    "veryslow".to_string(),
-                    parse_string("ctu=64 min-cu-size=8 bframes=8 b-adapt=2 rc-lookahead=40 lookahead-slices=1 scenecut=40 ref=5 limit-refs=0 me=star merange=57 subme=4 rect=1 amp=1 limit-modes=0 max-merge=5 early-skip=0 recursion-skip=1 fast-intra=0 b-intra=1 sao=1 signhide=1 weightp=1 weightb=1 aq-mode=2 cuTree=1 rdLevel=6 rdoq-level=2 tu-intra=3 tu-inter=3 limit-tu=0"),
-                ),
-                (
-                    "placebo".to_string(),
-                    parse_string("ctu=64 min-cu-size=8 bframes=8 b-adapt=2 rc-lookahead=60 lookahead-slices=1 scenecut=40 ref=5 limit-refs=0 me=star merange=92 subme=5 rect=1 amp=1 limit-modes=0 max-merge=5 early-skip=0 recursion-skip=0 fast-intra=0 b-intra=1 sao=1 signhide=1 weightp=1 weightb=1 aq-mode=2 cuTree=1 rdLevel=6 rdoq-level=2 tu-intra=4 tu-inter=4 limit-tu=0"),
-                ),
-            ],
+impl Determination {
+    /// The status in `--format shell` and `--format json`.
+    pub fn status(&self) -> &'static str {
+        match self {
+            Determination::Match(_) => "match",
+            Determination::Ambiguous(_) => "ambiguous",
+            Determination::NoMatch => "no-match",
+            Determination::InsufficientInput => "insufficient-input",
+            Determination::NotDerivable { .. } => "not-derivable",
         }
     }
 
-    pub fn print_preset_from_str(&self, input: &str) {
-        match self.determine_preset_from_str(input) {
-            Ok(preset_name) => println!("{}", preset_name),
-            Err(error_message) => {
-                writeln!(stderr(), "Error: {}", error_message).expect("Failed to write to stderr");
-                exit(1);
-            }
+    /// The matching preset, if there is a single one.
+    pub fn preset(&self) -> Option<&str> {
+        match self {
+            Determination::Match(preset_name) => Some(preset_name),
+            _ => None,
         }
     }
 
-    fn determine_preset_from_str(&self, input: &str) -> Result<String, String> {
-        // Parse the input into a HashMap of key-value pairs.
-        let mut encoder_settings = parse_string(&input);
-        let _ = encoder_settings.remove("me"); // the video has this in numeric format but the reference data is strings.
-
-        // for lookahead-slices, 0 is the same as 1, but the reference table uses 1, not 0.
-        match encoder_settings.get_mut("lookahead-slices") {
-            Some(k) if *k == "0" => *k = "1".to_string(),
-            _ => (),
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Determination::Match(_) => exit_code::SUCCESS,
+            Determination::Ambiguous(_) => exit_code::AMBIGUOUS,
+            Determination::NoMatch => exit_code::NO_MATCH,
+            Determination::InsufficientInput => exit_code::INSUFFICIENT_INPUT,
+            Determination::NotDerivable { .. } => exit_code::NOT_DERIVABLE,
         }
+    }
 
-        // Determine the preset by matching the settings.
-        self.determine_preset(&encoder_settings)
+    /// The determination for the input the settings were read from: without settings, a video
+    /// from a hardware encoder is `NotDerivable` rather than `InsufficientInput`, and so is an
+    /// x264 encode, whatever its settings match.
+    pub fn for_input(self, input: &str) -> Determination {
+        match self {
+            _ if encoder_name(input) == x264::ENCODER => Determination::NotDerivable { encoder: x264::ENCODER.to_string() },
+            Determination::InsufficientInput => match hardware_encoder(input) {
+                Some(encoder) => Determination::NotDerivable { encoder: encoder.to_string() },
+                None => Determination::InsufficientInput,
+            },
+            determination => determination,
+        }
     }
+}
 
-    /// Gives output for the candidate matches to be compared visually:
-    ///
-    ///                  | input | slow | veryslow | placebo
-    /// ----------------------------------------------------
-    /// merange          | 57    | 57   | 57       | 92
-    /// aq-mode          | 4     | 2    | 2        | 2
-    /// subme            | 3     | 3    | 4        | 5
-    /// b-adapt          | 2     | 2    | 2        | 2
-    ///
-    pub fn partially_matching_presets(&self, settings: &HashMap<String, String>) -> String {
-        let use_color = match self.cli.color {
-            ColorMode::Auto => atty::is(atty::Stream::Stdout),
-            ColorMode::Always => true,
-            ColorMode::Never => false,
-        };
+/// The hardware encoders, by a part of the names of their ffmpeg encoders (like `hevc_nvenc`),
+/// which ffmpeg writes into the encoder tags.
+const HARDWARE_ENCODERS: &[(&str, &str)] = &[
+    ("nvenc", "NVENC"),
+    ("_qsv", "Quick Sync Video"),
+    ("videotoolbox", "VideoToolbox"),
+    ("_vaapi", "VA-API"),
+    ("_amf", "AMF"),
+    ("_mediacodec", "MediaCodec"),
+];
+
+/// The hardware encoder named in the encoder tags of `mediainfo` output, if any.
+pub fn hardware_encoder(input: &str) -> Option<&'static str> {
+    lines(input)
+        .filter_map(|line| line.split_once(" : "))
+        .filter(|(field, _)| matches!(field.trim(), "Writing library" | "Writing application" | "Encoder" | "Encoded by"))
+        .find_map(|(_, value)| {
+            let value = value.to_lowercase();
+            HARDWARE_ENCODERS.iter().find(|(tag, _)| value.contains(tag)).map(|(_, encoder)| *encoder)
+        })
+}
 
-        fn width_of_values(iter: impl Iterator<Item = impl AsRef<str>>) -> usize {
-            let mut max_len = None;
-            for val in iter {
-                if max_len.map_or(true, |max_len| val.as_ref().len() > max_len) {
-                    max_len.replace(val.as_ref().len());
+/// The presets named by the container's tags in `mediainfo` output, as `(tag, preset)`: the
+/// `encoder` and `comment` tags that ffmpeg writes (which mediainfo shows as `Writing
+/// application` and `Comment`), and custom tags with `preset` in their names, which some
+/// pipelines add. Tags can say anything, so these only hint at the preset.
+pub fn tag_hints(input: &str, preset_names: &[&str]) -> Vec<(String, String)> {
+    let mut section = "";
+    let mut hints = Vec::new();
+    for line in lines(input) {
+        let Some((field, value)) = line.split_once(" : ") else {
+            if !line.trim().is_empty() {
+                section = line.trim();
+            }
+            continue;
+        };
+        let field = field.trim();
+        let is_hint_tag = matches!(field, "Writing application" | "Encoder" | "Comment" | "Description") || field.to_lowercase().contains("preset");
+        if section != "General" || !is_hint_tag {
+            continue;
+        }
+        // whole words only, so that `slow` isn't found in `slower`
+        for word in value.split(|c: char| !c.is_ascii_alphanumeric()) {
+            if let Some(preset_name) = preset_names.iter().find(|preset_name| preset_name.eq_ignore_ascii_case(word)) {
+                if !hints.iter().any(|(tag, hint): &(String, String)| tag == field && hint == preset_name) {
+                    hints.push((field.to_string(), preset_name.to_string()));
                 }
             }
-            max_len.expect("One or more element required")
         }
+    }
+    hints
+}
 
-        let preset_enc_params = self.presets.iter().map(|(_, params)| params).next().expect("There must be a preset").iter().map(|(param_name, _)| param_name).collect::<Vec<_>>();
-        let input_keys = settings.iter().map(|(param_name, _)| param_name).collect::<Vec<_>>();
-        // Filter these to not contain keys that don't match:
-        let preset_enc_params = if self.cli.verbose < 2 {
-            preset_enc_params.into_iter().filter(|param_name| input_keys.contains(param_name)).collect::<Vec<_>>()
-        } else {
-            preset_enc_params
-        };
-        let input_keys = if self.cli.verbose < 2 {
-            input_keys.into_iter().filter(|param_name| preset_enc_params.contains(param_name)).collect::<Vec<_>>()
-        } else {
-            input_keys
-        };
-
-        // manipulate the data into more convenient formats:
-        let preset_names = self.closest_matches(settings).into_iter().map(|(preset_name, _)| preset_name).take(3).collect::<Vec<_>>();
-        // filter by which presets to show
-        let presets = self.presets.iter().filter(|(preset_name, _)| preset_names.contains(preset_name)).collect::<Vec<_>>();
-        // filter the encoding params within
-        let presets = presets.iter().map(|(preset_name, encoder_values)| {
-            let encoder_values = encoder_values.iter().filter(|(param_name, _)| {
-                preset_enc_params.contains(param_name)
-            }).collect::<HashMap<_, _>>();
-            (preset_name, encoder_values)
-        }).collect::<HashMap<_, _>>();
-        let settings = settings.iter().filter(|(param_name, _)| input_keys.contains(param_name)).collect::<HashMap<_, _>>();
-
-        // Find the widths for padding:
-        let width_of_parameters = width_of_values(settings.keys());
-        let width_of_input_values = max("input".len(), width_of_values(settings.values()));
+/// Parses encoder settings from `mediainfo` output or a bare `key=value` settings string, and
+/// normalizes them to the preset table's names and values. In mediainfo's `Encoding settings`
+/// line, switches like `rect` and `no-amp` are read as `1` and `0`.
+fn parse_settings(input: &str) -> HashMap<String, String> {
+    parse_settings_borrowed(input).into_iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
 
-        // width of the values, not the keys:
-        let width_per_preset= presets.iter().map(|(preset_name, values)| {
-            let width = max(preset_name.len(), values.values().map(|v| v.len()).max().expect("Preset must have values"));
-            (preset_name, width)
-        }).collect::<HashMap<_, _>>();
+/// Like `parse_settings`, with the names and values borrowed from the input instead of copied.
+fn parse_settings_borrowed(input: &str) -> HashMap<&str, &str> {
+    settings_pairs(input).collect()
+}
 
-        fn add_finished_row(row: &mut String, table: &mut String) {
-            row.push('\n');
-            table.push_str(&row);
-            row.clear();
+/// The normalized names of the settings that `parse_settings` reads, in the order the input
+/// has them, for showing them in that order.
+pub fn settings_order(input: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    for (name, _) in settings_pairs(input) {
+        if !names.contains(&name) {
+            names.push(name);
         }
+    }
+    names
+}
 
-        let mut table = String::with_capacity(10_000);
-        let mut row = String::with_capacity(200);
-        row.push_str(&" ".repeat(width_of_parameters));
-        row.push_str(" | ");
-        row.push_str("input");
-        let padding = width_of_input_values - "input".len();
-        row.push_str(&" ".repeat(padding));
-
-        for preset_name in presets.keys() {
-            row.push_str(" | ");
-            row.push_str(preset_name);
-            let padding = width_per_preset[preset_name] - preset_name.len();
-            row.push_str(&" ".repeat(padding));
+/// The normalized settings of the input, in its order, as described in `parse_settings`. A
+/// parameter can be repeated, in which case the last value is the one that counts.
+fn settings_pairs(input: &str) -> impl Iterator<Item = (&str, &str)> {
+    let settings_string = encoding_settings(input);
+    let pairs = settings_string.is_none().then(|| parse_pairs(input)).into_iter().flatten();
+    let tokens = settings_string.into_iter().flat_map(str::split_whitespace).flat_map(|token| match token.split_once('=') {
+        Some(_) => x264::pairs(token),
+        None if token.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') && token.contains(|c: char| c.is_ascii_lowercase()) => {
+            vec![match token.strip_prefix("no-") {
+                Some(name) => (name, "0"),
+                None => (token, "1"),
+            }]
         }
-        add_finished_row(&mut row, &mut table);
-        row.push_str(&"-".repeat(table.len() - 1));
-        add_finished_row(&mut row, &mut table);
-
-
-        let default = &"-".to_string();
-        for encoder_param in settings.keys() {
-            // print the parameter first
-            let value = encoder_param;
-            row.push_str(value);
-            let padding = width_of_parameters - value.len();
-            row.push_str(&" ".repeat(padding));
-
-            // print the found encoded parameter value next
-            row.push_str(" | ");
-            let value = settings.get(encoder_param).unwrap_or(&default);
-            let padding = width_of_input_values - value.len(); // calculate before adding color sequences
-            let value = if use_color {
-                &value.bold().to_string()
-            } else {
-                value
-            };
-            row.push_str(value);
-            row.push_str(&" ".repeat(padding));
-
-            for (preset_name, preset_values) in presets.iter() {
-                row.push_str(" | ");
-                let value = preset_values.get(encoder_param).unwrap_or(&default);
-                let padding = width_per_preset[preset_name] - value.len(); // calculate before adding color sequences
-                let is_match = settings.get(encoder_param) == Some(value);
-                let value = if is_match && use_color {
-                    value.green().to_string()
-                } else {
-                    value.to_string()
-                };
-
-                row.push_str(value.as_str());
-                row.push_str(&" ".repeat(padding));
-            }
+        None => Vec::new(), // the `/` separators
+    });
+    pairs.chain(tokens).map(|(name, value)| params::normalize_borrowed(name, value))
+}
 
-            add_finished_row(&mut row, &mut table);
-        }
+/// The value of mediainfo's `Encoding settings` field.
+fn encoding_settings(input: &str) -> Option<&str> {
+    lines(input).find_map(|line| {
+        let (field, value) = line.split_once(" : ")?;
+        (field.trim() == "Encoding settings").then_some(value.trim())
+    })
+}
 
-        table
+/// The settings as the input has them, before they are normalized: mediainfo's `Encoding
+/// settings`, an encoder command line, or a bare `key=value` settings string. `None` if the
+/// input has none of them, like `mediainfo` output without encoding settings.
+pub fn raw_settings(input: &str) -> Option<String> {
+    if let Some(settings_string) = encoding_settings(input) {
+        return Some(settings_string.to_string());
     }
-
-    pub fn closest_matches(&self, settings: &HashMap<String, String>) -> Vec<(String, usize)> {
-        let mut matches = self.presets.iter().map(|(preset, preset_settings)| {
-            let match_count = settings
-                .iter()
-                .filter(|(key, value)| {
-                    preset_settings.get(*key) == Some(value)
-                })
-                .count();
-            (preset.to_owned(), match_count)
-        }).collect::<Vec<_>>();
-        matches.sort_by_key(|(_, match_count)| *match_count);
-        matches.reverse();
-        matches
+    if let Some(command_line) = resolve::find_command_line(input) {
+        return Some(command_line);
+    }
+    if let Some(options) = x264::options(input) {
+        return Some(options.to_string());
     }
+    let mediainfo = lines(input).any(|line| line.contains(" : "));
+    (!mediainfo && parse_pairs(input).next().is_some()).then(|| strip_bom(input).trim().to_string())
+}
 
-    /// Determines which x265 preset matches the given encoder parameters.
-    pub fn determine_preset(&self, settings: &HashMap<String, String>) -> Result<String, String> {
-        // Collect all matching presets.
-        let matching_presets = self.presets
-            .iter()
-            .filter(|(_, preset_settings)| self.preset_matches(settings, preset_settings))
-            .map(|(name, _)| name.to_owned())
-            .collect::<Vec<_>>();
+/// The name of the encoder that produced the input: the first word of mediainfo's
+/// `Writing library`, or the program of a command line. Empty if the input doesn't say.
+pub fn encoder_name(input: &str) -> String {
+    let writing_library = lines(input).find_map(|line| {
+        let (field, value) = line.split_once(" : ")?;
+        (field.trim() == "Writing library").then(|| value.split_whitespace().next().unwrap_or("").to_string())
+    });
+    writing_library
+        .or_else(|| {
+            let command_line = resolve::find_command_line(input)?;
+            // the ffmpeg command lines that are read are the ones that encode with libx265
+            Some(match resolve::program_name(command_line.split_whitespace().next()?) {
+                "ffmpeg" => "x265",
+                program => program,
+            }.to_string())
+        })
+        .or_else(|| x264::is_x264(input).then(|| x264::ENCODER.to_string()))
+        .unwrap_or_default()
+}
 
-        // Handle the results of the matching.
-        match matching_presets.len() {
-            0 if self.cli.verbose > 0 => Err(format!("No matching presets found. Partial matches:\n\n{}", self.partially_matching_presets(settings))),
-            0 => Err(format!("No matching presets found. Closest matches:\n:{:?}", self.closest_matches(settings))),
-            1 => Ok(matching_presets[0].to_string()),
-            _ => Err(format!(
-                "Multiple matching presets found: {:?}",
-                matching_presets
-            )),
+/// The properties of the video in `mediainfo` output that filters can use: `bit_depth`, `width`
+/// and `height`, as numbers.
+pub fn media_properties(input: &str) -> HashMap<String, String> {
+    let mut properties = HashMap::new();
+    for line in lines(input) {
+        let Some((field, value)) = line.split_once(" : ") else { continue };
+        let name = match field.trim() {
+            "Bit depth" => "bit_depth",
+            "Width" => "width",
+            "Height" => "height",
+            _ => continue,
+        };
+        // e.g. `1 920 pixels`
+        let number = value.trim().chars().take_while(|c| c.is_ascii_digit() || *c == ' ').filter(|c| *c != ' ').collect::<String>();
+        if !number.is_empty() {
+            properties.entry(name.to_string()).or_insert(number);
         }
     }
-
-    /// Checks if the given `input_settings` match a preset's settings.
-    ///
-    /// The preset matches if all key-value pairs in `input_settings` are present in `preset_settings`.
-    pub fn preset_matches(&self,
-        input_settings: &HashMap<String, String>,
-        preset_settings: &HashMap<String, String>,
-    ) -> bool {
-        input_settings
-            .iter()
-            .all(|(key, value)| {
-                let preset_value = preset_settings.get(key);
-                preset_value == None || preset_value == Some(value)
-            })
-    }
+    properties
 }
 
 fn parse_string(input: &str) -> HashMap<String, String> {
-    input
-        .split_whitespace()
-        .filter_map(|pair| {
-            let mut parts = pair.split('=');
-            Some((parts.next()?.to_string(), parts.next()?.to_string()))
-        })
-        .collect()
+    parse_pairs(input).map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+/// The `key=value` pairs of a string, borrowed from it. They can be joined with colons, and
+/// of x264's version string, only the settings after `options:` are read.
+fn parse_pairs(input: &str) -> impl Iterator<Item = (&str, &str)> {
+    let input = strip_bom(input);
+    x264::options(input).unwrap_or(input).split_whitespace().flat_map(x264::pairs)
+}
+
+/// The text without the byte order mark that Windows tools write at the start of UTF-8 files.
+pub(crate) fn strip_bom(text: &str) -> &str {
+    text.strip_prefix('\u{feff}').unwrap_or(text)
+}
+
+/// The lines of a text, which can end with `\n`, `\r\n` (as text copied from Windows tools
+/// does) or `\r`, without the line endings and the byte order mark.
+pub(crate) fn lines(text: &str) -> impl Iterator<Item = &str> {
+    strip_bom(text).split_terminator('\n').flat_map(|line| line.strip_suffix('\r').unwrap_or(line).split('\r'))
 }
 
 #[test]
-fn test_encoding_params() {
-    let input = "Encoding settings                        : cpuid=1111039 / frame-threads=4 / wpp / no-pmode / no-pme / no-psnr / no-ssim / log-level=2 / input-csp=1 / input-res=1860x1080 / interlace=0 / total-frames=0 / level-idc=0 / high-tier=1 / uhd-bd=0 / ref=5 / no-allow-non-conformance / no-repeat-headers / annexb / no-aud / no-eob / no-eos / no-hrd / info / hash=0 / temporal-layers=0 / open-gop / min-keyint=25 / keyint=250 / gop-lookahead=0 / bframes=8 / b-adapt=2 / b-pyramid / bframe-bias=0 / rc-lookahead=40 / lookahead-slices=0 / scenecut=40 / no-hist-scenecut / radl=0 / no-splice / no-intra-refresh / ctu=64 / min-cu-size=8 / rect / amp / max-tu-size=32 / tu-inter-depth=3 / tu-intra-depth=3 / limit-tu=0 / rdoq-level=2 / dynamic-rd=0.00 / no-ssim-rd / signhide / no-tskip / nr-intra=0 / nr-inter=0 / no-constrained-intra / strong-intra-smoothing / max-merge=5 / limit-refs=0 / no-limit-modes / me=3 / subme=4 / merange=57 / temporal-mvp / no-frame-dup / no-hme / weightp / weightb / no-analyze-src-pics / deblock=0:0 / sao / no-sao-non-deblock / rd=6 / selective-sao=4 / no-early-skip / rskip / no-fast-intra / no-tskip-fast / no-cu-lossless / b-intra / no-splitrd-skip / rdpenalty=0 / psy-rd=2.00 / psy-rdoq=1.00 / no-rd-refine / no-lossless / cbqpoffs=0 / crqpoffs=0 / rc=crf / crf=23.0 / qcomp=0.60 / qpstep=4 / stats-write=0 / stats-read=0 / ipratio=1.40 / pbratio=1.30 / aq-mode=2 / aq-strength=1.00 / cutree / zone-count=0 / no-strict-cbr / qg-size=32 / no-rc-grain / qpmax=69 / qpmin=0 / no-const-vbv / sar=0 / overscan=0 / videoformat=5 / range=0 / colorprim=1 / transfer=1 / colormatrix=1 / chromaloc=1 / chromaloc-top=0 / chromaloc-bottom=0 / display-window=0 / cll=0,0 / min-luma=0 / max-luma=1023 / log2-max-poc-lsb=8 / vui-timing-info / vui-hrd-info / slices=1 / no-opt-qp-pps / no-opt-ref-list-length-pps / no-multi-pass-opt-rps / scenecut-bias=0.05 / no-opt-cu-delta-qp / no-aq-motion / no-hdr10 / no-hdr10-opt / no-dhdr10-opt / no-idr-recovery-sei / analysis-reuse-level=0 / analysis-save-reuse-level=0 / analysis-load-reuse-level=0 / scale-factor=0 / refine-intra=0 / refine-inter=0 / refine-mv=1 / refine-ctu-distortion=0 / no-limit-sao / ctu-info=0 / no-lowpass-dct / refine-analysis-type=0 / copy-pic=1 / max-ausize-factor=1.0 / no-dynamic-refine / no-single-sei / no-hevc-aq / no-svt / no-field / qp-adaptation-range=1.00 / scenecut-aware-qp=0conformance-window-offsets / right=0 / bottom=0 / decoder-max-rate=0 / no-vbv-live-multi-pass / no-mcstf / no-sbrc";
-    assert_eq!(Determiner::default().determine_preset_from_str(input), Ok("veryslow".to_string()));
-    let input = "ctu=32 min-cu-size=8";
-    assert_eq!(Determiner::default().determine_preset_from_str(input), Ok("superfast".to_string()));
-    let input = "ctu=32 min-cu-size=8 bframes=8";
-    assert_eq!(Determiner::default().determine_preset_from_str(input), Err("No matching presets found. Closest matches:\n:[(\"placebo\", 2), (\"veryslow\", 2), (\"slower\", 2), (\"superfast\", 2), (\"slow\", 1), (\"medium\", 1), (\"fast\", 1), (\"faster\", 1), (\"veryfast\", 1), (\"ultrafast\", 1)]".to_string()));
-    let input = "ctu=32";
-    assert_eq!(Determiner::default().determine_preset_from_str(input), Err("Multiple matching presets found: [\"ultrafast\", \"superfast\"]".to_string()));
-}
\ No newline at end of file
+fn test_parse_settings() {
+    let settings = parse_settings("Writing library : x265 3.5\nEncoding settings                        : rect / no-amp / rd=4 / me=3 / tu-intra-depth=2 / lookahead-slices=0\n");
+    let expected = [("rect", "1"), ("amp", "0"), ("rdLevel", "4"), ("me", "star"), ("tu-intra", "2"), ("lookahead-slices", "1")];
+    assert_eq!(settings, expected.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect());
+    // without an `Encoding settings` line, only key=value pairs are read
+    assert_eq!(parse_settings("some words ctu=64"), HashMap::from([("ctu".to_string(), "64".to_string())]));
+    assert_eq!(parse_settings("\u{feff}ctu=64\r\n"), HashMap::from([("ctu".to_string(), "64".to_string())]));
+    let windows_settings = parse_settings("\u{feff}General\r\nWriting library : x265 3.5\r\nEncoding settings : rect / no-amp / rd=4 / me=3 / tu-intra-depth=2 / lookahead-slices=0\r\n");
+    assert_eq!(windows_settings, settings);
+    assert_eq!(settings_order("Encoding settings : rect / rd=4 / no-amp / rd=3"), ["rect", "rdLevel", "amp"]);
+    assert_eq!(raw_settings("General\nVideo\nEncoding settings : rect / rd=4\n"), Some("rect / rd=4".to_string()));
+    assert_eq!(raw_settings("ctu=64 rd=4\n"), Some("ctu=64 rd=4".to_string()));
+    assert_eq!(raw_settings("General\nComplete name : a.mkv\n"), None);
+    assert_eq!(lines("a\r\nb\rc\n\nd").collect::<Vec<_>>(), ["a", "b", "c", "", "d"]);
+    assert_eq!(encoder_name("Writing library : x265 3.5+1-f0c1022b6:[Linux][GCC 9.3.0][64 bit] 10bit\n"), "x265");
+    let nvenc = "General\nWriting application : Lavf60.16.100\nVideo\nFormat : HEVC\nWriting library : Lavc60.31.102 hevc_nvenc\n";
+    assert_eq!(Determination::InsufficientInput.for_input(nvenc), Determination::NotDerivable { encoder: "NVENC".to_string() });
+    assert_eq!(Determination::NoMatch.for_input(nvenc), Determination::NoMatch);
+    assert_eq!(hardware_encoder("Writing library : x265 3.5\n"), None);
+    let tagged = "General\nWriting application : Lavf60.16.100\nComment : x265 slow, crf 20\nPRESET : Slow\nVideo\nComment : fast\n";
+    let hints = tag_hints(tagged, &["fast", "slow", "slower"]);
+    assert_eq!(hints, [("Comment".to_string(), "slow".to_string()), ("PRESET".to_string(), "slow".to_string())]);
+    let properties = media_properties("Video\nWidth                                    : 1 920 pixels\nBit depth                                : 10 bits\n");
+    assert_eq!((properties["width"].as_str(), properties["bit_depth"].as_str()), ("1920", "10"));
+}
@@ -0,0 +1,76 @@
+use std::fmt;
+
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{
+    filter::LevelFilter,
+    fmt::{format::Writer, FmtContext, FormatEvent, FormatFields, FormattedFields},
+    registry::LookupSpan,
+};
+
+/// How much is printed on STDERR.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogLevel {
+    /// Nothing, like `--quiet`.
+    Off,
+    Error,
+    Warn,
+    /// Errors, warnings and notes, like the preset that `--delta` applies to.
+    #[default]
+    Info,
+    /// Also what determine-preset does, like running `mediainfo` and reading files.
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> LevelFilter {
+        match level {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Prints the events up to `level` on STDERR.
+pub fn init(level: LogLevel) {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(level)
+        .event_format(MessageFormat)
+        .init();
+}
+
+/// Prints errors and warnings as `Error: ...` and `Warning: ...`, and notes as they are, like
+/// the messages users see. Debug events are prefixed with their level and the spans they're in,
+/// like `debug: file{path=a.mkv}: Running mediainfo`.
+struct MessageFormat;
+
+impl<S, N> FormatEvent<S, N> for MessageFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "Error: ")?,
+            Level::WARN => write!(writer, "Warning: ")?,
+            Level::INFO => (),
+            level => {
+                write!(writer, "{}: ", level.as_str().to_lowercase())?;
+                for span in ctx.event_scope().into_iter().flat_map(|scope| scope.from_root()) {
+                    write!(writer, "{}", span.name())?;
+                    match span.extensions().get::<FormattedFields<N>>() {
+                        Some(fields) if !fields.is_empty() => write!(writer, "{{{}}}: ", fields)?,
+                        _ => write!(writer, ": ")?,
+                    }
+                }
+            }
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
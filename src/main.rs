@@ -1,57 +1,63 @@
-use std::{fs::File, io::{Read, Write, stderr, stdin}, process::exit};
-
-#[cfg(unix)]
-use std::os::unix::prelude::MetadataExt;
-#[cfg(windows)]
-use std::{fs::Metadata, os::windows::fs::MetadataExt};
-
-use clap::Parser;
-use content_inspector::inspect;
-use determine_preset::{Cli, Determiner};
-
-fn main() -> std::io::Result<()> {
-    let cli = Cli::parse();
-
-    let mut buffer = String::new();
-
-    match cli.input {
-        Some(ref input) if input != "-" => {
-            let mut file = match File::open(input) {
-                    Ok(file) => file,
-                    Err(err) => {
-                        writeln!(stderr(), "Failed to open file for reading: {}\n", err).expect("Could not write to stderr");
-                        exit(1)
-                    }
-                };
-            // Read the file start to determine if it is mediainfo output or a video
-            let md = file.metadata()?;
-            let is_text = if md.size() < 1024 {
-                file.read_to_string(&mut buffer).expect("Could not read from input file");
-                inspect(buffer.as_bytes()).is_text()
-            } else {
-                let mut header = [0; 1024];
-                file.read_exact(&mut header)?;
-                inspect(&header).is_text()
-            };
+use std::process::exit;
+
+use clap::{CommandFactory, FromArgMatches};
+#[cfg(feature = "count-allocations")]
+use determine_preset::bench::CountingAllocator;
+use determine_preset::{config, exit_code, fail, i18n::Lang, input, logging, schema, set_json_errors, Cli, Command, Determiner};
+
+// counts the allocations while `determine-preset bench` measures them
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn main() {
+    // clap exits with 2 for invalid arguments, which means an ambiguous match here
+    let exit_with = |err: clap::Error| -> ! {
+        // like `Cli::parse`, a closed STDOUT (`--help | head -1`) isn't an error
+        let _ = err.print();
+        exit(if err.use_stderr() { exit_code::ERROR } else { exit_code::SUCCESS })
+    };
+    let matches = Cli::command().try_get_matches().unwrap_or_else(|err| exit_with(err));
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| exit_with(err));
+    logging::init(cli.log_level());
+    if cli.schema {
+        println!("{}", serde_json::to_string_pretty(&schema::json_schema()).expect("JSON serialization can't fail"));
+        return;
+    }
+    let command = match cli.command.take() {
+        Some(Command::Determine(determine)) => {
+            cli.determine = determine;
+            None
+        }
+        command => command,
+    };
+    if let Err(error_message) = config::load().and_then(|config| config::apply(config, &mut cli, &matches)) {
+        fail(&error_message, cli.determine.exit_status(exit_code::ERROR));
+    }
+    cli.lang.get_or_insert_with(Lang::from_env);
+    set_json_errors(command.is_none() && cli.json_output());
+    input::set_no_exec(cli.no_exec);
+    input::set_mediainfo(cli.mediainfo.clone(), cli.mediainfo_arg.clone());
+
+    if let Some(command) = command {
+        Determiner::new(cli).run_command(&command);
+        return;
+    }
 
-            if is_text {
-                file.read_to_string(&mut buffer).expect("Could not read from input file");
-            } else {
-                // run mediainfo and populate the buffer with the output
-                let output = std::process::Command::new("mediainfo")
-                    .arg(input)
-                    .output()
-                    .expect("Failed to execute mediainfo");
-                buffer = String::from_utf8_lossy(&output.stdout).to_string();
+    if matches!(cli.determine.input.as_deref(), None | Some("-")) {
+        if atty::is(atty::Stream::Stdin) {
+            // without a path, a user at a terminal most likely doesn't know what to pass
+            if cli.determine.input.is_none() {
+                Cli::command().print_help().expect("Failed to write the help");
+                exit(exit_code::ERROR);
             }
+            eprintln!("Reading mediainfo output or x265 settings from the terminal; press Ctrl-D when done");
         }
-        _ => {
-            stdin().read_to_string(&mut buffer).expect("Could not read from stdin");
-        }
-
+        let records = input::read_stdin_records().unwrap_or_else(|error_message| fail(&error_message, cli.determine.exit_status(exit_code::IO_ERROR)));
+        Determiner::new(cli).print_preset_from_records(records);
+        return;
     }
+    let buffer = input::read_input(cli.determine.input.as_deref()).unwrap_or_else(|err| fail(&err.to_string(), cli.determine.exit_status(err.exit_code())));
 
     Determiner::new(cli).print_preset_from_str(&buffer);
-
-    Ok(())
 }
@@ -0,0 +1,510 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+use tracing::debug;
+
+use crate::{i18n::Lang, params, parse_settings, parse_settings_borrowed, presets::{self, Preset}, resolve, Determination, Difference};
+
+/// Parses the input into a HashMap of key-value pairs, in the form the preset tables use.
+/// If the input is an x265 or ffmpeg command line, the settings it would encode with are
+/// worked out instead.
+pub fn normalized_settings(input: &str, presets: &[Preset]) -> Result<HashMap<String, String>, String> {
+    let settings = match resolve::find_command_line(input) {
+        Some(command_line) => {
+            debug!("Resolving the command line {}", command_line);
+            resolve::resolve(&[command_line], presets)?
+        }
+        None => parse_settings(input),
+    };
+    debug!("Read {} settings from the input", settings.len());
+    Ok(settings)
+}
+
+/// Like `normalized_settings`, with the names and values borrowed from the input instead of
+/// copied, for matching many inputs with a [`PresetIndex`]. `None` if the input is a command
+/// line, whose settings have to be worked out (and so can't be borrowed).
+pub fn borrowed_settings(input: &str) -> Option<HashMap<&str, &str>> {
+    if resolve::find_command_line(input).is_some() {
+        return None;
+    }
+    Some(parse_settings_borrowed(input))
+}
+
+/// Determines the preset of `mediainfo` output, x265 settings or an encoder command line, for
+/// the bindings. If there is no single match, the error says why, in English.
+pub fn determine(input: &str, presets: &[Preset]) -> Result<String, String> {
+    let settings = normalized_settings(input, presets)?;
+    preset_or_reason(determination(&settings, presets).for_input(input), &settings, presets, Lang::En)
+}
+
+/// The matching preset, or why there is no single match, in `lang`.
+pub fn preset_or_reason(determination: Determination, settings: &HashMap<String, String>, presets: &[Preset], lang: Lang) -> Result<String, String> {
+    match determination {
+        Determination::Match(preset_name) => Ok(preset_name),
+        Determination::Ambiguous(matching_presets) => Err(format!("{}: {}", lang.tr("Multiple matching presets found"), matching_presets.join(", "))),
+        Determination::NoMatch => {
+            let matches = closest_matches(settings, presets);
+            let closest = matches.iter().take_while(|(_, match_count)| *match_count == matches[0].1).map(|(name, _)| name.as_str());
+            Err(format!("{}. {}: {}", lang.tr("No matching presets found"), lang.tr("Closest matches"), closest.collect::<Vec<_>>().join(", ")))
+        }
+        Determination::InsufficientInput => Err(lang.tr("The input has none of the parameters that the presets set").to_string()),
+        Determination::NotDerivable { encoder } => Err(lang.not_derivable_message(&encoder)),
+    }
+}
+
+/// Matches the settings against the presets.
+pub fn determination(settings: &HashMap<String, String>, presets: &[Preset]) -> Determination {
+    determination_with(&Exact, settings, presets)
+}
+
+/// Matches the settings against the presets with a matching strategy.
+pub fn determination_with(matcher: &dyn Matcher, settings: &HashMap<String, String>, presets: &[Preset]) -> Determination {
+    if !settings.keys().any(|param_name| presets.iter().any(|(_, preset_settings)| preset_settings.contains_key(param_name))) {
+        return Determination::InsufficientInput;
+    }
+    let mut matching_presets = matcher.matching_presets(settings, presets);
+    match matching_presets.len() {
+        0 => Determination::NoMatch,
+        1 => Determination::Match(matching_presets.remove(0)),
+        _ => Determination::Ambiguous(matching_presets),
+    }
+}
+
+/// The names of all presets that match the settings.
+pub fn matching_presets(settings: &HashMap<String, String>, presets: &[Preset]) -> Vec<String> {
+    presets
+        .iter()
+        .filter(|(_, preset_settings)| preset_matches(settings, preset_settings))
+        .map(|(name, _)| name.to_owned())
+        .collect()
+}
+
+/// Checks if the given `input_settings` match a preset's settings.
+///
+/// The preset matches if all key-value pairs in `input_settings` are present in `preset_settings`.
+pub fn preset_matches(input_settings: &HashMap<String, String>, preset_settings: &HashMap<String, String>) -> bool {
+    input_settings
+        .iter()
+        .all(|(key, value)| {
+            let preset_value = preset_settings.get(key);
+            preset_value.is_none() || preset_value == Some(value)
+        })
+}
+
+/// A strategy for deciding which presets settings match, so that new ones can be added without
+/// changing the callers. Parameters that the input doesn't have are never held against a
+/// preset, except by `Strict`.
+pub trait Matcher: Send + Sync {
+    /// The names of the presets that match the settings, in the order of `presets`.
+    fn matching_presets(&self, settings: &HashMap<String, String>, presets: &[Preset]) -> Vec<String>;
+
+    /// Whether the matcher matches exactly like [`PresetIndex`], so that the index can be used
+    /// in its place.
+    fn is_exact(&self) -> bool {
+        false
+    }
+}
+
+/// Every parameter that both the input and the preset have must be the same. This is the
+/// default.
+pub struct Exact;
+
+impl Matcher for Exact {
+    fn matching_presets(&self, settings: &HashMap<String, String>, presets: &[Preset]) -> Vec<String> {
+        matching_presets(settings, presets)
+    }
+
+    fn is_exact(&self) -> bool {
+        true
+    }
+}
+
+/// Like `Exact`, and the input must also have every parameter that the preset defines, so
+/// truncated settings don't match.
+pub struct Strict;
+
+impl Matcher for Strict {
+    fn matching_presets(&self, settings: &HashMap<String, String>, presets: &[Preset]) -> Vec<String> {
+        presets.iter()
+            .filter(|(_, preset_settings)| preset_settings.iter().all(|(param_name, value)| settings.get(param_name) == Some(value)))
+            .map(|(name, _)| name.to_owned())
+            .collect()
+    }
+}
+
+/// The presets with the fewest parameters that differ from the input, if at most
+/// `max_differences` differ, for inputs with a few parameters changed from a preset.
+pub struct Tolerant {
+    pub max_differences: usize,
+}
+
+impl Matcher for Tolerant {
+    fn matching_presets(&self, settings: &HashMap<String, String>, presets: &[Preset]) -> Vec<String> {
+        let differences = presets.iter()
+            .map(|(name, preset_settings)| {
+                let differences = settings.iter().filter(|(param_name, value)| preset_settings.get(*param_name).is_some_and(|preset_value| preset_value != *value)).count();
+                (name, differences)
+            })
+            .collect::<Vec<_>>();
+        let fewest = differences.iter().map(|(_, differences)| *differences).min().unwrap_or(0);
+        differences.into_iter()
+            .filter(|(_, differences)| *differences == fewest && fewest <= self.max_differences)
+            .map(|(name, _)| name.to_owned())
+            .collect()
+    }
+}
+
+/// The presets whose matching parameters have the highest weight, if it's at least
+/// `min_score` of the weight of the parameters they define. A parameter weighs as much as the
+/// number of values it has in the presets, less one, so a parameter that tells many presets
+/// apart counts for more, and one that all presets share doesn't count.
+pub struct Weighted {
+    pub min_score: f64,
+}
+
+impl Matcher for Weighted {
+    fn matching_presets(&self, settings: &HashMap<String, String>, presets: &[Preset]) -> Vec<String> {
+        let weight = |param_name: &str| {
+            let values = presets.iter().filter_map(|(_, preset_settings)| preset_settings.get(param_name)).collect::<std::collections::HashSet<_>>();
+            values.len().saturating_sub(1) as f64
+        };
+        let weights = settings.keys().map(|param_name| (param_name.as_str(), weight(param_name))).collect::<HashMap<_, _>>();
+        let scores = presets.iter()
+            .map(|(name, preset_settings)| {
+                let comparable = settings.keys().filter(|param_name| preset_settings.contains_key(*param_name));
+                let total = comparable.clone().map(|param_name| weights[param_name.as_str()]).sum::<f64>();
+                let matching = comparable.filter(|param_name| preset_settings.get(*param_name) == settings.get(*param_name)).map(|param_name| weights[param_name.as_str()]).sum::<f64>();
+                (name, if total == 0.0 { 1.0 } else { matching / total })
+            })
+            .collect::<Vec<_>>();
+        let best = scores.iter().map(|(_, score)| *score).fold(0.0, f64::max);
+        scores.into_iter()
+            .filter(|(_, score)| *score == best && best >= self.min_score)
+            .map(|(name, _)| name.to_owned())
+            .collect()
+    }
+}
+
+/// Where settings fall between the presets, from `0.0` for the first (fastest) preset to one
+/// less than the number of presets for the last (slowest). Each parameter places the settings
+/// in the middle of the presets that have its value, or, for a number that no preset has,
+/// between the presets with the nearest values above and below it. The places are averaged,
+/// each with its parameter's weight as in [`Weighted`]. `None` if no parameter tells the presets
+/// apart.
+pub fn speed_position(settings: &HashMap<String, String>, presets: &[Preset]) -> Option<f64> {
+    // the middle of the positions of the presets with a value of the parameter
+    let position = |param_name: &str, value: &str| {
+        let positions = presets.iter().enumerate().filter(|(_, (_, preset_settings))| preset_settings.get(param_name).map(String::as_str) == Some(value)).map(|(position, _)| position as f64).collect::<Vec<_>>();
+        (!positions.is_empty()).then(|| positions.iter().sum::<f64>() / positions.len() as f64)
+    };
+    let mut weighted_sum = 0.0;
+    let mut total_weight = 0.0;
+    for (param_name, value) in settings {
+        let mut values = presets.iter().filter_map(|(_, preset_settings)| preset_settings.get(param_name)).collect::<Vec<_>>();
+        values.sort();
+        values.dedup();
+        let weight = values.len().saturating_sub(1) as f64;
+        if weight == 0.0 {
+            continue;
+        }
+        let place = position(param_name, value).or_else(|| {
+            let number = value.parse::<f64>().ok()?;
+            let numbers = values.iter().filter_map(|value| Some((value.parse::<f64>().ok()?, value.as_str()))).collect::<Vec<_>>();
+            let below = numbers.iter().filter(|(value, _)| *value < number).max_by(|a, b| a.0.total_cmp(&b.0));
+            let above = numbers.iter().filter(|(value, _)| *value > number).min_by(|a, b| a.0.total_cmp(&b.0));
+            match (below, above) {
+                (Some((low, low_value)), Some((high, high_value))) => {
+                    let (low_place, high_place) = (position(param_name, low_value)?, position(param_name, high_value)?);
+                    Some(low_place + (high_place - low_place) * (number - low) / (high - low))
+                }
+                (Some((_, nearest)), None) | (None, Some((_, nearest))) => position(param_name, nearest),
+                (None, None) => None,
+            }
+        });
+        if let Some(place) = place {
+            weighted_sum += place * weight;
+            total_weight += weight;
+        }
+    }
+    (total_weight > 0.0).then(|| weighted_sum / total_weight)
+}
+
+/// Describes where settings that match no preset fall between the presets (see
+/// [`speed_position`]), like `custom settings, roughly between slow and slower`.
+pub fn speed_estimate(settings: &HashMap<String, String>, presets: &[Preset]) -> Option<String> {
+    let position = speed_position(settings, presets)?;
+    let name = |position: f64| presets[position as usize].0.as_str();
+    if (position - position.round()).abs() < 0.25 {
+        Some(format!("custom settings, closest in speed to {}", name(position.round())))
+    } else {
+        Some(format!("custom settings, roughly between {} and {}", name(position.floor()), name(position.ceil())))
+    }
+}
+
+/// The presets with the number of the input's parameters they match, from the most to the
+/// least similar. Presets that match as many parameters are in the order of `presets`, i.e.
+/// from the fastest to the slowest, so the order is the same for every call.
+pub fn closest_matches(settings: &HashMap<String, String>, presets: &[Preset]) -> Vec<(String, usize)> {
+    closest_match_details(settings, presets).into_iter().map(|closest| (closest.preset, closest.matched)).collect()
+}
+
+/// How close a preset is to the input's settings, from [`closest_match_details`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClosestMatch {
+    pub preset: String,
+    /// The number of the input's parameters that the preset sets to the same value.
+    pub matched: usize,
+    /// The number of the input's parameters that the preset sets, which are compared.
+    pub compared: usize,
+    /// The parameters that the preset sets to another value, as `(name, input value, preset
+    /// value)`, in the order of the preset table.
+    pub differences: Vec<Difference>,
+}
+
+impl ClosestMatch {
+    /// The number of the input's parameters that would have to change for the preset to match
+    /// exactly: a single measure of the distance to it.
+    pub fn changes_needed(&self) -> usize {
+        self.differences.len()
+    }
+}
+
+/// Like [`closest_matches`], with the number of parameters compared with each preset and the
+/// parameters that differ.
+pub fn closest_match_details(settings: &HashMap<String, String>, presets: &[Preset]) -> Vec<ClosestMatch> {
+    let mut matches = presets.iter().map(|(preset, preset_settings)| {
+        let mut matched = 0;
+        let mut differences = Vec::new();
+        for (param_name, value) in settings {
+            match preset_settings.get(param_name) {
+                Some(preset_value) if preset_value == value => matched += 1,
+                Some(preset_value) => differences.push((param_name.clone(), value.clone(), preset_value.clone())),
+                None => {}
+            }
+        }
+        differences.sort_by_key(|(param_name, _, _)| (params::PARAMS.iter().position(|param| param.name == param_name.as_str()).unwrap_or(usize::MAX), param_name.clone()));
+        ClosestMatch { preset: preset.to_owned(), matched, compared: matched + differences.len(), differences }
+    }).collect::<Vec<_>>();
+    // the sort is stable, so ties stay in the presets' order
+    matches.sort_by_key(|closest| std::cmp::Reverse(closest.matched));
+    matches
+}
+
+/// The percentage of the input parameters defined by the preset that have the same value
+/// in the preset. Parameters the preset does not define are not counted.
+pub fn match_percentage(settings: &HashMap<String, String>, preset_settings: &HashMap<String, String>) -> usize {
+    let comparable = settings.keys().filter(|key| preset_settings.contains_key(*key)).count();
+    if comparable == 0 {
+        return 0;
+    }
+    let matching = settings.iter().filter(|(key, value)| preset_settings.get(*key) == Some(value)).count();
+    matching * 100 / comparable
+}
+
+/// How many of a preset's parameters the input has: the evidence for a match, as a preset
+/// matches however few of its parameters the input has.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coverage {
+    /// The number of the preset's parameters that the input has.
+    pub present: usize,
+    /// The number of parameters that the preset sets.
+    pub total: usize,
+    /// The preset's parameters that the input doesn't have, in the order of the preset table.
+    pub absent: Vec<String>,
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "matched on {}/{} preset parameters", self.present, self.total)?;
+        if !self.absent.is_empty() {
+            write!(f, "; {} absent: {}", self.absent.len(), self.absent.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// How many of the preset's parameters the settings have.
+pub fn coverage(settings: &HashMap<String, String>, preset_settings: &HashMap<String, String>) -> Coverage {
+    let mut absent = preset_settings.keys().filter(|param_name| !settings.contains_key(*param_name)).cloned().collect::<Vec<_>>();
+    absent.sort_by_key(|param_name| (params::PARAMS.iter().position(|param| param.name == param_name.as_str()).unwrap_or(usize::MAX), param_name.clone()));
+    Coverage { present: preset_settings.len() - absent.len(), total: preset_settings.len(), absent }
+}
+
+/// The presets compiled for matching many inputs. The parameters that the presets define and
+/// the values they set are numbered, and the set of presets that accept each value is
+/// precomputed, so settings are compiled once to [`CompiledSettings`], and matching them is a
+/// bitwise AND per parameter instead of comparing strings with every preset.
+pub struct PresetIndex {
+    preset_names: Vec<String>,
+    /// The ID of each parameter, which is its position in `params`.
+    param_ids: HashMap<String, u16>,
+    params: Vec<ParamIndex>,
+}
+
+/// The presets that accept each value of a parameter that some preset defines.
+struct ParamIndex {
+    /// The presets that don't define the parameter, which accept any value.
+    undefined: PresetSet,
+    /// The ID of each value that a preset sets, which is its position in `accepting`.
+    value_ids: HashMap<String, u16>,
+    /// For each value that a preset sets, the presets that accept it.
+    accepting: Vec<PresetSet>,
+}
+
+/// Settings compiled by a [`PresetIndex`], for matching them without looking at their text:
+/// the ID of each parameter that a preset defines, with the ID of its value, or `None` for a
+/// value that no preset sets. The other parameters are left out, as they don't decide a match.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompiledSettings(Vec<(u16, Option<u16>)>);
+
+/// A set of presets by their position, as a bitset.
+#[derive(Clone)]
+struct PresetSet(Vec<u64>);
+
+impl PresetSet {
+    fn new(preset_count: usize) -> PresetSet {
+        PresetSet(vec![0; preset_count.div_ceil(64)])
+    }
+
+    fn insert(&mut self, preset: usize) {
+        self.0[preset / 64] |= 1 << (preset % 64);
+    }
+
+    fn intersect(&mut self, other: &PresetSet) {
+        for (word, other_word) in self.0.iter_mut().zip(&other.0) {
+            *word &= other_word;
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.0.len() * 64).filter(|preset| self.0[preset / 64] & (1 << (preset % 64)) != 0)
+    }
+}
+
+impl PresetIndex {
+    /// The index of the built-in x265 presets, which is built on the first call and shared.
+    pub fn x265() -> Arc<PresetIndex> {
+        static X265: OnceLock<Arc<PresetIndex>> = OnceLock::new();
+        X265.get_or_init(|| Arc::new(PresetIndex::new(&presets::x265()))).clone()
+    }
+
+    pub fn new(presets: &[Preset]) -> PresetIndex {
+        let mut param_ids: HashMap<String, u16> = HashMap::new();
+        let mut params = Vec::new();
+        for (param_name, _) in presets.iter().flat_map(|(_, settings)| settings) {
+            if param_ids.contains_key(param_name) {
+                continue;
+            }
+            let mut undefined = PresetSet::new(presets.len());
+            for (preset, (_, settings)) in presets.iter().enumerate() {
+                if !settings.contains_key(param_name) {
+                    undefined.insert(preset);
+                }
+            }
+            let mut value_ids: HashMap<String, u16> = HashMap::new();
+            let mut accepting: Vec<PresetSet> = Vec::new();
+            for (preset, (_, settings)) in presets.iter().enumerate() {
+                if let Some(value) = settings.get(param_name) {
+                    let value_id = *value_ids.entry(value.clone()).or_insert_with(|| {
+                        accepting.push(undefined.clone());
+                        (accepting.len() - 1) as u16
+                    });
+                    accepting[value_id as usize].insert(preset);
+                }
+            }
+            param_ids.insert(param_name.clone(), params.len() as u16);
+            params.push(ParamIndex { undefined, value_ids, accepting });
+        }
+        PresetIndex { preset_names: presets.iter().map(|(name, _)| name.clone()).collect(), param_ids, params }
+    }
+
+    /// Numbers the parameters and values of the settings, for matching them with
+    /// `compiled_determination`, as many times as needed.
+    pub fn compile<'a>(&self, settings: impl IntoIterator<Item = (&'a str, &'a str)>) -> CompiledSettings {
+        let compiled = settings.into_iter().filter_map(|(param_name, value)| {
+            let param_id = *self.param_ids.get(param_name)?;
+            Some((param_id, self.params[param_id as usize].value_ids.get(value).copied()))
+        });
+        CompiledSettings(compiled.collect())
+    }
+
+    /// Matches the settings against the presets, like [`determination`].
+    pub fn determination<'a>(&self, settings: impl IntoIterator<Item = (&'a str, &'a str)>) -> Determination {
+        self.compiled_determination(&self.compile(settings))
+    }
+
+    /// Matches settings compiled by this index against the presets, like [`determination`].
+    pub fn compiled_determination(&self, settings: &CompiledSettings) -> Determination {
+        if settings.0.is_empty() {
+            return Determination::InsufficientInput;
+        }
+        let mut matching = PresetSet::new(self.preset_names.len());
+        for preset in 0..self.preset_names.len() {
+            matching.insert(preset);
+        }
+        for &(param_id, value_id) in &settings.0 {
+            let param = &self.params[param_id as usize];
+            matching.intersect(value_id.map_or(&param.undefined, |value_id| &param.accepting[value_id as usize]));
+        }
+        let mut matching_presets = matching.iter().map(|preset| self.preset_names[preset].clone()).collect::<Vec<_>>();
+        match matching_presets.len() {
+            0 => Determination::NoMatch,
+            1 => Determination::Match(matching_presets.remove(0)),
+            _ => Determination::Ambiguous(matching_presets),
+        }
+    }
+}
+
+#[test]
+fn test_determine() {
+    let presets = crate::presets::x265();
+    assert_eq!(determine("ctu=64 bframes=4 rdLevel=4 lookahead-slices=4", &presets), Ok("slow".to_string()));
+    assert_eq!(determine("ctu=32 min-cu-size=8 bframes=8", &presets), Err("No matching presets found. Closest matches: superfast, slower, veryslow, placebo".to_string()));
+    let closest = closest_matches(&crate::parse_string("ctu=64"), &presets).into_iter().map(|(preset_name, _)| preset_name).collect::<Vec<_>>();
+    assert_eq!(closest, ["veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow", "placebo", "ultrafast", "superfast"]);
+    let details = closest_match_details(&crate::parse_string("ctu=64 bframes=3 rdLevel=4 crf=20"), &presets);
+    assert_eq!(details[0], ClosestMatch {
+        preset: "slow".to_string(),
+        matched: 2,
+        compared: 3,
+        differences: vec![("bframes".to_string(), "3".to_string(), "4".to_string())],
+    });
+    assert_eq!(details[0].changes_needed(), 1);
+    // rc-lookahead=30 is between slow's 25 and slower's 40
+    let custom = crate::parse_string("rc-lookahead=30");
+    assert!(speed_position(&custom, &presets).is_some_and(|position| position > 6.0 && position < 7.5));
+    assert_eq!(speed_estimate(&crate::parse_string("ctu=32 min-cu-size=16"), &presets).as_deref(), Some("custom settings, roughly between ultrafast and superfast"));
+    assert_eq!(speed_estimate(&crate::parse_string("crf=20"), &presets), None);
+    let slow = &presets.iter().find(|(preset_name, _)| preset_name == "slow").unwrap().1;
+    let coverage = coverage(&crate::parse_string("ctu=64 bframes=4 rdLevel=4"), slow);
+    assert_eq!((coverage.present, coverage.absent.len()), (3, slow.len() - 3));
+    assert!(coverage.to_string().starts_with(&format!("matched on 3/{} preset parameters; {} absent: ", slow.len(), slow.len() - 3)));
+    let truncated = crate::parse_string("ctu=64 bframes=4 rdLevel=4 lookahead-slices=4");
+    assert_eq!(determination_with(&Strict, &truncated, &presets), Determination::NoMatch);
+    let changed = crate::parse_string("ctu=64 bframes=4 rdLevel=4 lookahead-slices=4 rc-lookahead=30");
+    assert_eq!(determination_with(&Exact, &changed, &presets), Determination::NoMatch);
+    assert_eq!(determination_with(&Tolerant { max_differences: 1 }, &changed, &presets), Determination::Match("slow".to_string()));
+    assert_eq!(determination_with(&Weighted { min_score: 0.5 }, &changed, &presets), Determination::Match("slow".to_string()));
+    assert_eq!(determine("crf=20", &presets), Err("The input has none of the parameters that the presets set".to_string()));
+}
+
+#[test]
+fn test_preset_index() {
+    let presets = crate::presets::x265();
+    let index = PresetIndex::new(&presets);
+    let inputs = ["ctu=32", "ctu=64 bframes=4 rdLevel=4 lookahead-slices=4", "ctu=32 bframes=8", "ctu=48", "crf=20", "me=star merange=92 crf=20"];
+    for input in inputs {
+        let settings = normalized_settings(input, &presets).unwrap();
+        let borrowed = borrowed_settings(input).unwrap();
+        assert_eq!(borrowed.len(), settings.len());
+        assert_eq!(index.determination(borrowed), determination(&settings, &presets), "{}", input);
+    }
+    // the parameters that no preset defines are left out, and values that none sets have no ID
+    let compiled = index.compile([("crf", "20"), ("ctu", "48"), ("bframes", "4")]);
+    assert_eq!(compiled.0.len(), 2);
+    assert_eq!(compiled.0.iter().filter(|(_, value_id)| value_id.is_none()).count(), 1);
+    assert_eq!(index.compiled_determination(&CompiledSettings::default()), Determination::InsufficientInput);
+}
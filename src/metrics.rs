@@ -0,0 +1,194 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+    time::Duration,
+};
+
+use tracing::{debug, warn};
+
+/// Counters of the daemon's requests, exported in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    /// The number of determinations by status and preset (the closest preset if there was no
+    /// single match).
+    determinations: BTreeMap<(String, String), u64>,
+    /// The number of errors by their code, like `io_error`.
+    errors: BTreeMap<String, u64>,
+    /// The number of requests answered from the daemon's cache, and of those that weren't.
+    cache_hits: u64,
+    cache_misses: u64,
+    requests: u64,
+    request_time: Duration,
+}
+
+impl Metrics {
+    pub fn record_determination(&self, status: &str, preset_name: &str, duration: Duration) {
+        let mut counters = self.counters.lock().expect("A request thread panicked");
+        *counters.determinations.entry((status.to_string(), preset_name.to_string())).or_default() += 1;
+        counters.record_request(duration);
+    }
+
+    pub fn record_error(&self, code: &str, duration: Duration) {
+        let mut counters = self.counters.lock().expect("A request thread panicked");
+        *counters.errors.entry(code.to_string()).or_default() += 1;
+        counters.record_request(duration);
+    }
+
+    /// Counts a request as answered from the cache or not, besides its determination or error.
+    pub fn record_cache_lookup(&self, hit: bool) {
+        let mut counters = self.counters.lock().expect("A request thread panicked");
+        if hit {
+            counters.cache_hits += 1;
+        } else {
+            counters.cache_misses += 1;
+        }
+    }
+
+    /// The counters in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let counters = self.counters.lock().expect("A request thread panicked");
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            let _ = writeln!(text, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(text, "{}{} {}", name, labels, value);
+            }
+        };
+        metric(
+            "determine_preset_determinations_total",
+            "counter",
+            "Determinations by status and preset (the closest preset if there was no single match).",
+            counters.determinations.iter()
+                .map(|((status, preset_name), count)| (format!("{{status=\"{}\",preset=\"{}\"}}", escape(status), escape(preset_name)), count.to_string()))
+                .collect(),
+        );
+        metric(
+            "determine_preset_errors_total",
+            "counter",
+            "Requests that failed, by error code.",
+            counters.errors.iter().map(|(code, count)| (format!("{{code=\"{}\"}}", escape(code)), count.to_string())).collect(),
+        );
+        metric("determine_preset_requests_total", "counter", "Requests answered.", vec![(String::new(), counters.requests.to_string())]);
+        metric(
+            "determine_preset_request_seconds_total",
+            "counter",
+            "Time spent answering requests, including reading the files.",
+            vec![(String::new(), counters.request_time.as_secs_f64().to_string())],
+        );
+        metric(
+            "determine_preset_cache_hits_total",
+            "counter",
+            "Requests answered from the cache, as the file hadn't changed since it was last requested.",
+            vec![(String::new(), counters.cache_hits.to_string())],
+        );
+        metric(
+            "determine_preset_cache_misses_total",
+            "counter",
+            "Requests whose file had to be read.",
+            vec![(String::new(), counters.cache_misses.to_string())],
+        );
+        let lookups = counters.cache_hits + counters.cache_misses;
+        metric(
+            "determine_preset_cache_hit_ratio",
+            "gauge",
+            "The share of the requests answered from the cache.",
+            vec![(String::new(), (counters.cache_hits as f64 / lookups.max(1) as f64).to_string())],
+        );
+        let request_seconds = counters.request_time.as_secs_f64();
+        metric(
+            "determine_preset_scan_files_per_second",
+            "gauge",
+            "Requests answered per second spent answering them, the daemon's scan throughput.",
+            vec![(String::new(), (counters.requests as f64 / request_seconds.max(f64::EPSILON)).to_string())],
+        );
+        text
+    }
+}
+
+impl Counters {
+    fn record_request(&mut self, duration: Duration) {
+        self.requests += 1;
+        self.request_time += duration;
+    }
+}
+
+/// Escapes a label value for the Prometheus text format.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// How long a metrics client may take to send its request or read the answer, so one that
+/// stalls doesn't keep its connection open forever.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Answers HTTP requests for `/metrics` until the process is killed. Connections are served in
+/// parallel, like the daemon's.
+pub fn serve(listener: TcpListener, metrics: &Metrics) {
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    scope.spawn(|| {
+                        if let Err(err) = handle_request(stream, metrics) {
+                            warn!("Could not answer a metrics request: {}", err);
+                        }
+                    });
+                }
+                Err(err) => warn!("Could not accept a metrics connection: {}", err),
+            }
+        }
+    });
+}
+
+fn handle_request(stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // the headers aren't needed, but are read so the client doesn't see the connection reset
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    debug!("Metrics request: {}", request_line.trim_end());
+    let (status, body) = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", "/metrics", _] => ("200 OK", metrics.render()),
+        ["GET", _, _] => ("404 Not Found", "Not found; the metrics are at /metrics\n".to_string()),
+        _ => ("405 Method Not Allowed", "Only GET is supported\n".to_string()),
+    };
+    write!(
+        &stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[test]
+fn test_render_metrics() {
+    let metrics = Metrics::default();
+    metrics.record_determination("match", "slow", Duration::from_millis(500));
+    metrics.record_determination("match", "slow", Duration::from_millis(250));
+    metrics.record_error("io_error", Duration::from_millis(250));
+    metrics.record_cache_lookup(true);
+    metrics.record_cache_lookup(false);
+    metrics.record_cache_lookup(false);
+    metrics.record_cache_lookup(false);
+    let text = metrics.render();
+    assert!(text.contains("\ndetermine_preset_determinations_total{status=\"match\",preset=\"slow\"} 2\n"));
+    assert!(text.contains("\ndetermine_preset_errors_total{code=\"io_error\"} 1\n"));
+    assert!(text.contains("\ndetermine_preset_requests_total 3\n"));
+    assert!(text.contains("\ndetermine_preset_request_seconds_total 1\n"));
+    assert!(text.contains("# TYPE determine_preset_cache_hit_ratio gauge\ndetermine_preset_cache_hit_ratio 0.25\n"));
+    assert!(text.contains("\ndetermine_preset_scan_files_per_second 3\n"));
+}
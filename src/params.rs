@@ -0,0 +1,252 @@
+/// Where the x265 command line options are documented. Each option has an anchor on this page.
+const CLI_DOCS: &str = "https://x265.readthedocs.io/en/master/cli.html";
+
+/// An encoder parameter used in the preset tables.
+pub struct Param {
+    /// The name used in the preset tables.
+    pub name: &'static str,
+    /// The x265 command line option (without the leading `--`) that sets the parameter.
+    pub option: &'static str,
+    /// Whether the option is a switch, enabled with `--option` and disabled with `--no-option`,
+    /// rather than taking a value.
+    pub flag: bool,
+    /// A one-line explanation of what the parameter does.
+    pub description: &'static str,
+}
+
+/// The parameters in the x265 preset table. Most are named like their command line option, but
+/// the documentation's table uses a few different names.
+pub const PARAMS: &[Param] = &[
+    Param { name: "ctu", option: "ctu", flag: false,
+        description: "Maximum coding tree unit size. Larger CTUs compress better, especially at high resolutions." },
+    Param { name: "min-cu-size", option: "min-cu-size", flag: false,
+        description: "Minimum coding unit size. Smaller sizes allow finer partitioning at the cost of speed." },
+    Param { name: "bframes", option: "bframes", flag: false,
+        description: "Maximum number of consecutive B-frames." },
+    Param { name: "b-adapt", option: "b-adapt", flag: false,
+        description: "How B-frames are placed: 0 fixed, 1 fast lookahead decision, 2 full trellis decision." },
+    Param { name: "rc-lookahead", option: "rc-lookahead", flag: false,
+        description: "Number of frames the lookahead examines for frame type and rate control decisions." },
+    Param { name: "lookahead-slices", option: "lookahead-slices", flag: false,
+        description: "Number of slices the lookahead analysis is split into for threading. 0 and 1 both mean no slicing." },
+    Param { name: "scenecut", option: "scenecut", flag: false,
+        description: "How aggressively I-frames are inserted at scene changes. 0 disables scenecut detection." },
+    Param { name: "ref", option: "ref", flag: false,
+        description: "Maximum number of reference frames for inter prediction." },
+    Param { name: "limit-refs", option: "limit-refs", flag: false,
+        description: "Limits the references searched, using the choices of neighbouring and split CUs (bitmask of 1, 2)." },
+    Param { name: "me", option: "me", flag: false,
+        description: "Motion search method: dia, hex, umh, star, sea or full, from the fastest to the most thorough." },
+    Param { name: "merange", option: "merange", flag: false,
+        description: "Motion search range in pixels." },
+    Param { name: "subme", option: "subme", flag: false,
+        description: "Amount of sub-pixel motion refinement, from 0 to 7." },
+    Param { name: "rect", option: "rect", flag: true,
+        description: "Enables the analysis of rectangular motion partitions (Nx2N and 2NxN)." },
+    Param { name: "amp", option: "amp", flag: true,
+        description: "Enables the analysis of asymmetric motion partitions." },
+    Param { name: "limit-modes", option: "limit-modes", flag: true,
+        description: "Skips the analysis of partition modes that are unlikely to be chosen." },
+    Param { name: "max-merge", option: "max-merge", flag: false,
+        description: "Maximum number of merge candidates considered, from 1 to 5." },
+    Param { name: "early-skip", option: "early-skip", flag: true,
+        description: "Checks for the merge/skip mode first and stops the analysis early if it is chosen." },
+    Param { name: "recursion-skip", option: "rskip", flag: false,
+        description: "Skips the recursion into smaller CUs when the current depth already looks good." },
+    Param { name: "fast-intra", option: "fast-intra", flag: true,
+        description: "Uses a faster, less exhaustive search of the angular intra modes." },
+    Param { name: "b-intra", option: "b-intra", flag: true,
+        description: "Evaluates intra modes in B-frames." },
+    Param { name: "sao", option: "sao", flag: true,
+        description: "Enables the Sample Adaptive Offset loop filter." },
+    Param { name: "signhide", option: "signhide", flag: true,
+        description: "Hides the sign bit of one coefficient per transform group, saving bits." },
+    Param { name: "weightp", option: "weightp", flag: true,
+        description: "Enables weighted prediction in P-frames, which helps with fades." },
+    Param { name: "weightb", option: "weightb", flag: true,
+        description: "Enables weighted prediction in B-frames." },
+    Param { name: "aq-mode", option: "aq-mode", flag: false,
+        description: "Adaptive quantization mode: 0 disabled, 1 variance, 2 auto-variance, 3 auto-variance biased to dark scenes, 4 edge-aware." },
+    Param { name: "cuTree", option: "cutree", flag: true,
+        description: "Enables CU-tree, which lowers the quantizer of blocks that are referenced by later frames." },
+    Param { name: "rdLevel", option: "rd", flag: false,
+        description: "Level of rate-distortion optimization in the mode decision, from 1 to 6. Higher is slower and better." },
+    Param { name: "rdoq-level", option: "rdoq-level", flag: false,
+        description: "Rate-distortion optimized quantization: 0 disabled, 1 at the end of the analysis, 2 throughout." },
+    Param { name: "tu-intra", option: "tu-intra-depth", flag: false,
+        description: "Maximum depth of the transform unit tree for intra CUs." },
+    Param { name: "tu-inter", option: "tu-inter-depth", flag: false,
+        description: "Maximum depth of the transform unit tree for inter CUs." },
+    Param { name: "limit-tu", option: "limit-tu", flag: false,
+        description: "Limits the transform unit recursion for inter CUs, using the depths of neighbouring TUs (0 to 4)." },
+];
+
+/// Parameters that don't differ between the presets, but often matter when comparing encodes.
+pub const OTHER_PARAMS: &[Param] = &[
+    Param { name: "crf", option: "crf", flag: false,
+        description: "Constant rate factor: the quality target. Lower is higher quality and larger." },
+    Param { name: "qcomp", option: "qcomp", flag: false,
+        description: "Quantizer curve compression. Lower values make the bitrate more constant." },
+    Param { name: "psy-rd", option: "psy-rd", flag: false,
+        description: "Psycho-visual rate-distortion strength, which favors preserving detail and grain." },
+    Param { name: "psy-rdoq", option: "psy-rdoq", flag: false,
+        description: "Psycho-visual strength of the rate-distortion optimized quantization." },
+    Param { name: "aq-strength", option: "aq-strength", flag: false,
+        description: "Strength of the adaptive quantization." },
+    Param { name: "deblock", option: "deblock", flag: false,
+        description: "Deblocking filter offsets (tC:beta). Negative values preserve more detail." },
+    Param { name: "keyint", option: "keyint", flag: false,
+        description: "Maximum interval between keyframes (GOP length)." },
+    Param { name: "min-keyint", option: "min-keyint", flag: false,
+        description: "Minimum interval between keyframes." },
+    Param { name: "open-gop", option: "open-gop", flag: true,
+        description: "Allows frames after a keyframe to reference frames before it." },
+    Param { name: "b-pyramid", option: "b-pyramid", flag: true,
+        description: "Allows B-frames to be used as references." },
+    Param { name: "vbv-maxrate", option: "vbv-maxrate", flag: false,
+        description: "Maximum bitrate of the video buffering verifier, in kbps. 0 means unconstrained." },
+    Param { name: "vbv-bufsize", option: "vbv-bufsize", flag: false,
+        description: "Size of the video buffering verifier buffer, in kbits." },
+    Param { name: "frame-threads", option: "frame-threads", flag: false,
+        description: "Number of frames encoded in parallel. Doesn't change the preset, but slightly affects quality." },
+];
+
+/// The other options that x265 records in its settings string, or that its command line takes,
+/// without descriptions. `right` and `bottom` are the fields of `conformance-window-offsets`,
+/// which x265 writes as separate settings.
+pub const KNOWN_OPTIONS: &[&str] = &[
+    "cpuid", "wpp", "pmode", "pme", "psnr", "ssim", "log-level", "input-csp", "input-res", "input-depth", "output-depth", "interlace",
+    "total-frames", "level-idc", "high-tier", "uhd-bd", "allow-non-conformance", "repeat-headers", "annexb", "aud", "eob", "eos", "hrd",
+    "info", "hash", "temporal-layers", "gop-lookahead", "b-adapt", "bframe-bias", "rc-lookahead", "scenecut", "hist-scenecut", "radl",
+    "splice", "intra-refresh", "max-tu-size", "dynamic-rd", "ssim-rd", "tskip", "nr-intra", "nr-inter", "constrained-intra",
+    "strong-intra-smoothing", "max-merge", "limit-refs", "limit-modes", "temporal-mvp", "frame-dup", "hme", "analyze-src-pics",
+    "sao-non-deblock", "selective-sao", "early-skip", "rskip", "fast-intra", "tskip-fast", "cu-lossless", "b-intra", "splitrd-skip",
+    "rdpenalty", "rd-refine", "lossless", "cbqpoffs", "crqpoffs", "rc", "qpstep", "stats-write", "stats-read", "ipratio", "pbratio",
+    "zone-count", "zones", "strict-cbr", "qg-size", "rc-grain", "qpmax", "qpmin", "const-vbv", "vbv-init", "bitrate", "qp", "pass",
+    "sar", "overscan", "videoformat", "range", "colorprim", "transfer", "colormatrix", "chromaloc", "chromaloc-top", "chromaloc-bottom",
+    "display-window", "master-display", "max-cll", "cll", "min-luma", "max-luma", "log2-max-poc-lsb", "vui-timing-info", "vui-hrd-info",
+    "slices", "opt-qp-pps", "opt-ref-list-length-pps", "multi-pass-opt-rps", "scenecut-bias", "opt-cu-delta-qp", "aq-motion", "hdr",
+    "hdr10", "hdr-opt", "hdr10-opt", "dhdr10-opt", "idr-recovery-sei", "analysis-reuse-level", "analysis-save-reuse-level",
+    "analysis-load-reuse-level", "scale-factor", "refine-intra", "refine-inter", "refine-mv", "refine-ctu-distortion", "limit-sao",
+    "ctu-info", "lowpass-dct", "refine-analysis-type", "copy-pic", "max-ausize-factor", "dynamic-refine", "single-sei", "hevc-aq", "svt",
+    "field", "qp-adaptation-range", "scenecut-aware-qp", "conformance-window-offsets", "right", "bottom", "decoder-max-rate",
+    "vbv-live-multi-pass", "mcstf", "sbrc", "pools", "numa-pools", "lookahead-threads", "preset", "tune", "profile", "level",
+];
+
+/// Options that describe the input, the machine or the run of the encoder rather than how to
+/// encode, so a command line that reproduces an encode leaves them out. The rate control and
+/// the tune are given as ffmpeg options instead.
+pub const RUN_OPTIONS: &[&str] = &[
+    "cpuid", "input-csp", "input-res", "input-depth", "output-depth", "total-frames", "conformance-window-offsets", "right", "bottom",
+    "frame-threads", "pools", "numa-pools", "lookahead-threads", "psnr", "ssim", "log-level", "stats-write", "stats-read", "pass", "rc",
+    "crf", "preset", "tune",
+];
+
+/// The x265 defaults of the options that the presets don't set, as x265 records them in its
+/// settings string (switches as `1` or `0`). Options that describe the input or the machine
+/// rather than a choice (like `cpuid`, `input-res` and `frame-threads`), that x265 works out
+/// from other options (like `min-keyint` and `selective-sao`), or whose default depends on the
+/// preset (like `psy-rdoq`) aren't listed.
+pub const DEFAULTS: &[(&str, &str)] = &[
+    ("wpp", "1"), ("pmode", "0"), ("pme", "0"), ("psnr", "0"), ("ssim", "0"), ("log-level", "2"), ("interlace", "0"),
+    ("level-idc", "0"), ("high-tier", "1"), ("uhd-bd", "0"), ("allow-non-conformance", "0"), ("repeat-headers", "0"),
+    ("annexb", "1"), ("aud", "0"), ("eob", "0"), ("eos", "0"), ("hrd", "0"), ("info", "1"), ("hash", "0"), ("temporal-layers", "0"),
+    ("open-gop", "1"), ("keyint", "250"), ("gop-lookahead", "0"), ("b-pyramid", "1"), ("bframe-bias", "0"), ("hist-scenecut", "0"),
+    ("radl", "0"), ("splice", "0"), ("intra-refresh", "0"), ("max-tu-size", "32"), ("dynamic-rd", "0"), ("ssim-rd", "0"),
+    ("tskip", "0"), ("nr-intra", "0"), ("nr-inter", "0"), ("constrained-intra", "0"), ("strong-intra-smoothing", "1"),
+    ("temporal-mvp", "1"), ("frame-dup", "0"), ("hme", "0"), ("analyze-src-pics", "0"), ("deblock", "0:0"), ("sao-non-deblock", "0"),
+    ("tskip-fast", "0"), ("cu-lossless", "0"), ("splitrd-skip", "0"), ("rdpenalty", "0"), ("rd-refine", "0"), ("lossless", "0"),
+    ("cbqpoffs", "0"), ("crqpoffs", "0"), ("rc", "crf"), ("crf", "28"), ("qcomp", "0.60"), ("qpstep", "4"), ("stats-write", "0"),
+    ("stats-read", "0"), ("ipratio", "1.40"), ("pbratio", "1.30"), ("aq-mode", "2"), ("aq-strength", "1.00"), ("psy-rd", "2.00"),
+    ("cutree", "1"), ("zone-count", "0"), ("strict-cbr", "0"), ("rc-grain", "0"), ("qpmax", "69"), ("qpmin", "0"), ("const-vbv", "0"),
+    ("vbv-maxrate", "0"), ("vbv-bufsize", "0"), ("sar", "0"), ("overscan", "0"), ("videoformat", "5"), ("range", "0"), ("colorprim", "2"),
+    ("transfer", "2"), ("colormatrix", "2"), ("chromaloc", "0"), ("display-window", "0"), ("cll", "0,0"), ("min-luma", "0"),
+    ("log2-max-poc-lsb", "8"), ("vui-timing-info", "1"), ("vui-hrd-info", "1"), ("slices", "1"), ("opt-qp-pps", "0"),
+    ("opt-ref-list-length-pps", "0"), ("multi-pass-opt-rps", "0"), ("scenecut-bias", "0.05"), ("opt-cu-delta-qp", "0"),
+    ("aq-motion", "0"), ("hdr10", "0"), ("hdr10-opt", "0"), ("dhdr10-opt", "0"), ("idr-recovery-sei", "0"),
+    ("analysis-reuse-level", "0"), ("analysis-save-reuse-level", "0"), ("analysis-load-reuse-level", "0"), ("scale-factor", "0"),
+    ("refine-intra", "0"), ("refine-inter", "0"), ("refine-mv", "1"), ("refine-ctu-distortion", "0"), ("ctu-info", "0"),
+    ("lowpass-dct", "0"), ("refine-analysis-type", "0"), ("copy-pic", "1"), ("max-ausize-factor", "1.0"), ("dynamic-refine", "0"),
+    ("single-sei", "0"), ("hevc-aq", "0"), ("svt", "0"), ("field", "0"), ("qp-adaptation-range", "1.00"), ("scenecut-aware-qp", "0"),
+    ("vbv-live-multi-pass", "0"), ("mcstf", "0"), ("sbrc", "0"),
+];
+
+/// Whether a parameter is at its x265 default (see `DEFAULTS`), so it says nothing about how the
+/// encode was configured. Parameters without a known default aren't.
+pub fn is_default(name: &str, value: &str) -> bool {
+    DEFAULTS.iter().find(|(option, _)| *option == name).is_some_and(|(_, default)| same_value(name, value, default))
+}
+
+/// Whether a value is the same as another, comparing numbers by value (so `2` is `2.00`) and
+/// pairs of numbers in either form (`0:0` or `0,0`).
+pub fn same_value(name: &str, value: &str, other: &str) -> bool {
+    let numbers = |text: &str| text.split([':', ',']).map(|number| number.trim().parse::<f64>().ok()).collect::<Option<Vec<_>>>();
+    match (numbers(value), numbers(other)) {
+        // `deblock=1` is shorthand for the default offsets
+        (Some(values), Some(others)) if name == "deblock" && values == [1.0] => others == [0.0, 0.0],
+        (Some(values), Some(others)) => values == others,
+        _ => value == other,
+    }
+}
+
+/// Whether a parameter, by its preset table name or its option name, is an x265 option: one of
+/// `PARAMS`, `OTHER_PARAMS` or `KNOWN_OPTIONS`.
+pub fn is_known(name: &str) -> bool {
+    find(name).is_some() || KNOWN_OPTIONS.contains(&name)
+}
+
+/// Looks up a parameter by its preset table name or by its command line option name.
+pub fn find(name: &str) -> Option<&'static Param> {
+    PARAMS.iter().chain(OTHER_PARAMS).find(|param| param.name == name || param.option == name)
+}
+
+/// Whether the parameter is one of the parameters that the x265 presets set.
+pub fn is_preset_param(name: &str) -> bool {
+    PARAMS.iter().any(|param| param.name == name || param.option == name)
+}
+
+/// The URL of the x265 documentation for a known parameter.
+pub fn doc_url(name: &str) -> Option<String> {
+    find(name).map(|param| format!("{}#cmdoption-{}", CLI_DOCS, param.option))
+}
+
+/// The x265 command line argument(s) that set a parameter to a value, e.g. `--ref 4` or `--no-rect`.
+pub fn x265_args(name: &str, value: &str) -> String {
+    match find(name) {
+        Some(param) if param.flag && value == "0" => format!("--no-{}", param.option),
+        Some(param) if param.flag => format!("--{}", param.option),
+        Some(param) => format!("--{} {}", param.option, value),
+        None => format!("--{} {}", name, value),
+    }
+}
+
+/// A parameter in the `key=value` form of ffmpeg's `-x265-params` option, e.g. `ref=4` or `rect=0`.
+pub fn x265_param(name: &str, value: &str) -> String {
+    let option = find(name).map_or(name, |param| param.option);
+    format!("{}={}", option, value)
+}
+
+/// Converts a parameter to the form the preset table uses: renames it from its x265 option name
+/// (e.g. `rd` to `rdLevel`), and converts values that x265 reports differently.
+pub fn normalize(name: &str, value: &str) -> (String, String) {
+    let (name, value) = normalize_borrowed(name, value);
+    (name.to_string(), value.to_string())
+}
+
+/// Like `normalize`, without copying the name and value.
+pub fn normalize_borrowed<'a>(name: &'a str, value: &'a str) -> (&'a str, &'a str) {
+    let name = find(name).map_or(name, |param| param.name);
+    let value = match (name, value) {
+        ("me", value) => motion_search_name(value).unwrap_or(value),
+        // for lookahead-slices, 0 is the same as 1, but the reference table uses 1, not 0.
+        ("lookahead-slices", "0") => "1",
+        (_, value) => value,
+    };
+    (name, value)
+}
+
+/// The name of a motion search method given by number, as in the `me=3` of an x265 settings
+/// string.
+pub fn motion_search_name(value: &str) -> Option<&'static str> {
+    ["dia", "hex", "umh", "star", "sea", "full"].get(value.parse::<usize>().ok()?).copied()
+}
@@ -0,0 +1,240 @@
+//! A writer of Parquet files with a table of text columns, for `scan --format parquet`. It writes
+//! the subset of the format that readers like DuckDB, pandas and Spark need: one row group, one
+//! uncompressed page per column, and PLAIN-encoded values that may be null.
+
+use std::ops::Deref;
+
+/// The magic number at the start and end of a Parquet file.
+const MAGIC: &[u8] = b"PAR1";
+
+// Parquet's enums, from parquet.thrift
+const TYPE_BYTE_ARRAY: i32 = 6;
+const REPETITION_OPTIONAL: i32 = 1;
+const CONVERTED_TYPE_UTF8: i32 = 0;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const CODEC_UNCOMPRESSED: i32 = 0;
+const PAGE_TYPE_DATA_PAGE: i32 = 0;
+
+/// A Parquet file with the named text columns. Each row has a value, or null, for each column.
+pub fn write<S: Deref<Target = str>>(column_names: &[&str], rows: &[Vec<Option<S>>]) -> Vec<u8> {
+    let mut file = MAGIC.to_vec();
+    let mut column_chunks = Vec::new();
+    for (column, column_name) in column_names.iter().enumerate() {
+        let values = rows.iter().map(|row| row.get(column).and_then(Option::as_deref)).collect::<Vec<_>>();
+        let offset = file.len();
+        file.extend(column_chunk(&values));
+        column_chunks.push((column_name, offset, file.len() - offset));
+    }
+
+    let mut footer = Compact::default();
+    footer.struct_begin();
+    footer.i32(1, 1);
+    footer.list_begin(2, STRUCT, column_names.len() + 1);
+    footer.struct_begin();
+    footer.string(4, "schema");
+    footer.i32(5, column_names.len() as i32);
+    footer.struct_end();
+    for column_name in column_names {
+        footer.struct_begin();
+        footer.i32(1, TYPE_BYTE_ARRAY);
+        footer.i32(3, REPETITION_OPTIONAL);
+        footer.string(4, column_name);
+        footer.i32(6, CONVERTED_TYPE_UTF8);
+        footer.struct_end();
+    }
+    footer.i64(3, rows.len() as i64);
+    footer.list_begin(4, STRUCT, 1);
+    footer.struct_begin();
+    footer.list_begin(1, STRUCT, column_chunks.len());
+    for (column_name, offset, size) in &column_chunks {
+        footer.struct_begin();
+        footer.i64(2, *offset as i64);
+        footer.field(3, STRUCT);
+        footer.struct_begin();
+        footer.i32(1, TYPE_BYTE_ARRAY);
+        footer.list_begin(2, I32, 2);
+        footer.zigzag(ENCODING_PLAIN.into());
+        footer.zigzag(ENCODING_RLE.into());
+        footer.list_begin(3, BINARY, 1);
+        footer.binary(column_name.as_bytes());
+        footer.i32(4, CODEC_UNCOMPRESSED);
+        footer.i64(5, rows.len() as i64);
+        footer.i64(6, *size as i64);
+        footer.i64(7, *size as i64);
+        footer.i64(9, *offset as i64);
+        footer.struct_end();
+        footer.struct_end();
+    }
+    let total_size = column_chunks.iter().map(|(_, _, size)| size).sum::<usize>();
+    footer.i64(2, total_size as i64);
+    footer.i64(3, rows.len() as i64);
+    footer.struct_end();
+    footer.string(6, concat!("determine-preset version ", env!("CARGO_PKG_VERSION")));
+    footer.struct_end();
+
+    file.extend(&footer.0);
+    file.extend((footer.0.len() as u32).to_le_bytes());
+    file.extend(MAGIC);
+    file
+}
+
+/// A column's page: its header, then the definition levels saying which values aren't null,
+/// then the values that aren't.
+fn column_chunk(values: &[Option<&str>]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let levels = definition_levels(values);
+    data.extend((levels.len() as u32).to_le_bytes());
+    data.extend(levels);
+    for value in values.iter().flatten() {
+        data.extend((value.len() as u32).to_le_bytes());
+        data.extend(value.as_bytes());
+    }
+
+    let mut header = Compact::default();
+    header.struct_begin();
+    header.i32(1, PAGE_TYPE_DATA_PAGE);
+    header.i32(2, data.len() as i32);
+    header.i32(3, data.len() as i32);
+    header.field(5, STRUCT);
+    header.struct_begin();
+    header.i32(1, values.len() as i32);
+    header.i32(2, ENCODING_PLAIN);
+    header.i32(3, ENCODING_RLE);
+    header.i32(4, ENCODING_RLE);
+    header.struct_end();
+    header.struct_end();
+    header.0.extend(data);
+    header.0
+}
+
+/// The definition levels of an optional column, 1 for a value and 0 for a null, as a single
+/// bit-packed run of the RLE/bit-packing hybrid encoding with a bit width of 1.
+fn definition_levels(values: &[Option<&str>]) -> Vec<u8> {
+    let mut levels = Vec::new();
+    push_varint(&mut levels, ((values.len().div_ceil(8) as u64) << 1) | 1);
+    for group in values.chunks(8) {
+        levels.push(group.iter().enumerate().filter(|(_, value)| value.is_some()).fold(0, |byte, (bit, _)| byte | 1 << bit));
+    }
+    levels
+}
+
+// Thrift's compact protocol types
+const BINARY: u8 = 8;
+const I32: u8 = 5;
+const I64: u8 = 6;
+const STRUCT: u8 = 12;
+
+/// A struct encoded with Thrift's compact protocol, in which Parquet's metadata is written.
+/// Fields are written in increasing order of their ids, as their ids are encoded as the
+/// difference from the previous field's.
+#[derive(Default)]
+struct Compact(Vec<u8>, Vec<i16>);
+
+impl Compact {
+    fn field(&mut self, id: i16, field_type: u8) {
+        let last_id = self.1.last_mut().expect("Fields are written in a struct");
+        let delta = id - *last_id;
+        assert!((1..=15).contains(&delta), "Field ids are written in increasing order");
+        *last_id = id;
+        self.0.push((delta as u8) << 4 | field_type);
+    }
+
+    fn struct_begin(&mut self) {
+        self.1.push(0);
+    }
+
+    fn struct_end(&mut self) {
+        self.1.pop();
+        self.0.push(0);
+    }
+
+    fn zigzag(&mut self, value: i64) {
+        push_varint(&mut self.0, ((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        self.zigzag(value.into());
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        self.zigzag(value);
+    }
+
+    fn binary(&mut self, value: &[u8]) {
+        push_varint(&mut self.0, value.len() as u64);
+        self.0.extend(value);
+    }
+
+    fn string(&mut self, id: i16, value: &str) {
+        self.field(id, BINARY);
+        self.binary(value.as_bytes());
+    }
+
+    /// Starts a list field of `len` elements, which are written next.
+    fn list_begin(&mut self, id: i16, element_type: u8, len: usize) {
+        self.field(id, 9);
+        if len < 15 {
+            self.0.push((len as u8) << 4 | element_type);
+        } else {
+            self.0.push(0xf0 | element_type);
+            push_varint(&mut self.0, len as u64);
+        }
+    }
+}
+
+/// Appends an unsigned LEB128 varint.
+fn push_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+#[test]
+fn test_parquet() {
+    let rows = [vec![Some("a.mkv".to_string()), Some("slow".to_string())], vec![Some("b.mkv".to_string()), None]];
+    let file = write(&["path", "preset"], &rows);
+    assert!(file.starts_with(MAGIC) && file.ends_with(MAGIC));
+    let footer_len = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+    assert!(footer_len < file.len() - 12);
+    // the definition levels of the preset column: one bit-packed group, with only the first set
+    assert_eq!(definition_levels(&[Some("slow"), None]), [0b11, 0b01]);
+    let mut compact = Compact::default();
+    compact.struct_begin();
+    compact.i32(1, -1);
+    compact.string(3, "ab");
+    compact.struct_end();
+    assert_eq!(compact.0, [0x15, 0x01, 0x28, 0x02, b'a', b'b', 0x00]);
+}
+
+#[test]
+fn test_read_parquet() {
+    use ::parquet::{file::reader::{FileReader, SerializedFileReader}, record::Field};
+
+    let mut rows = vec![
+        vec![Some("a.mkv".to_string()), Some("match".to_string()), Some("slow".to_string())],
+        vec![Some("b.mkv".to_string()), Some("error".to_string()), None],
+        vec![Some("c:\\video's \"name\".mkv".to_string()), None, Some("ultrafast".to_string())],
+    ];
+    // enough rows for several groups of definition levels
+    rows.extend((0..300).map(|video| vec![Some(format!("{}.mkv", video)), Some("match".to_string()), (video % 3 == 0).then(|| "medium".to_string())]));
+    let path = std::env::temp_dir().join(format!("determine-preset-test-{}.parquet", std::process::id()));
+    std::fs::write(&path, write(&["path", "status", "preset"], &rows)).unwrap();
+    let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+    let reader = reader.unwrap();
+    let columns = reader.metadata().file_metadata().schema_descr().columns().iter().map(|column| column.name().to_string()).collect::<Vec<_>>();
+    assert_eq!(columns, ["path", "status", "preset"]);
+    let read_rows = reader.get_row_iter(None).unwrap()
+        .map(|row| row.unwrap().get_column_iter().map(|(_, field)| match field {
+            Field::Str(text) => Some(text.clone()),
+            Field::Null => None,
+            field => panic!("Expected a string, found {:?}", field),
+        }).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(read_rows, rows);
+}
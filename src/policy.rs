@@ -0,0 +1,75 @@
+use std::{collections::BTreeMap, fs, path::{Component, Path}};
+
+use crate::GateArgs;
+
+/// The conditions that the videos in directory trees must meet, read from a TOML file with a
+/// table per directory, like:
+///
+/// ```toml
+/// ["Movies/4K"]
+/// require = "slow"
+/// filter = "bit_depth == 10"
+///
+/// [TV]
+/// require = "medium"
+/// ```
+///
+/// The tables take the keys of the options of the same names: `require`, `at-most` and
+/// `filter`.
+pub struct Policy {
+    /// The directories, split into their components, with their conditions, from the deepest.
+    rules: Vec<(Vec<String>, String, GateArgs)>,
+}
+
+pub fn load(path: &str) -> Result<Policy, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Could not read the policy {}: {}", path, err))?;
+    parse(&text).map_err(|error_message| format!("{}: {}", path, error_message))
+}
+
+pub fn parse(text: &str) -> Result<Policy, String> {
+    let tables: BTreeMap<String, GateArgs> = toml::from_str(crate::strip_bom(text)).map_err(|err| err.message().to_string())?;
+    let mut rules = tables.into_iter().map(|(dir, gate)| (components(Path::new(&dir)), dir, gate)).collect::<Vec<_>>();
+    rules.sort_by_key(|(components, _, _)| std::cmp::Reverse(components.len()));
+    Ok(Policy { rules })
+}
+
+/// The names of the directories and files in a path, without `.` and the root.
+fn components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Policy {
+    /// The directories and conditions of the rules.
+    pub fn rules(&self) -> impl Iterator<Item = (&str, &GateArgs)> {
+        self.rules.iter().map(|(_, dir, gate)| (dir.as_str(), gate))
+    }
+
+    /// The rule that applies to a video: that of the deepest directory that the video is in. A
+    /// directory matches wherever it is in the video's path, so `Movies/4K` applies to
+    /// `/srv/media/Movies/4K/a.mkv`, and `.` applies to every video.
+    pub fn rule(&self, path: &str) -> Option<(&str, &GateArgs)> {
+        let path_components = components(Path::new(path));
+        let dirs = path_components.split_last().map_or(&[][..], |(_, dirs)| dirs);
+        self.rules.iter()
+            .find(|(components, _, _)| components.is_empty() || dirs.windows(components.len()).any(|window| window == components.as_slice()))
+            .map(|(_, dir, gate)| (dir.as_str(), gate))
+    }
+}
+
+#[test]
+fn test_policy() {
+    let policy = parse("[\"Movies/4K\"]\nrequire = \"slow\"\nfilter = \"bit_depth == 10\"\n\n[Movies]\nrequire = \"medium\"\n\n[TV]\nat-most = \"slower\"\n").unwrap();
+    assert_eq!(policy.rule("/srv/Movies/4K/a.mkv").map(|(dir, gate)| (dir, gate.require.as_deref())), Some(("Movies/4K", Some("slow"))));
+    assert_eq!(policy.rule("Movies/HD/b.mkv").map(|(dir, _)| dir), Some("Movies"));
+    assert_eq!(policy.rule("TV/Show/c.mkv").map(|(_, gate)| gate.at_most.as_deref()), Some(Some("slower")));
+    assert!(policy.rule("Music/4K/d.mkv").is_none());
+    // the file name isn't a directory
+    assert!(policy.rule("Other/TV").is_none());
+    assert!(parse("[TV]\nrequires = \"slow\"\n").is_err());
+    assert!(parse("[\".\"]\nrequire = \"slow\"\n").unwrap().rule("a.mkv").is_some());
+}
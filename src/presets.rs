@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+use tracing::debug;
+
+use crate::{lines, params, parse_string};
+
+/// A preset's name and the encoder settings it implies.
+pub type Preset = (String, HashMap<String, String>);
+
+/// The built-in x265 presets, from the fastest to the slowest. They are parsed on the first
+/// call, and shared by all callers.
+pub fn x265() -> Arc<[Preset]> {
+    static X265: OnceLock<Arc<[Preset]>> = OnceLock::new();
+    X265.get_or_init(|| Arc::from(parse_x265())).clone()
+}
+
+fn parse_x265() -> Vec<Preset> {
+    // Preset configurations from: https://x265.readthedocs.io/en/master/presets.html
+    vec![
+        (
+            "ultrafast".to_string(),
+            parse_string("ctu=32 min-cu-size=16 bframes=3 b-adapt=0 rc-lookahead=5 lookahead-slices=8 scenecut=0 ref=1 limit-refs=0 me=dia merange=57 subme=0 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=1 recursion-skip=1 fast-intra=1 b-intra=0 sao=0 signhide=0 weightp=0 weightb=0 aq-mode=0 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
+        ),
+        (
+            "superfast".to_string(),
+            parse_string("ctu=32 min-cu-size=8 bframes=3 b-adapt=0 rc-lookahead=10 lookahead-slices=8 scenecut=40 ref=1 limit-refs=0 me=hex merange=57 subme=1 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=1 recursion-skip=1 fast-intra=1 b-intra=0 sao=0 signhide=1 weightp=0 weightb=0 aq-mode=0 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
+        ),
+        (
+            "veryfast".to_string(),
+            parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=0 rc-lookahead=15 lookahead-slices=8 scenecut=40 ref=2 limit-refs=3 me=hex merange=57 subme=1 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=1 recursion-skip=1 fast-intra=1 b-intra=0 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
+        ),
+        (
+            "faster".to_string(),
+            parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=0 rc-lookahead=15 lookahead-slices=8 scenecut=40 ref=2 limit-refs=3 me=hex merange=57 subme=2 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=1 recursion-skip=1 fast-intra=1 b-intra=0 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
+        ),
+        (
+            "fast".to_string(),
+            parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=0 rc-lookahead=15 lookahead-slices=8 scenecut=40 ref=3 limit-refs=3 me=hex merange=57 subme=2 rect=0 amp=0 limit-modes=0 max-merge=2 early-skip=0 recursion-skip=1 fast-intra=1 b-intra=0 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=2 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
+        ),
+        (
+            "medium".to_string(),
+            parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=2 rc-lookahead=20 lookahead-slices=8 scenecut=40 ref=3 limit-refs=1 me=hex merange=57 subme=2 rect=0 amp=0 limit-modes=0 max-merge=3 early-skip=1 recursion-skip=1 fast-intra=0 b-intra=1 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=3 rdoq-level=0 tu-intra=1 tu-inter=1 limit-tu=0"),
+        ),
+        (
+            // Note: these are not stable/unchanging. I saw a "slow" video with lookahead-slices=6. I'm not sure which version was used to encode it.
+            "slow".to_string(),
+            parse_string("ctu=64 min-cu-size=8 bframes=4 b-adapt=2 rc-lookahead=25 lookahead-slices=4 scenecut=40 ref=4 limit-refs=3 me=star merange=57 subme=3 rect=1 amp=0 limit-modes=1 max-merge=3 early-skip=0 recursion-skip=1 fast-intra=0 b-intra=0 sao=1 signhide=1 weightp=1 weightb=0 aq-mode=2 cuTree=1 rdLevel=4 rdoq-level=2 tu-intra=1 tu-inter=1 limit-tu=0"),
+        ),
+        (
+            "slower".to_string(),
+            parse_string("ctu=64 min-cu-size=8 bframes=8 b-adapt=2 rc-lookahead=40 lookahead-slices=1 scenecut=40 ref=5 limit-refs=1 me=star merange=57 subme=4 rect=1 amp=1 limit-modes=1 max-merge=4 early-skip=0 recursion-skip=1 fast-intra=0 b-intra=1 sao=1 signhide=1 weightp=1 weightb=1 aq-mode=2 cuTree=1 rdLevel=6 rdoq-level=2 tu-intra=3 tu-inter=3 limit-tu=4"),
+        ),
+        (
+            "veryslow".to_string(),
+            parse_string("ctu=64 min-cu-size=8 bframes=8 b-adapt=2 rc-lookahead=40 lookahead-slices=1 scenecut=40 ref=5 limit-refs=0 me=star merange=57 subme=4 rect=1 amp=1 limit-modes=0 max-merge=5 early-skip=0 recursion-skip=1 fast-intra=0 b-intra=1 sao=1 signhide=1 weightp=1 weightb=1 aq-mode=2 cuTree=1 rdLevel=6 rdoq-level=2 tu-intra=3 tu-inter=3 limit-tu=0"),
+        ),
+        (
+            "placebo".to_string(),
+            parse_string("ctu=64 min-cu-size=8 bframes=8 b-adapt=2 rc-lookahead=60 lookahead-slices=1 scenecut=40 ref=5 limit-refs=0 me=star merange=92 subme=5 rect=1 amp=1 limit-modes=0 max-merge=5 early-skip=0 recursion-skip=0 fast-intra=0 b-intra=1 sao=1 signhide=1 weightp=1 weightb=1 aq-mode=2 cuTree=1 rdLevel=6 rdoq-level=2 tu-intra=4 tu-inter=4 limit-tu=0"),
+        ),
+    ]
+}
+
+/// Loads presets from a file with one preset per line, from the fastest to the slowest, in the
+/// form `name key=value key=value ...`. Blank lines and lines starting with `#` are ignored.
+pub fn load(path: &str) -> Result<Vec<Preset>, String> {
+    debug!("Reading the presets from {}", path);
+    let text = std::fs::read_to_string(path).map_err(|err| format!("Failed to read presets file {}: {}", path, err))?;
+    parse(&text).map_err(|err| format!("Invalid presets file {}: {}", path, err))
+}
+
+/// Parses the presets file format described in [`load`].
+pub fn parse(text: &str) -> Result<Vec<Preset>, String> {
+    let mut presets: Vec<Preset> = Vec::new();
+    for (line_number, line) in lines(text).enumerate().map(|(index, line)| (index + 1, line.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, settings) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if let Some(token) = settings.split_whitespace().find(|token| !token.contains('=')) {
+            return Err(format!("line {}: expected key=value, found `{}`", line_number, token));
+        }
+        if settings.trim().is_empty() {
+            return Err(format!("line {}: preset {} has no parameters", line_number, name));
+        }
+        if presets.iter().any(|(existing, _)| existing == name) {
+            return Err(format!("line {}: preset {} is defined more than once", line_number, name));
+        }
+        presets.push((name.to_string(), parse_string(settings)));
+    }
+    if presets.is_empty() {
+        return Err("no presets defined".to_string());
+    }
+    Ok(presets)
+}
+
+/// Finds problems with a preset table that don't prevent it from being used, but probably make
+/// the results less reliable.
+pub fn validate(presets: &[Preset]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let all_params = presets.iter().flat_map(|(_, settings)| settings.keys()).collect::<std::collections::BTreeSet<_>>();
+    for param_name in &all_params {
+        if !params::is_preset_param(param_name) {
+            warnings.push(format!("parameter {} is not a known x265 preset parameter", param_name));
+        }
+    }
+    for (name, settings) in presets {
+        let missing = all_params.iter().filter(|param_name| !settings.contains_key(**param_name)).map(|param_name| param_name.as_str()).collect::<Vec<_>>();
+        if !missing.is_empty() {
+            warnings.push(format!("preset {} doesn't define {}, so it will match any value", name, missing.join(", ")));
+        }
+    }
+    warnings
+}
+
+#[test]
+fn test_parse_presets_file() {
+    let presets = parse("# comment\n\nfast ctu=32 ref=1\nslow ctu=64\n").unwrap();
+    assert_eq!(presets.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), ["fast", "slow"]);
+    assert_eq!(presets[0].1.get("ref"), Some(&"1".to_string()));
+    assert_eq!(validate(&presets), ["preset slow doesn't define ref, so it will match any value"]);
+    assert_eq!(parse("fast ctu=32\nfast ctu=64"), Err("line 2: preset fast is defined more than once".to_string()));
+    assert_eq!(parse("fast"), Err("line 1: preset fast has no parameters".to_string()));
+    assert_eq!(parse("\u{feff}# comment\r\nfast ctu=32\r\nfast ctu=64\r\n"), Err("line 3: preset fast is defined more than once".to_string()));
+    assert!(validate(&x265()).is_empty());
+    assert!(Arc::ptr_eq(&x265(), &x265()));
+}
@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::matcher;
+
+/// Determines the preset of `mediainfo` output, x265 settings or an encoder command line.
+/// Raises ValueError if there is no single match.
+#[pyfunction]
+fn determine(settings: &str) -> PyResult<String> {
+    matcher::determine(settings, &crate::presets::x265()).map_err(PyValueError::new_err)
+}
+
+/// Parses `mediainfo` output, x265 settings or an encoder command line into the settings that
+/// are matched against the presets, with normalized names like `rdLevel`.
+#[pyfunction]
+fn parse(settings: &str) -> PyResult<HashMap<String, String>> {
+    matcher::normalized_settings(settings, &crate::presets::x265()).map_err(PyValueError::new_err)
+}
+
+/// The built-in x265 presets as (name, settings) pairs, from the fastest to the slowest.
+#[pyfunction]
+fn presets() -> Vec<(String, HashMap<String, String>)> {
+    crate::presets::x265().to_vec()
+}
+
+/// Python bindings: `import determine_preset`.
+#[pymodule]
+#[pyo3(name = "determine_preset")]
+fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(determine, module)?)?;
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(presets, module)?)?;
+    Ok(())
+}
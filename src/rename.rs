@@ -0,0 +1,96 @@
+use std::{collections::HashMap, fs, io, path::{Path, PathBuf}};
+
+/// The placeholders of a rename template, besides the file's `stem` and `ext`.
+pub const PLACEHOLDERS: &[&str] = &["stem", "ext", "preset", "encoder", "bit_depth", "width", "height"];
+
+/// Fills in the `{placeholder}`s of a template, like `{stem} [x265 {preset}]{ext}`, from
+/// `values`. An error names a placeholder that isn't one of `PLACEHOLDERS`, or one without a
+/// value.
+pub fn render(template: &str, values: &HashMap<&str, String>) -> Result<String, String> {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or_else(|| format!("The rename template has a `{{` without a `}}`: {}", template))? + start;
+        let name = &rest[start + 1..end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!("Unknown placeholder {{{}}} in the rename template. Known placeholders: {}", name, PLACEHOLDERS.join(", ")));
+        }
+        output.push_str(values.get(name).ok_or_else(|| format!("The video has no {}", name))?);
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Checks that a template only has known placeholders, and names a file that keeps the video's
+/// `{stem}`, so that videos with the same preset aren't all given the same name.
+pub fn check(template: &str) -> Result<(), String> {
+    let values = PLACEHOLDERS.iter().map(|name| (*name, "x".to_string())).collect();
+    let name = render(template, &values)?;
+    if name.contains(['/', '\\']) {
+        return Err(format!("The rename template must give a file name, without directories: {}", template));
+    }
+    if !template.contains("{stem}") {
+        return Err(format!("The rename template must have {{stem}}, so that the videos keep different names: {}", template));
+    }
+    Ok(())
+}
+
+/// Renames a file, unless a file already has the new name, in which case the error is
+/// `AlreadyExists`. The file is hard linked to the new name and then unlinked from the old one,
+/// as a rename would replace an existing file. On file systems without hard links, it's
+/// renamed after checking that the new name is free.
+pub fn rename_new(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::hard_link(from, to) {
+        Ok(()) => fs::remove_file(from),
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Err(err),
+        Err(_) if to.exists() => Err(io::ErrorKind::AlreadyExists.into()),
+        Err(_) => fs::rename(from, to),
+    }
+}
+
+/// The path that a video is renamed to, in the same directory. `values` are completed with the
+/// video's `stem`, and its `ext`ension including the dot, or empty. A video whose name already
+/// has the template's text around its stem, as it does after being renamed, keeps its path, so
+/// renaming again doesn't tag it twice.
+pub fn new_path(path: &str, template: &str, mut values: HashMap<&str, String>) -> Result<PathBuf, String> {
+    let path = Path::new(path);
+    let file_name = path.file_name().ok_or_else(|| format!("{} has no file name", path.display()))?.to_string_lossy();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    values.insert("ext", path.extension().map_or(String::new(), |ext| format!(".{}", ext.to_string_lossy())));
+    values.insert("stem", "\0".to_string());
+    if let Some((before, after)) = render(template, &values)?.split_once('\0') {
+        if file_name.len() > before.len() + after.len() && file_name.starts_with(before) && file_name.ends_with(after) {
+            return Ok(path.to_path_buf());
+        }
+    }
+    values.insert("stem", stem);
+    Ok(path.with_file_name(render(template, &values)?))
+}
+
+#[test]
+fn test_rename() {
+    let values = HashMap::from([("preset", "slow".to_string())]);
+    let path = new_path("/videos/movie.mkv", "{stem} [x265 {preset}]{ext}", values.clone()).unwrap();
+    assert_eq!(path, Path::new("/videos/movie [x265 slow].mkv"));
+    assert_eq!(new_path("/videos/movie [x265 slow].mkv", "{stem} [x265 {preset}]{ext}", values.clone()).unwrap(), Path::new("/videos/movie [x265 slow].mkv"));
+    assert_eq!(new_path("movie", "{stem}.{preset}{ext}", values.clone()).unwrap(), Path::new("movie.slow"));
+    assert!(new_path("movie.mkv", "{stem} {bit_depth}bit{ext}", values).is_err());
+    assert!(check("{stem} {crf}{ext}").is_err());
+    assert!(check("{stem} {preset").is_err());
+    assert!(check("x265/{stem}{ext}").is_err());
+    assert!(check("{stem} [{encoder} {preset} {bit_depth}bit]{ext}").is_ok());
+    assert!(check("{preset}{ext}").is_err());
+    let dir = std::env::temp_dir().join(format!("determine-preset-test-rename-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (a, b, c) = (dir.join("a.mkv"), dir.join("b.mkv"), dir.join("c.mkv"));
+    fs::write(&a, "a").unwrap();
+    fs::write(&b, "b").unwrap();
+    let replaced = rename_new(&a, &b).map_err(|err| err.kind());
+    let renamed = rename_new(&a, &c).map_err(|err| err.kind());
+    let contents = (fs::read_to_string(&b).unwrap(), fs::read_to_string(&c).unwrap(), a.exists());
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!((replaced, renamed), (Err(io::ErrorKind::AlreadyExists), Ok(())));
+    assert_eq!(contents, ("b".to_string(), "a".to_string(), false));
+}
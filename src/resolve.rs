@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use crate::{params, presets::Preset};
+
+/// The preset x265 uses when none is given.
+const DEFAULT_PRESET: &str = "medium";
+
+/// Works out the settings an x265 or ffmpeg (libx265) command line encodes with, the way x265
+/// does: start from the preset, apply the tune, then apply the explicit options, regardless of
+/// the order they were given in. The result uses the preset table's parameter names where there
+/// are any, so it can be matched against the presets.
+pub fn resolve(args: &[String], presets: &[Preset]) -> Result<HashMap<String, String>, String> {
+    let args = match args {
+        // a whole command line passed as one argument
+        [command_line] => split_command_line(command_line)?,
+        _ => args.to_vec(),
+    };
+    match args.first() {
+        Some(program) if program_name(program) == "ffmpeg" => resolve_ffmpeg(&args[1..], presets),
+        Some(program) if program_name(program) == "x265" => resolve_x265(&args[1..], presets),
+        _ => resolve_x265(&args, presets),
+    }
+}
+
+fn resolve_x265(args: &[String], presets: &[Preset]) -> Result<HashMap<String, String>, String> {
+    let mut args = args.iter().map(String::as_str).peekable();
+
+    let mut preset_name = DEFAULT_PRESET;
+    let mut tune = None;
+    let mut options = Vec::new();
+    while let Some(arg) = args.next() {
+        let Some(option) = arg.strip_prefix("--").or_else(|| arg.strip_prefix('-').filter(|option| option.len() == 1)) else {
+            continue; // an input or output file
+        };
+        let (option, value) = match option.split_once('=') {
+            Some((option, value)) => (option, Some(value)),
+            // options that aren't switches take the next argument as their value
+            None if !option.starts_with("no-") && !params::find(option).is_some_and(|param| param.flag) && args.peek().is_some_and(|next| !next.starts_with('-')) => (option, args.next()),
+            None => (option, None),
+        };
+        match (option, value) {
+            ("preset" | "p", Some(value)) => preset_name = value,
+            ("tune" | "t", Some(value)) => tune = Some(value),
+            ("input" | "output" | "o", _) => (),
+            ("preset" | "p" | "tune" | "t", None) => return Err(format!("--{} needs a value", option)),
+            (option, value) => options.push((option, value)),
+        }
+    }
+
+    apply_options(preset_name, tune, options, presets)
+}
+
+/// Resolves the options of an ffmpeg command line that encodes with libx265: `-preset`,
+/// `-tune`, `-crf` and the parameters in `-x265-params`. Other options are ignored.
+fn resolve_ffmpeg(args: &[String], presets: &[Preset]) -> Result<HashMap<String, String>, String> {
+    let mut preset_name = DEFAULT_PRESET;
+    let mut tune = None;
+    let mut options = Vec::new();
+    let mut args = args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        // options may be qualified by a stream specifier, like `-preset:v`
+        let option = arg.split(':').next().unwrap_or(arg);
+        match option {
+            "-preset" | "-tune" | "-crf" | "-x265-params" => {
+                let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                match option {
+                    "-preset" => preset_name = value,
+                    "-tune" => tune = Some(value),
+                    "-crf" => options.push(("crf", Some(value))),
+                    _ => options.extend(value.split(':').filter(|pair| !pair.is_empty()).map(|pair| match pair.split_once('=') {
+                        // x265 reads `no-rect=1` as disabling rect
+                        Some((option, "1")) if option.starts_with("no-") => (option, None),
+                        Some((option, value)) => (option, Some(value)),
+                        None => (pair, None),
+                    })),
+                }
+            }
+            _ => (),
+        }
+    }
+    apply_options(preset_name, tune, options, presets)
+}
+
+/// Starts from the preset's settings, then applies the tune and the options, which are option
+/// names without the leading dashes, with their values. Switches have no value.
+fn apply_options(preset_name: &str, tune: Option<&str>, options: Vec<(&str, Option<&str>)>, presets: &[Preset]) -> Result<HashMap<String, String>, String> {
+    // x265 also takes the presets by number, from 0 for the fastest
+    let mut settings = presets.iter()
+        .find(|(name, _)| name == preset_name)
+        .or_else(|| preset_name.parse::<usize>().ok().and_then(|speed| presets.get(speed)))
+        .map(|(_, settings)| settings.clone())
+        .ok_or_else(|| format!("Unknown preset: {}", preset_name))?;
+    if let Some(tune) = tune {
+        apply_tune(&mut settings, tune)?;
+    }
+    for (option, value) in options {
+        let (option, value) = match (option.strip_prefix("no-"), value) {
+            (Some(option), None) => (option, "0"),
+            (_, Some(value)) => (option, value),
+            (_, None) => (option, "1"),
+        };
+        let (name, value) = params::normalize(option, value);
+        settings.insert(name, value);
+    }
+    Ok(settings)
+}
+
+/// The name of a program without its directory or `.exe` extension.
+pub fn program_name(program: &str) -> &str {
+    let program = program.rsplit(['/', '\\']).next().unwrap_or(program);
+    program.strip_suffix(".exe").unwrap_or(program)
+}
+
+/// Finds an x265 or ffmpeg command line in some text, like a job script, joining lines that
+/// end with a backslash. Returns `None` if the text doesn't contain an encoder invocation with
+/// options.
+pub fn find_command_line(text: &str) -> Option<String> {
+    let mut lines = crate::lines(text);
+    while let Some(line) = lines.next() {
+        let line = line.trim_start().trim_start_matches("$ ");
+        let Some(program) = line.split_whitespace().next() else { continue };
+        if !matches!(program_name(program), "x265" | "ffmpeg") || !line.contains(" -") {
+            continue;
+        }
+        let mut command_line = line.to_string();
+        while command_line.ends_with('\\') {
+            command_line.pop();
+            command_line.push_str(lines.next().unwrap_or(""));
+        }
+        return Some(command_line);
+    }
+    None
+}
+
+/// Applies the changes a `--tune` option makes on top of the preset, as in x265's
+/// `x265_param_default_preset`.
+pub fn apply_tune(settings: &mut HashMap<String, String>, tune: &str) -> Result<(), String> {
+    let changes: &[(&str, &str)] = match tune {
+        "psnr" => &[("aq-strength", "0.00"), ("psy-rd", "0.00"), ("psy-rdoq", "0.00")],
+        "ssim" => &[("aq-mode", "2"), ("psy-rd", "0.00"), ("psy-rdoq", "0.00")],
+        "fastdecode" | "fast-decode" => &[("deblock", "0"), ("sao", "0"), ("weightp", "0"), ("weightb", "0"), ("b-intra", "0")],
+        "zerolatency" | "zero-latency" => &[("b-adapt", "0"), ("bframes", "0"), ("rc-lookahead", "0"), ("scenecut", "0"), ("cuTree", "0"), ("frame-threads", "1")],
+        "grain" => &[
+            ("ipratio", "1.10"), ("pbratio", "1.00"), ("cuTree", "0"), ("aq-mode", "0"), ("qpstep", "1"),
+            ("rdoq-level", "1"), ("psy-rd", "4.00"), ("psy-rdoq", "10.00"), ("sao", "0"), ("const-vbv", "1"),
+        ],
+        "animation" => {
+            let bframes = settings.get("bframes").and_then(|bframes| bframes.parse::<u32>().ok()).unwrap_or(4);
+            settings.insert("bframes".to_string(), (bframes + 2).min(16).to_string());
+            &[("psy-rd", "0.40"), ("aq-strength", "0.40"), ("deblock", "1:1")]
+        }
+        _ => return Err(format!("Unknown tune: {}", tune)),
+    };
+    for (name, value) in changes {
+        settings.insert(name.to_string(), value.to_string());
+    }
+    Ok(())
+}
+
+/// Splits a command line into arguments like a POSIX shell would, handling quotes and
+/// backslash escapes, but not expansions.
+pub fn split_command_line(command_line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut in_arg = false;
+    let mut chars = command_line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err("Unterminated ' quote in command line".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => arg.push(chars.next().ok_or("Unterminated \" quote in command line")?),
+                        Some(c) => arg.push(c),
+                        None => return Err("Unterminated \" quote in command line".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                match chars.next() {
+                    Some('\n') | None => (), // line continuation
+                    Some(c) => arg.push(c),
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            c => {
+                in_arg = true;
+                arg.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+    Ok(args)
+}
+
+#[test]
+fn test_resolve() {
+    let presets = crate::presets::x265();
+    let args = ["x265 --preset slow --tune grain --rc-lookahead=30 --no-rect --me 1 -o 'out file.hevc' in.y4m".to_string()];
+    let settings = resolve(&args, &presets).unwrap();
+    assert_eq!(settings["rc-lookahead"], "30");
+    assert_eq!(settings["rect"], "0");
+    assert_eq!(settings["me"], "hex");
+    assert_eq!(settings["cuTree"], "0"); // from the tune
+    assert_eq!(settings["ref"], "4"); // from the preset
+    assert_eq!(resolve(&["x265".to_string()], &presets).unwrap(), presets[5].1);
+    let args = ["ffmpeg -i in.mkv -c:v libx265 -preset:v veryslow -crf 18 -x265-params rect=0:no-amp=1:rd=4 out.mkv".to_string()];
+    let settings = resolve(&args, &presets).unwrap();
+    assert_eq!((settings["rect"].as_str(), settings["amp"].as_str(), settings["rdLevel"].as_str()), ("0", "0", "4"));
+    assert_eq!(settings["crf"], "18");
+    assert_eq!(find_command_line("#!/bin/sh\nset -e\n/usr/bin/x265 --preset slow \\\n  --crf 20 in.y4m\n"), Some("/usr/bin/x265 --preset slow   --crf 20 in.y4m".to_string()));
+    assert_eq!(find_command_line("Encoding settings : ctu=64"), None);
+    assert_eq!(split_command_line(r#"a "b c" d\ e 'f"g'"#).unwrap(), ["a", "b c", "d e", "f\"g"]);
+}
@@ -0,0 +1,271 @@
+use serde_json::{json, Value};
+
+/// The version of the JSON output, in the `schema_version` field. It is increased when a field
+/// is removed or changes meaning; new fields can be added without increasing it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The JSON Schema of the JSON output: the result of `determine --format json` (including
+/// errors) and of `explain --format json`.
+pub fn json_schema() -> Value {
+    let mut schema = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "determine-preset output",
+        "oneOf": [{ "$ref": "#/$defs/determination" }, { "$ref": "#/$defs/error" }, { "$ref": "#/$defs/preset" }],
+        "$defs": {
+            "schema_version": { "const": SCHEMA_VERSION },
+            "error_detail": {
+                "type": "object",
+                "properties": {
+                    "code": { "enum": ["ambiguous", "no_match", "insufficient_input", "not_derivable", "io_error", "rejected", "exec_forbidden", "error"] },
+                    "message": { "type": "string" },
+                },
+                "required": ["code", "message"],
+            },
+            "determination": {
+                "description": "The result of `determine --format json`.",
+                "type": "object",
+                "properties": {
+                    "schema_version": { "$ref": "#/$defs/schema_version" },
+                    "status": { "enum": ["match", "ambiguous", "no-match", "insufficient-input", "not-derivable"] },
+                    "preset": {
+                        "description": "The matching or closest preset, or its position with `--numeric`.",
+                        "type": ["string", "null"],
+                    },
+                    "confidence": {
+                        "description": "The percentage of the parameters that match the preset.",
+                        "type": "integer", "minimum": 0, "maximum": 100,
+                    },
+                    "encoder": { "type": "string" },
+                    "matches": { "type": "array", "items": { "type": "string" } },
+                    "handbrake_preset": {
+                        "description": "The HandBrake preset family likely used, like `HQ`, if HandBrake encoded the video.",
+                        "type": "string",
+                    },
+                    "rate_control": {
+                        "description": "How the bitrate was controlled, like `single-pass CRF 20.0` or `2-pass ABR 5000 kbps`, if the settings say.",
+                        "type": "string",
+                    },
+                    "profile": {
+                        "description": "The HEVC profile, level and tier, like `Main10@L5.1 High tier`, if the settings say.",
+                        "type": "string",
+                    },
+                    "gop": {
+                        "description": "The GOP structure, like `open GOP, keyint 250, min-keyint 25, scenecut 40, 4 B-frames (pyramid)`, if the settings say.",
+                        "type": "string",
+                    },
+                    "x265_era": {
+                        "description": "The x265 releases that could have written the settings, like `x265 3.4 or later, before 4.0`, from the parameters that later releases added.",
+                        "type": "string",
+                    },
+                    "bit_depth": { "type": "integer", "minimum": 8 },
+                    "chroma_subsampling": { "enum": ["4:0:0", "4:2:0", "4:2:2", "4:4:4"] },
+                    "dynamic_range": { "enum": ["SDR", "HDR10", "HLG"] },
+                    "color": {
+                        "description": "The color description and HDR metadata, with friendly names like `BT.2020` and `PQ`. Present with `dynamic_range`.",
+                        "type": "object",
+                        "properties": {
+                            "primaries": { "type": ["string", "null"] },
+                            "transfer": { "type": ["string", "null"] },
+                            "matrix": { "type": ["string", "null"] },
+                            "mastering_display": { "type": "boolean" },
+                            "max_cll": { "type": ["integer", "null"] },
+                            "max_fall": { "type": ["integer", "null"] },
+                        },
+                    },
+                    "dolby_vision": {
+                        "type": "object",
+                        "properties": {
+                            "profile": { "description": "Like `5`, `7` or `8.1`.", "type": ["string", "null"] },
+                            "layers": { "description": "Like `BL+RPU`.", "type": ["string", "null"] },
+                        },
+                    },
+                    "interlacing": {
+                        "description": "How an interlaced encode was coded. Absent if it's progressive.",
+                        "type": "object",
+                        "properties": {
+                            "field_order": { "enum": ["top field first", "bottom field first"] },
+                            "field_coding": { "description": "Whether x265 coded the frames as fields itself, or null if the settings don't say.", "type": ["boolean", "null"] },
+                        },
+                    },
+                    "quality_category": {
+                        "description": "A one-line classification, like `archival-grade (slow preset, CRF 18)`: `archival-grade`, `balanced`, `speed-focused`, or for streaming, the class of `latency`, and why.",
+                        "type": "string",
+                    },
+                    "latency": {
+                        "description": "Whether the encode was configured for `low-latency streaming`, `streaming` or `offline quality`, and why, like `offline quality (4 B-frames, 20 frames of lookahead, no VBV)`. The limits of a VBV cap are in `vbv`.",
+                        "type": "string",
+                    },
+                    "quality_params": {
+                        "description": "Parameters that affect the quality independently of the preset, with their x265 defaults.",
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/quality_param" },
+                    },
+                    "tag_hints": {
+                        "description": "The presets that the container's tags name. Tags can say anything, so these are unverified hints, unlike `preset`.",
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": { "tag": { "type": "string" }, "preset": { "type": "string" } },
+                            "required": ["tag", "preset"],
+                        },
+                    },
+                    "raw_settings": {
+                        "description": "The settings as the input has them: mediainfo's `Encoding settings`, an encoder command line, or a `key=value` settings string. Absent if the input has none.",
+                        "type": "string",
+                    },
+                    "settings": {
+                        "description": "The settings that were matched, normalized to the preset table's names and values, like `rdLevel` for `rd` and `rect: \"1\"` for `rect`.",
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                    },
+                    "unknown_params": {
+                        "description": "The parameters of `settings` that no preset sets and that aren't x265 options, which are ignored. Present if there are any.",
+                        "type": "array",
+                        "items": { "type": "string" },
+                    },
+                    "coverage": {
+                        "description": "How many of the matching preset's parameters the input has, as a preset matches however few it has. Present if a single preset matches.",
+                        "$ref": "#/$defs/coverage",
+                    },
+                    "comparison": {
+                        "description": "The comparison with the closest presets that the text output shows as a table, with the same rows at the same verbosity. Present if no preset matches.",
+                        "$ref": "#/$defs/comparison",
+                    },
+                    "speed_estimate": {
+                        "description": "Where the settings fall between the presets, like `custom settings, roughly between slow and slower`. Present if no preset matches and the settings tell the presets apart.",
+                        "type": "string",
+                    },
+                    "error": {
+                        "description": "Why there is no single match, unless the input passed `--require` or `--filter`.",
+                        "$ref": "#/$defs/error_detail",
+                    },
+                },
+                "required": ["schema_version", "status", "preset", "confidence", "encoder", "matches"],
+            },
+            "error": {
+                "description": "An error that prevented determining the preset, like an unreadable input.",
+                "type": "object",
+                "properties": {
+                    "schema_version": { "$ref": "#/$defs/schema_version" },
+                    "status": { "const": "error" },
+                    "error": { "$ref": "#/$defs/error_detail" },
+                },
+                "required": ["schema_version", "status", "error"],
+            },
+            "preset": {
+                "description": "The result of `explain --format json`.",
+                "type": "object",
+                "properties": {
+                    "schema_version": { "$ref": "#/$defs/schema_version" },
+                    "preset": { "type": "string" },
+                    "parameters": { "type": "object", "additionalProperties": { "type": ["integer", "string"] } },
+                },
+                "required": ["schema_version", "preset", "parameters"],
+            },
+        },
+    });
+    // separate `json!`s, as the macro's recursion limit is too low for the whole schema
+    schema["$defs"]["comparison"] = comparison_schema();
+    schema["$defs"]["coverage"] = coverage_schema();
+    schema["$defs"]["quality_param"] = quality_param_schema();
+    schema["$defs"]["determination"]["properties"]["vbv"] = vbv_schema();
+    schema
+}
+
+fn vbv_schema() -> Value {
+    json!({
+        "description": "The video buffering verifier limits, which constrain the bitrate for devices or streaming. Absent if the bitrate isn't capped.",
+        "type": "object",
+        "properties": {
+            "maxrate": { "description": "`vbv-maxrate`, in kbps.", "type": "integer", "minimum": 1 },
+            "bufsize": { "description": "`vbv-bufsize`, in kbits, or null if the settings don't say.", "type": ["integer", "null"] },
+            "strict_cbr": { "type": "boolean" },
+        },
+        "required": ["maxrate", "bufsize", "strict_cbr"],
+    })
+}
+
+fn quality_param_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "name": { "enum": ["psy-rd", "psy-rdoq", "aq-strength", "qcomp", "deblock"] },
+            "value": { "type": "string" },
+            "default": { "type": "string" },
+            "is_default": { "type": "boolean" },
+        },
+        "required": ["name", "value", "default", "is_default"],
+    })
+}
+
+fn coverage_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "present": { "type": "integer", "minimum": 0 },
+            "total": { "type": "integer", "minimum": 0 },
+            "absent": { "description": "The preset's parameters that the input doesn't have.", "type": "array", "items": { "type": "string" } },
+        },
+        "required": ["present", "total", "absent"],
+    })
+}
+
+fn comparison_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "presets": {
+                "description": "The closest presets, from the closest, with the number of the displayed parameters they match and differ in.",
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "preset": { "type": "string" },
+                        "confidence": { "type": "integer", "minimum": 0, "maximum": 100 },
+                        "matched": { "type": "integer", "minimum": 0 },
+                        "differed": { "type": "integer", "minimum": 0 },
+                        "changes_needed": {
+                            "description": "The number of all the input's parameters that would have to change for the preset to match exactly.",
+                            "type": "integer",
+                            "minimum": 0,
+                        },
+                    },
+                    "required": ["preset", "confidence", "matched", "differed", "changes_needed"],
+                },
+            },
+            "rows": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "param": { "type": "string" },
+                        "doc_url": { "description": "The parameter's entry in the x265 documentation, or null if it isn't a preset parameter.", "type": ["string", "null"] },
+                        "input": { "description": "Null if the input doesn't have the parameter.", "type": ["string", "null"] },
+                        "presets": {
+                            "description": "The value of each of the `presets`, in the same order: null if the preset doesn't set the parameter.",
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": { "value": { "type": ["string", "null"] }, "matches": { "type": "boolean" } },
+                                "required": ["value", "matches"],
+                            },
+                        },
+                    },
+                    "required": ["param", "doc_url", "input", "presets"],
+                },
+            },
+        },
+        "required": ["presets", "rows"],
+    })
+}
+
+#[test]
+fn test_json_schema() {
+    let schema = json_schema();
+    for (name, definition) in schema["$defs"].as_object().unwrap() {
+        // every required field is described
+        for field in definition["required"].as_array().into_iter().flatten() {
+            assert!(definition["properties"].get(field.as_str().unwrap()).is_some(), "{} in {}", field, name);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{strip_bom, Determination};
+
+/// A sample of a regression corpus: a file whose first line is a header like `expected: slow`,
+/// followed by the input, i.e. `mediainfo` output, x265 settings or an encoder command line.
+/// The expected result is the name of the preset that should match, or a status of
+/// `--format json` for the inputs without a single match, like `ambiguous` or `no-match`.
+pub fn parse_sample(text: &str) -> Result<(&str, &str), String> {
+    let text = strip_bom(text);
+    let (header, input) = text.split_once('\n').unwrap_or((text, ""));
+    let expected = header.trim_end_matches('\r').strip_prefix("expected:").map(str::trim).filter(|expected| !expected.is_empty());
+    let expected = expected.ok_or_else(|| format!("expected a header like `expected: slow`, found `{}`", header.trim()))?;
+    Ok((expected, input))
+}
+
+/// Whether a determination is the expected result of a sample. If it isn't, the error describes
+/// the determination.
+pub fn check(expected: &str, determination: &Determination) -> Result<(), String> {
+    let passed = match determination {
+        Determination::Match(preset_name) => preset_name == expected,
+        determination => determination.status() == expected,
+    };
+    if passed {
+        return Ok(());
+    }
+    Err(match determination {
+        Determination::Match(preset_name) => preset_name.clone(),
+        Determination::Ambiguous(matching_presets) => format!("ambiguous ({})", matching_presets.join(", ")),
+        determination => determination.status().to_string(),
+    })
+}
+
+#[test]
+fn test_selftest() {
+    assert_eq!(parse_sample("\u{feff}expected: slow\r\nctu=64\n"), Ok(("slow", "ctu=64\n")));
+    assert!(parse_sample("ctu=64\n").is_err());
+    assert_eq!(check("slow", &Determination::Match("slow".to_string())), Ok(()));
+    assert_eq!(check("no-match", &Determination::NoMatch), Ok(()));
+    let ambiguous = Determination::Ambiguous(vec!["slow".to_string(), "slower".to_string()]);
+    assert_eq!(check("slow", &ambiguous), Err("ambiguous (slow, slower)".to_string()));
+}
@@ -0,0 +1,157 @@
+use std::cmp::max;
+
+/// The visual style used to draw the comparison table.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum TableStyle {
+    /// Columns separated by `|` with `-` rules.
+    #[default]
+    Ascii,
+    /// Box-drawing characters, for terminals that support them.
+    Unicode,
+    /// No rules and narrow column gaps, for narrow panes.
+    Compact,
+    /// A GitHub-flavored Markdown table, for pasting into issues. Never colored.
+    Github,
+}
+
+/// How a cell is highlighted when colors are enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    None,
+    Bold,
+    Match,
+}
+
+pub struct Cell {
+    pub text: String,
+    pub highlight: Highlight,
+    /// A URL the text links to, in terminals that support OSC 8 hyperlinks.
+    pub link: Option<String>,
+}
+
+impl Cell {
+    pub fn new(text: impl Into<String>) -> Cell {
+        Cell { text: text.into(), highlight: Highlight::None, link: None }
+    }
+
+    pub fn highlighted(text: impl Into<String>, highlight: Highlight) -> Cell {
+        Cell { text: text.into(), highlight, link: None }
+    }
+
+    fn width(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// The cell's text, with the escape codes of its highlight if `use_color`, which the caller
+    /// decides once for the whole output.
+    pub fn render(&self, use_color: bool, use_links: bool) -> String {
+        let text = match self.highlight {
+            _ if !use_color => self.text.clone(),
+            Highlight::None => self.text.clone(),
+            Highlight::Bold => format!("\x1b[1m{}\x1b[0m", self.text),
+            Highlight::Match => format!("\x1b[32m{}\x1b[0m", self.text),
+        };
+        match &self.link {
+            Some(url) if use_links => format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text),
+            _ => text,
+        }
+    }
+}
+
+/// A table with a header row, body rows and summary (footer) rows. All rows must have the
+/// same number of cells.
+#[derive(Default)]
+pub struct Table {
+    pub header: Vec<Cell>,
+    pub rows: Vec<Vec<Cell>>,
+    pub footer: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn render(&self, style: TableStyle, use_color: bool, use_links: bool) -> String {
+        let use_color = use_color && style != TableStyle::Github;
+        let use_links = use_links && style != TableStyle::Github;
+        let column_count = self.header.len();
+        let widths = (0..column_count).map(|column| {
+            self.all_rows().map(|row| row[column].width()).fold(0, max)
+        }).collect::<Vec<_>>();
+
+        let (separator, rule_char, rule_junction) = match style {
+            TableStyle::Ascii => (" | ", '-', "-"),
+            TableStyle::Unicode => (" │ ", '─', "─┼─"),
+            TableStyle::Compact => ("  ", ' ', ""),
+            TableStyle::Github => (" | ", '-', " | "),
+        };
+
+        let render_row = |row: &[Cell]| {
+            let mut line = String::with_capacity(200);
+            if style == TableStyle::Github {
+                line.push_str("| ");
+            }
+            for (column, cell) in row.iter().enumerate() {
+                if column > 0 {
+                    line.push_str(separator);
+                }
+                line.push_str(&cell.render(use_color, use_links));
+                // pad using the plain text width, so escape sequences don't break the alignment
+                line.push_str(&" ".repeat(widths[column] - cell.width()));
+            }
+            if style == TableStyle::Github {
+                line.push_str(" |");
+            }
+            line.push('\n');
+            line
+        };
+        let rule = || {
+            let mut line = match style {
+                TableStyle::Ascii => widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>().join("---"),
+                TableStyle::Github => format!("| {} |", widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>().join(rule_junction)),
+                _ => widths.iter().map(|width| rule_char.to_string().repeat(*width)).collect::<Vec<_>>().join(rule_junction),
+            };
+            line.push('\n');
+            line
+        };
+
+        let mut table = String::with_capacity(10_000);
+        table.push_str(&render_row(&self.header));
+        if style != TableStyle::Compact {
+            table.push_str(&rule());
+        }
+        for row in &self.rows {
+            table.push_str(&render_row(row));
+        }
+        if !self.footer.is_empty() && matches!(style, TableStyle::Ascii | TableStyle::Unicode) {
+            table.push_str(&rule());
+        }
+        for row in &self.footer {
+            table.push_str(&render_row(row));
+        }
+        table
+    }
+
+    /// Removes columns from the right until the rendered table is at most `width` characters
+    /// wide, keeping at least `min_columns` columns.
+    pub fn fit_to_width(&mut self, style: TableStyle, width: usize, min_columns: usize) {
+        while self.header.len() > min_columns && self.rendered_width(style) > width {
+            for row in std::iter::once(&mut self.header).chain(self.rows.iter_mut()).chain(self.footer.iter_mut()) {
+                row.pop();
+            }
+        }
+    }
+
+    fn rendered_width(&self, style: TableStyle) -> usize {
+        self.render(style, false, false).lines().map(|line| line.chars().count()).max().unwrap_or(0)
+    }
+
+    fn all_rows(&self) -> impl Iterator<Item = &Vec<Cell>> {
+        std::iter::once(&self.header).chain(self.rows.iter()).chain(self.footer.iter())
+    }
+}
+
+#[test]
+fn test_render_cell() {
+    let cell = Cell::highlighted("slow", Highlight::Match);
+    assert_eq!(cell.render(true, false), "\x1b[32mslow\x1b[0m");
+    assert_eq!(cell.render(false, false), "slow");
+    assert_eq!(Cell::highlighted("slow", Highlight::Bold).render(true, false), "\x1b[1mslow\x1b[0m");
+}
@@ -0,0 +1,220 @@
+use std::{
+    env, fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+use crate::presets;
+
+/// The GitHub repository that releases are published in.
+const REPOSITORY: &str = "lefth/determine-preset";
+
+/// The name of the release asset with the preset table, in the format of `--presets-file`.
+const PRESETS_ASSET: &str = "presets.txt";
+
+/// Ends the name of the release asset with the SHA-256 checksum of another, like
+/// `presets.txt.sha256`, in the format of `sha256sum`.
+const CHECKSUM_SUFFIX: &str = ".sha256";
+
+/// A release's version and downloads, from the GitHub API.
+#[derive(Debug, PartialEq)]
+pub struct Release {
+    pub version: String,
+    /// The names and URLs of the release's files.
+    pub assets: Vec<(String, String)>,
+}
+
+/// The latest release of `REPOSITORY`.
+pub fn latest_release() -> Result<Release, String> {
+    let url = format!("https://api.github.com/repos/{}/releases/latest", REPOSITORY);
+    debug!("Fetching {}", url);
+    let text = get(&url)?.body_mut().read_to_string().map_err(|err| format!("Could not read the latest release from {}: {}", url, err))?;
+    parse_release(&text)
+}
+
+fn get(url: &str) -> Result<ureq::http::Response<ureq::Body>, String> {
+    ureq::get(url)
+        .header("User-Agent", concat!("determine-preset/", env!("CARGO_PKG_VERSION")))
+        .header("Accept", "application/vnd.github+json")
+        .call()
+        .map_err(|err| format!("Could not fetch {}: {}", url, err))
+}
+
+/// Reads a release from the JSON the GitHub API describes it with.
+pub fn parse_release(text: &str) -> Result<Release, String> {
+    let release: Value = serde_json::from_str(text).map_err(|err| format!("The release isn't valid JSON: {}", err))?;
+    let version = release["tag_name"].as_str().ok_or("The release has no tag")?;
+    let assets = release["assets"].as_array().into_iter().flatten()
+        .filter_map(|asset| Some((asset["name"].as_str()?.to_string(), asset["browser_download_url"].as_str()?.to_string())))
+        .collect();
+    Ok(Release { version: version.trim_start_matches('v').to_string(), assets })
+}
+
+/// Whether `version` is newer than `current` by semantic versioning, so a release is newer than
+/// its pre-releases. Versions that aren't semantic versions are never newer.
+pub fn is_newer(version: &str, current: &str) -> bool {
+    match (semver::Version::parse(version), semver::Version::parse(current)) {
+        (Ok(version), Ok(current)) => version > current,
+        _ => false,
+    }
+}
+
+/// The name of the release asset with the binary for this platform, like
+/// `determine-preset-x86_64-linux` or `determine-preset-x86_64-windows.exe`.
+pub fn binary_asset() -> String {
+    format!("determine-preset-{}-{}{}", env::consts::ARCH, env::consts::OS, env::consts::EXE_SUFFIX)
+}
+
+/// Replaces the running binary with the release's, and installs its preset table, if it has
+/// one. Each download is checked against the SHA-256 checksum the release publishes with it
+/// (see `CHECKSUM_SUFFIX`) before it's used. Describes what was updated, or why nothing was.
+pub fn update(release: &Release) -> Result<String, String> {
+    let current = env!("CARGO_PKG_VERSION");
+    if !is_newer(&release.version, current) {
+        return Ok(format!("determine-preset {} is the latest version\n", current));
+    }
+    let asset_name = binary_asset();
+    let url = asset_url(release, &asset_name).ok_or_else(|| format!("Release {} has no binary for this platform ({})", release.version, asset_name))?;
+    let exe = env::current_exe().map_err(|err| format!("Could not find the running binary: {}", err))?;
+    let download = exe.with_extension("download");
+    // both checksums are fetched first, so a release without them changes nothing
+    let binary_checksum = checksum(release, &asset_name)?;
+    let presets = asset_url(release, PRESETS_ASSET).map(|url| checksum(release, PRESETS_ASSET).map(|checksum| (url, checksum))).transpose()?;
+    download_to(url, &download)?;
+    verify(&download, &binary_checksum).and_then(|()| replace(&download, &exe)).inspect_err(|_| {
+        let _ = fs::remove_file(&download);
+    })?;
+    let mut report = format!("Updated determine-preset from {} to {}\n", current, release.version);
+    if let Some((url, presets_checksum)) = presets {
+        report.push_str(&install_presets(url, &presets_checksum)?);
+    }
+    Ok(report)
+}
+
+fn asset_url<'a>(release: &'a Release, asset_name: &str) -> Option<&'a str> {
+    release.assets.iter().find(|(name, _)| name == asset_name).map(|(_, url)| url.as_str())
+}
+
+/// The SHA-256 checksum of a release asset, in lowercase hex, from the release's checksum asset
+/// for it. Releases without one aren't installed, as their downloads can't be checked.
+fn checksum(release: &Release, asset_name: &str) -> Result<String, String> {
+    let checksum_name = format!("{}{}", asset_name, CHECKSUM_SUFFIX);
+    let url = asset_url(release, &checksum_name).ok_or_else(|| format!("Release {} has no {} to check {} against, so it wasn't installed", release.version, checksum_name, asset_name))?;
+    let text = get(url)?.body_mut().read_to_string().map_err(|err| format!("Could not download {}: {}", url, err))?;
+    parse_checksum(&text).ok_or_else(|| format!("{} isn't a SHA-256 checksum", checksum_name))
+}
+
+/// The checksum in the first word of a `sha256sum` line, like `e3b0c442...  presets.txt`.
+fn parse_checksum(text: &str) -> Option<String> {
+    let checksum = text.split_whitespace().next()?.to_ascii_lowercase();
+    (checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit())).then_some(checksum)
+}
+
+/// The SHA-256 checksum of the file at `path`, in lowercase hex.
+fn sha256(path: &Path) -> Result<String, String> {
+    let read_error = |err| format!("Could not read {}: {}", path.display(), err);
+    let mut file = fs::File::open(path).map_err(read_error)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).map_err(read_error)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Fails if the downloaded file doesn't have the checksum the release published for it.
+fn verify(download: &Path, checksum: &str) -> Result<(), String> {
+    let actual = sha256(download)?;
+    if actual != checksum {
+        return Err(format!("The download's SHA-256 checksum is {}, but the release published {}, so it wasn't installed", actual, checksum));
+    }
+    Ok(())
+}
+
+fn download_to(url: &str, path: &Path) -> Result<(), String> {
+    info!("Downloading {}", url);
+    let mut response = get(url)?;
+    let mut file = fs::File::create(path).map_err(|err| format!("Could not create {}: {}", path.display(), err))?;
+    std::io::copy(&mut response.body_mut().as_reader(), &mut file).map_err(|err| format!("Could not download {}: {}", url, err))?;
+    Ok(())
+}
+
+/// Moves the downloaded binary over the running one. The running one is moved aside first, as
+/// Windows doesn't allow replacing it.
+fn replace(download: &Path, exe: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(download, fs::Permissions::from_mode(0o755)).map_err(|err| format!("Could not make {} executable: {}", download.display(), err))?;
+    }
+    let old = exe.with_extension("old");
+    fs::rename(exe, &old).map_err(|err| format!("Could not replace {}: {}", exe.display(), err))?;
+    if let Err(err) = fs::rename(download, exe) {
+        let _ = fs::rename(&old, exe);
+        return Err(format!("Could not replace {}: {}", exe.display(), err));
+    }
+    // Windows keeps the running binary until it exits
+    let _ = fs::remove_file(&old);
+    Ok(())
+}
+
+/// The path the release's preset table is installed at:
+/// `$XDG_DATA_HOME/determine-preset/presets.txt`, or `~/.local/share/determine-preset/presets.txt`.
+pub fn presets_path() -> Option<PathBuf> {
+    let data_dir = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))?;
+    Some(data_dir.join("determine-preset").join(PRESETS_ASSET))
+}
+
+/// Downloads the release's preset table, checks that it parses, and installs it at
+/// `presets_path`. It's only used with `--presets-file`, so that updating doesn't change
+/// results without being asked to.
+fn install_presets(url: &str, checksum: &str) -> Result<String, String> {
+    let path = presets_path().ok_or("Could not find a directory to install the presets in, as HOME isn't set")?;
+    let dir = path.parent().expect("The presets path is in a directory");
+    fs::create_dir_all(dir).map_err(|err| format!("Could not create {}: {}", dir.display(), err))?;
+    let download = path.with_extension("download");
+    download_to(url, &download)?;
+    let path_text = path.display().to_string();
+    if let Err(error_message) = verify(&download, checksum) {
+        let _ = fs::remove_file(&download);
+        return Err(error_message);
+    }
+    if let Err(error_message) = presets::load(&download.display().to_string()) {
+        let _ = fs::remove_file(&download);
+        return Err(format!("The release's presets are invalid, so they weren't installed: {}", error_message));
+    }
+    fs::rename(&download, &path).map_err(|err| format!("Could not install the presets at {}: {}", path_text, err))?;
+    Ok(format!("Installed the release's presets at {}. Match against them with `--presets-file {}`, or `presets-file` in the configuration file\n", path_text, path_text))
+}
+
+#[test]
+fn test_update() {
+    let release = parse_release(r#"{"tag_name": "v0.2.0", "assets": [{"name": "presets.txt", "browser_download_url": "https://example.com/presets.txt"}]}"#).unwrap();
+    assert_eq!(release, Release { version: "0.2.0".to_string(), assets: vec![("presets.txt".to_string(), "https://example.com/presets.txt".to_string())] });
+    assert_eq!(asset_url(&release, PRESETS_ASSET), Some("https://example.com/presets.txt"));
+    assert!(is_newer("0.10.0", "0.9.1"));
+    assert!(!is_newer("0.1.0", "0.1.0"));
+    assert!(is_newer("0.2.0-rc.1", "0.1.0") && is_newer("0.2.0", "0.2.0-rc.1") && !is_newer("0.2.0-rc.1", "0.2.0"));
+    assert!(!is_newer("latest", "0.1.0"));
+    assert!(parse_release("{}").is_err());
+    let empty = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855";
+    assert_eq!(parse_checksum(&format!("{}  presets.txt\n", empty)), Some(empty.to_lowercase()));
+    assert_eq!(parse_checksum("e3b0c442  presets.txt"), None);
+    let path = env::temp_dir().join(format!("determine-preset-test-update-{}", std::process::id()));
+    fs::write(&path, "").unwrap();
+    let (matching, other) = (verify(&path, &empty.to_lowercase()), verify(&path, &"0".repeat(64)));
+    fs::remove_file(&path).unwrap();
+    assert_eq!(matching, Ok(()));
+    assert!(other.is_err());
+}
@@ -0,0 +1,118 @@
+use tracing::info;
+
+use crate::{lines, params};
+
+/// Whether a line starts a stream in VLC's codec information, like `Stream 0`.
+pub fn is_stream_header(line: &str) -> bool {
+    line.trim().strip_prefix("Stream ").is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Whether text was copied from VLC's "Codec Information" dialog: streams like `Stream 0`, with
+/// fields like `Codec: MPEG-H Part2/HEVC (H.265) (hevc)`.
+pub fn is_vlc(text: &str) -> bool {
+    lines(text).any(is_stream_header) && lines(text).any(|line| line.trim_start().starts_with("Codec: "))
+}
+
+/// Rewrites VLC's codec information as the `mediainfo` fields it has, so that it's read like
+/// `mediainfo` output. `None` if the text isn't from VLC.
+///
+/// VLC shows the video's format, size, frame rate and color, and the metadata of the container,
+/// but not the encoder settings, which only tags written by some tools have. Any `key=value`
+/// settings of x265 parameters in the tags are kept, so the presets can still be ranked by them.
+pub fn to_mediainfo(text: &str) -> Option<String> {
+    if !is_vlc(text) {
+        return None;
+    }
+    let mut metadata = Vec::new();
+    let mut streams: Vec<Vec<(&str, &str)>> = Vec::new();
+    for line in lines(text) {
+        if is_stream_header(line) {
+            streams.push(Vec::new());
+            continue;
+        }
+        let Some((field, value)) = line.trim().split_once(": ") else { continue };
+        match streams.last_mut() {
+            Some(stream) => stream.push((field, value.trim())),
+            None => metadata.push((field, value.trim())),
+        }
+    }
+    let video = streams.iter().find(|stream| stream.iter().any(|(field, value)| *field == "Type" && *value == "Video")).cloned().unwrap_or_default();
+
+    let mut general = vec!["General".to_string()];
+    let mut settings = Vec::new();
+    for (field, value) in &metadata {
+        let mediainfo_field = match field.to_lowercase().as_str() {
+            "encoded by" => Some("Encoded by"),
+            "encoder" => Some("Writing application"),
+            "comment" => Some("Comment"),
+            "description" => Some("Description"),
+            name if name.contains("preset") => Some(*field),
+            _ => None,
+        };
+        if let Some(mediainfo_field) = mediainfo_field {
+            general.push(format!("{} : {}", mediainfo_field, value));
+        }
+        settings.extend(value.split([' ', '/']).filter(|pair| pair.split_once('=').is_some_and(|(name, _)| params::find(name).is_some())));
+    }
+
+    let mut fields = vec!["Video".to_string()];
+    for (field, value) in &video {
+        match *field {
+            "Codec" if value.contains("HEVC") => fields.push("Format : HEVC".to_string()),
+            "Video resolution" => {
+                if let Some((width, height)) = value.split_once('x') {
+                    fields.push(format!("Width : {} pixels", width.trim()));
+                    fields.push(format!("Height : {} pixels", height.trim()));
+                }
+            }
+            "Frame rate" => fields.push(format!("Frame rate : {} FPS", value)),
+            // e.g. `Planar 4:2:0 YUV 10-bit LE`
+            "Decoded format" => {
+                let words = value.split_whitespace().collect::<Vec<_>>();
+                if let Some(bit_depth) = words.iter().find_map(|word| word.strip_suffix("-bit")) {
+                    fields.push(format!("Bit depth : {} bits", bit_depth));
+                }
+                if let Some(chroma) = words.iter().find(|word| word.starts_with("4:")) {
+                    fields.push(format!("Chroma subsampling : {}", chroma));
+                }
+            }
+            "Color primaries" => fields.extend(lookup(PRIMARIES, value).map(|primaries| format!("Color primaries : {}", primaries))),
+            "Color transfer function" => fields.extend(lookup(TRANSFERS, value).map(|transfer| format!("Transfer characteristics : {}", transfer))),
+            "Color space" => fields.extend(lookup(MATRICES, value).map(|matrix| format!("Matrix coefficients : {}", matrix))),
+            _ => {}
+        }
+    }
+    if settings.is_empty() {
+        info!("VLC's codec information doesn't have the encoder settings, so the preset can only be determined from a video's tags. Pass the video itself to read its settings with mediainfo");
+    } else {
+        fields.push(format!("Encoding settings : {}", settings.join(" / ")));
+    }
+    Some(format!("{}\n\n{}\n", general.join("\n"), fields.join("\n")))
+}
+
+/// The `mediainfo` names of VLC's color primaries, by a part of VLC's name, like
+/// `ITU-R BT.2020`.
+const PRIMARIES: &[(&str, &str)] = &[("2020", "BT.2020"), ("709", "BT.709"), ("625", "BT.601 PAL"), ("525", "BT.601 NTSC"), ("P3", "Display P3")];
+
+/// The transfer functions, like `PRIMARIES`, from names like `SMPTE ST2084 (PQ)`.
+const TRANSFERS: &[(&str, &str)] = &[("2084", "PQ"), ("HLG", "HLG"), ("B67", "HLG"), ("sRGB", "sRGB"), ("709", "BT.709"), ("601", "BT.601"), ("170M", "BT.601")];
+
+/// The color spaces (matrix coefficients), like `PRIMARIES`, from names like
+/// `ITU-R BT.2020 Range`. BT.601 isn't mapped, as VLC doesn't say whether it's PAL or NTSC.
+const MATRICES: &[(&str, &str)] = &[("2020", "BT.2020 non-constant"), ("709", "BT.709")];
+
+fn lookup(table: &[(&str, &'static str)], value: &str) -> Option<&'static str> {
+    table.iter().find(|(part, _)| value.contains(part)).map(|(_, name)| *name)
+}
+
+#[test]
+fn test_vlc() {
+    let text = "Encoded by: HandBrake 1.6.1\nSettings: ctu=64 / bframes=4 / crf=20\nStream 0\n    Codec: MPEG-H Part2/HEVC (H.265) (hevc)\n    Type: Video\n    Video resolution: 3840x2160\n    Frame rate: 23.976024\n    Decoded format: Planar 4:2:0 YUV 10-bit LE\n    Color primaries: ITU-R BT.2020\n    Color transfer function: SMPTE ST2084 (PQ)\n    Color space: ITU-R BT.2020 Range\nStream 1\n    Codec: A52 Audio (aka AC3) (a52)\n    Type: Audio\n";
+    let mediainfo = to_mediainfo(text).unwrap();
+    assert!(mediainfo.starts_with("General\nEncoded by : HandBrake 1.6.1\n\nVideo\nFormat : HEVC\nWidth : 3840 pixels\nHeight : 2160 pixels\n"));
+    assert!(mediainfo.contains("Bit depth : 10 bits\nChroma subsampling : 4:2:0\nColor primaries : BT.2020\nTransfer characteristics : PQ\nMatrix coefficients : BT.2020 non-constant\n"));
+    assert!(mediainfo.ends_with("Encoding settings : ctu=64 / bframes=4 / crf=20\n"));
+    assert_eq!(crate::parse_settings(&mediainfo).get("ctu").map(String::as_str), Some("64"));
+    assert!(crate::handbrake::is_handbrake(&mediainfo));
+    assert_eq!(to_mediainfo("General\nComplete name : a.mkv\n"), None);
+}
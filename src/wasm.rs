@@ -0,0 +1,10 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{matcher, presets};
+
+/// Determines the preset of pasted `mediainfo` output, x265 settings or an encoder command line,
+/// for web pages. If there is no single match, the error message is thrown.
+#[wasm_bindgen(js_name = determinePreset)]
+pub fn determine_preset(settings: &str) -> Result<String, String> {
+    matcher::determine(settings, &presets::x265())
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use crate::{matcher::ClosestMatch, params, presets::Preset, Determination};
+
+/// The most parameters that can differ from the closest preset for a result to be a near miss,
+/// which the wizard is offered for.
+pub const NEAR_MISS: usize = 3;
+
+/// A parameter that the candidate presets disagree on, or that the input disagrees with them on.
+#[derive(Debug, PartialEq)]
+pub struct Question {
+    pub param_name: String,
+    pub input_value: Option<String>,
+    /// Each candidate's value, if it sets the parameter.
+    pub preset_values: Vec<(String, Option<String>)>,
+}
+
+/// The presets to walk through the differences with: the matching presets of an ambiguous
+/// result, or the closest presets of a near miss. Empty for the other results.
+pub fn candidates<'a>(determination: &Determination, presets: &'a [Preset], closest: &[ClosestMatch]) -> Vec<&'a Preset> {
+    let find = |preset_name: &str| presets.iter().find(|(name, _)| name == preset_name);
+    match determination {
+        Determination::Ambiguous(matching_presets) => matching_presets.iter().filter_map(|preset_name| find(preset_name)).collect(),
+        Determination::NoMatch => {
+            let best = closest.first().map_or(0, |closest| closest.matched);
+            closest.iter()
+                .take_while(|closest| closest.matched == best)
+                .filter(|closest| closest.differences.len() <= NEAR_MISS)
+                .filter_map(|closest| find(&closest.preset))
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The parameters to ask about, in the order of the preset table: those that some candidate
+/// sets to another value than the input, and those missing from the input that the candidates
+/// disagree on.
+pub fn questions(settings: &HashMap<String, String>, candidates: &[&Preset]) -> Vec<Question> {
+    params::PARAMS.iter()
+        .filter_map(|param| {
+            let input_value = settings.get(param.name);
+            let preset_values = candidates.iter().map(|(preset_name, preset_settings)| (preset_name.clone(), preset_settings.get(param.name).cloned())).collect::<Vec<_>>();
+            let differs = match input_value {
+                Some(input_value) => preset_values.iter().any(|(_, value)| value.as_ref().is_some_and(|value| value != input_value)),
+                None => preset_values.iter().any(|(_, value)| *value != preset_values[0].1),
+            };
+            differs.then(|| Question { param_name: param.name.to_string(), input_value: input_value.cloned(), preset_values })
+        })
+        .collect()
+}
+
+/// The answers to the questions.
+#[derive(Debug, Default, PartialEq)]
+pub struct Answers {
+    /// The parameters of the input not to trust, which are left out of its settings.
+    pub ignored: Vec<String>,
+    /// The values of parameters missing from the input, that the user knows.
+    pub known: Vec<(String, String)>,
+}
+
+impl Answers {
+    /// The settings refined by the answers.
+    pub fn apply(&self, settings: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut settings = settings.clone();
+        for param_name in &self.ignored {
+            settings.remove(param_name);
+        }
+        settings.extend(self.known.iter().cloned());
+        settings
+    }
+}
+
+/// Asks each question on `output`, reading the answers from `input`. A parameter of the input
+/// is trusted unless it's answered with `i`, and a missing parameter is left missing unless
+/// its value is entered. The questions stop at the end of the input.
+pub fn ask(questions: &[Question], mut input: impl BufRead, mut output: impl Write) -> io::Result<Answers> {
+    let mut answers = Answers::default();
+    for question in questions {
+        let preset_values = question.preset_values.iter()
+            .map(|(preset_name, value)| format!("{} has {}", preset_name, value.as_deref().unwrap_or("no value")))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match &question.input_value {
+            Some(input_value) => write!(output, "{}: the input has {}; {}. [T]rust it, or [i]gnore it? ", question.param_name, input_value, preset_values)?,
+            None => write!(output, "{}: the input doesn't record it; {}. Its value, if you know it (Enter to skip): ", question.param_name, preset_values)?,
+        }
+        output.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            writeln!(output)?;
+            break;
+        }
+        let answer = answer.trim();
+        match &question.input_value {
+            Some(_) if answer.eq_ignore_ascii_case("i") || answer.eq_ignore_ascii_case("ignore") => answers.ignored.push(question.param_name.clone()),
+            None if !answer.is_empty() => answers.known.push((question.param_name.clone(), answer.to_string())),
+            _ => {}
+        }
+    }
+    Ok(answers)
+}
+
+#[test]
+fn test_wizard() {
+    let presets = crate::presets::parse("slow ctu=64 rdLevel=4 bframes=4\nslower ctu=64 rdLevel=6 bframes=8\n").unwrap();
+    let settings = crate::parse_string("ctu=64 bframes=3");
+    let closest = crate::matcher::closest_match_details(&settings, &presets);
+    let candidates = candidates(&Determination::NoMatch, &presets, &closest);
+    assert_eq!(candidates.len(), 2);
+    let questions = questions(&settings, &candidates);
+    let names = questions.iter().map(|question| question.param_name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["bframes", "rdLevel"]);
+    let mut output = Vec::new();
+    let answers = ask(&questions, "i\n6\n".as_bytes(), &mut output).unwrap();
+    assert_eq!(answers, Answers { ignored: vec!["bframes".to_string()], known: vec![("rdLevel".to_string(), "6".to_string())] });
+    assert_eq!(answers.apply(&settings), crate::parse_string("ctu=64 rdLevel=6"));
+    assert!(String::from_utf8(output).unwrap().starts_with("bframes: the input has 3; slow has 4, slower has 8. [T]rust it, or [i]gnore it? "));
+}
@@ -0,0 +1,60 @@
+/// The encoder name of x264, whose settings are read like x265's, but aren't matched against
+/// the x265 presets: its parameters like `subme`, `weightp` and `deblock` mean different things.
+pub const ENCODER: &str = "x264";
+
+/// The settings after `options:`, up to the end of the line, as x264 writes them into its
+/// version string (`x264 - core 164 r3095 baee400 - H.264/MPEG-4 AVC codec - ... - options:
+/// cabac=1 ref=3 ...`), or as they are given joined with colons, like `options:
+/// cabac=1:ref=3`. `None` if the text has no `options:`.
+pub fn options(text: &str) -> Option<&str> {
+    let start = text.match_indices("options:").map(|(start, _)| start).find(|start| text[..*start].chars().next_back().is_none_or(char::is_whitespace))?;
+    let options = &text[start + "options:".len()..];
+    Some(options.split(['\n', '\r']).next().unwrap_or_default().trim())
+}
+
+/// The `key=value` pairs of a token, which can be several joined with colons, like
+/// `cabac=1:ref=3:deblock=1:0:0`. A colon only starts another pair if an `=` follows it, as
+/// values like `deblock=1:0:0`, `analyse=0x3:0x113` and x265's `deblock=0:0` have colons too.
+pub fn pairs(token: &str) -> Vec<(&str, &str)> {
+    let mut starts = vec![0];
+    let mut offset = 0;
+    for piece in token.split(':') {
+        if offset > 0 && piece.contains('=') {
+            starts.push(offset);
+        }
+        offset += piece.len() + 1;
+    }
+    starts.iter().enumerate().filter_map(|(index, start)| {
+        let end = starts.get(index + 1).map_or(token.len(), |next| next - 1);
+        let mut parts = token[*start..end].split('=');
+        Some((parts.next()?, parts.next()?))
+    }).collect()
+}
+
+/// Whether the text has the version string that x264 writes into the video, like `x264 - core
+/// 164`, which names the encoder of settings that aren't in `mediainfo` output.
+pub fn is_x264(text: &str) -> bool {
+    text.contains("x264 - core ")
+}
+
+#[test]
+fn test_x264() {
+    assert_eq!(pairs("cabac=1:ref=3:deblock=1:0:0:analyse=0x3:0x113"), [("cabac", "1"), ("ref", "3"), ("deblock", "1:0:0"), ("analyse", "0x3:0x113")]);
+    assert_eq!(pairs("deblock=0:0"), [("deblock", "0:0")]);
+    assert_eq!(pairs("no-pairs"), []);
+    let sei = "x264 - core 164 r3095 baee400 - H.264/MPEG-4 AVC codec - Copyleft 2003-2022 - http://www.videolan.org/x264.html - options: cabac=1 ref=3 deblock=1:0:0 psy_rd=1.00:0.00\n";
+    assert_eq!(options(sei), Some("cabac=1 ref=3 deblock=1:0:0 psy_rd=1.00:0.00"));
+    assert!(is_x264(sei));
+    assert_eq!(options("x264-options:ref=3"), None);
+    let settings = crate::parse_settings(sei);
+    assert_eq!((settings["deblock"].as_str(), settings["psy_rd"].as_str(), settings.len()), ("1:0:0", "1.00:0.00", 4));
+    let settings = crate::parse_settings("options: cabac=1:ref=3:deblock=1:0:0:me=hex:subme=7");
+    assert_eq!((settings["ref"].as_str(), settings["deblock"].as_str(), settings["me"].as_str(), settings.len()), ("3", "1:0:0", "hex", 5));
+    let mediainfo = "Video\nFormat : AVC\nWriting library : x264 core 164 r3095 baee400\nEncoding settings : cabac=1 / ref=3 / deblock=1:0:0 / analyse=0x3:0x113 / me=hex / psy_rd=1.00:0.00 / 8x8dct=1\n";
+    let settings = crate::parse_settings(mediainfo);
+    assert_eq!((settings["analyse"].as_str(), settings["8x8dct"].as_str(), settings.len()), ("0x3:0x113", "1", 7));
+    assert_eq!(crate::encoder_name(sei), "x264");
+    assert_eq!(crate::Determination::NoMatch.for_input(sei), crate::Determination::NotDerivable { encoder: ENCODER.to_string() });
+    assert_eq!(crate::Determination::NotDerivable { encoder: ENCODER.to_string() }.exit_code(), crate::exit_code::NOT_DERIVABLE);
+    assert_eq!(crate::raw_settings(sei).as_deref(), options(sei));
+}